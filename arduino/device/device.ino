@@ -1,9 +1,13 @@
 #include <FastLED.h>
 
 #define LED_PIN 6
-#define NUM_LEDS 12*22
+// Must match the strips x LEDs per strip geometry of the Pi side. Rebuild for any other panel,
+// e.g. with -DNUM_LEDS=512 in the compiler flags.
+#ifndef NUM_LEDS
+#define NUM_LEDS (12*22)
+#endif
 #define LED_TYPE WS2812B
-#define REC_BUFFER_SIZE NUM_LEDS*3+1
+#define REC_BUFFER_SIZE (NUM_LEDS*3+1)
 #define UPDATES_PER_SECOND 60
 #define endMarker 0xFF
 
@@ -29,6 +33,11 @@ void loop()
   if (Serial.available() > 0)
   {
     byte dataIn = Serial.read();
+    if (receptionIndex >= REC_BUFFER_SIZE)
+    {
+      // Frame longer than the panel: the geometries differ, drop it
+      receptionIndex = 0;
+    }
     receivedInts[receptionIndex++] = dataIn;
     digitalWrite(LED_PIN, HIGH);
     if (dataIn == endMarker)
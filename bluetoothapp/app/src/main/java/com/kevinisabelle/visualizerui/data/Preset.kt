@@ -18,7 +18,9 @@ data class Preset(
     val skew: Float,
     val brightness: Float,
     val displayMode: DisplayMode,
-    val animationMode: AnimationMode
+    val animationMode: AnimationMode,
+    // Trailing `key=value` fields the app does not edit (e.g. peak_hold_ms), kept as-is
+    val extensions: List<String> = emptyList()
 ) {
     companion object {
         fun fromSettings(settings: Settings?) : Preset {
@@ -146,12 +148,13 @@ fun encodePresetCsv(preset: Preset): String {
             "${preset.skew}," +
             "${preset.brightness}," +
             "${preset.displayMode.code}," +
-            "${preset.animationMode.code}"
+            "${preset.animationMode.code}" +
+            preset.extensions.joinToString("") { ",$it" }
 }
 
 fun decodePresetCsv(csv: String): Preset {
     val parts = csv.split(',')
-    require(parts.size == 15) { "Invalid preset CSV format" }
+    require(parts.size >= 15) { "Invalid preset CSV format" }
 
     val index = parts[0].toUByte()
     val name = parts[1].trim()
@@ -194,6 +197,7 @@ fun decodePresetCsv(csv: String): Preset {
         skew,
         brightness,
         displayMode,
-        animationMode
+        animationMode,
+        parts.drop(15)
     )
 }
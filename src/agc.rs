@@ -0,0 +1,108 @@
+//! Automatic gain control.
+//!
+//! A fixed `gain` only suits one volume: a quiet room shows nothing and a party fills every
+//! strip. With AGC on, the DSP thread follows the loudest band of each spectrum with a peak
+//! envelope (fast attack, slow release) and picks the gain that puts that envelope at
+//! `target` of the strip height. The renderer then uses this gain instead of `Settings::gain`;
+//! the per-band gains still apply on top.
+
+use crate::constants::{
+    DEFAULT_AGC_ATTACK_MS, DEFAULT_AGC_MAX_GAIN, DEFAULT_AGC_MIN_GAIN, DEFAULT_AGC_RELEASE_MS, DEFAULT_AGC_TARGET,
+};
+use crate::values::Envelope;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgcSettings {
+    pub enabled: bool,
+    /// Strip height (0..1) the loudness envelope is brought to.
+    pub target: f32,
+    /// Time constant (ms) of the envelope when the music gets louder, i.e. how fast the gain drops.
+    pub attack_ms: usize,
+    /// Time constant (ms) when it gets quieter, i.e. how fast the gain recovers.
+    pub release_ms: usize,
+    pub min_gain: f32,
+    /// Caps how much silence and hiss get amplified.
+    pub max_gain: f32,
+}
+
+impl Default for AgcSettings {
+    fn default() -> Self {
+        AgcSettings {
+            enabled: false,
+            target: DEFAULT_AGC_TARGET,
+            attack_ms: DEFAULT_AGC_ATTACK_MS,
+            release_ms: DEFAULT_AGC_RELEASE_MS,
+            min_gain: DEFAULT_AGC_MIN_GAIN,
+            max_gain: DEFAULT_AGC_MAX_GAIN,
+        }
+    }
+}
+
+/// Loudness envelope, updated once per spectrum on the DSP thread.
+#[derive(Debug, Clone, Default)]
+pub struct AutoGain {
+    envelope: Envelope,
+}
+
+impl AutoGain {
+    /// Feed the loudest band of a spectrum (per-band gains applied, global gain not),
+    /// `elapsed` seconds after the previous one. Returns the new gain.
+    pub fn update(&mut self, loudness: f32, elapsed: f32, settings: &AgcSettings) -> f32 {
+        self.envelope.update(loudness, elapsed, settings.attack_ms as f32, settings.release_ms as f32);
+        self.gain(settings)
+    }
+
+    pub fn gain(&self, settings: &AgcSettings) -> f32 {
+        let envelope = self.envelope.value();
+        if envelope > 0.0 {
+            (settings.target / envelope).clamp(settings.min_gain, settings.max_gain)
+        } else {
+            settings.max_gain
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agc() -> AgcSettings {
+        AgcSettings { enabled: true, target: 0.8, attack_ms: 50, release_ms: 2000, min_gain: 1.0, max_gain: 40.0 }
+    }
+
+    /// Feed `loudness` for `seconds` at 100 spectra per second.
+    fn run(auto_gain: &mut AutoGain, loudness: f32, seconds: f32) -> f32 {
+        let mut gain = 0.0;
+        for _ in 0..(seconds * 100.0) as usize {
+            gain = auto_gain.update(loudness, 0.01, &agc());
+        }
+        gain
+    }
+
+    #[test]
+    fn settles_on_the_gain_that_reaches_the_target() {
+        let mut auto_gain = AutoGain::default();
+        let gain = run(&mut auto_gain, 0.1, 20.0);
+        assert!((gain - 8.0).abs() < 0.01, "{}", gain);
+    }
+
+    #[test]
+    fn drops_fast_on_loud_passages_and_recovers_slowly() {
+        let mut auto_gain = AutoGain::default();
+        run(&mut auto_gain, 0.05, 20.0);
+        // 0.25 s of a 10x louder passage: already close to the new gain.
+        let loud = run(&mut auto_gain, 0.5, 0.25);
+        assert!(loud < 2.0, "{}", loud);
+        // 0.25 s back at the old level: still far from the old gain of 16.
+        let quiet = run(&mut auto_gain, 0.05, 0.25);
+        assert!(quiet < 3.0, "{}", quiet);
+    }
+
+    #[test]
+    fn gain_stays_within_its_bounds() {
+        let mut auto_gain = AutoGain::default();
+        assert_eq!(auto_gain.gain(&agc()), 40.0);
+        assert_eq!(run(&mut auto_gain, 0.0001, 20.0), 40.0);
+        assert_eq!(run(&mut auto_gain, 10.0, 5.0), 1.0);
+    }
+}
@@ -1,61 +1,263 @@
-﻿use crate::color::{Color, BLACK};
-use crate::constants::{END_MARKER, LEDS_PER_STRIP, NUM_LEDS};
-use crate::settings::{AnimationMode, DisplayMode, Settings};
-use crate::values::{SamplesWindow, StateValues};
+﻿use crate::calibration::{apply_calibration, save_calibration};
+use crate::chroma::PITCH_CLASSES;
+use crate::color::{Color, BLACK};
+use crate::constants::{END_MARKER, OSCILLOSCOPE_WINDOW_MS, OVERRUN_REPORT_MS};
+use crate::dsp::db_scale;
+use crate::geometry::LedGeometry;
+use crate::settings::{resample_bands, AnimationMode, BeatEffect, ColorModulation, DisplayMode, Settings};
+use crate::pipeline::Pipeline;
+use crate::sinks::LedSink;
+use crate::strobe::{flash_color, Strobe};
+use crate::values::{Analysis, PeakHold};
+use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex};
-use std::thread::sleep;
-use std::time::Duration;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
 
-pub fn animate_leds(state_values: &Arc<Mutex<StateValues>>, settings_arc: &Arc<Mutex<Settings>>, port: &mut dyn serialport::SerialPort) {
+/// `BeatEffect::Flash`: share of the beat the flash lasts, and how far it washes towards `color3`.
+const FLASH_LENGTH: f32 = 0.25;
+const FLASH_LEVEL: f32 = 0.6;
+/// `BeatEffect::Pulse`: how far the levels sink by the end of a beat.
+const PULSE_DEPTH: f32 = 0.5;
+/// `ColorModulation::Hue`: how far round the colour wheel the palette turns at the top of the feature range.
+const MODULATION_HUE_RANGE: f32 = 180.0;
+/// `DisplayMode::Chroma`: brightness of the notes outside the current chord.
+const OUT_OF_CHORD_LEVEL: f32 = 0.25;
 
-    let frequency_levels = state_values.lock().unwrap().frequencies.clone();
-    let settings = settings_arc.lock().unwrap().clone();
-    let frame_delay = Duration::from_millis(1_000 / settings.fps as u64);
+/// What the render loop keeps from one frame to the next.
+#[derive(Debug, Default)]
+pub struct RenderState {
+    /// Markers of the *WithMax modes, one per strip.
+    pub peaks: Vec<PeakHold>,
+    pub strobe: Strobe,
+    /// Last calibration run whose gains were applied.
+    pub calibrated_run: u32,
+    /// `Pipeline::overruns` as of the last report, and when it was checked.
+    pub overruns_reported: usize,
+    pub overruns_checked: Option<Instant>,
+}
 
-    let mut buf = Vec::with_capacity(NUM_LEDS * 3 + 1);
-    let nb_frequency_levels = settings_arc.lock().unwrap().frequencies.len();
-    let sample_to_average = settings_arc.lock().unwrap().smooth_size;
+/// Render one frame from the latest analysis.
+pub fn animate_leds(pipeline: &Pipeline, settings_arc: &Arc<Mutex<Settings>>, state: &mut RenderState, sink: &mut dyn LedSink) {
 
-    if (settings.display_mode == DisplayMode::Oscilloscope){
+    // The only place the BLE-side settings are read; the DSP thread gets this snapshot.
+    let settings = Arc::new(settings_arc.lock().unwrap().clone());
+    pipeline.publish_settings(settings.clone());
+    let analysis = pipeline.analysis.load_full();
+    let beat = analysis.beat;
+    let settings = match settings.beat_effect {
+        BeatEffect::Advance => Arc::new(rotate_colors(&settings, beat.count)),
+        _ => settings,
+    };
+    let settings = match settings.color_modulation {
+        ColorModulation::Off => settings,
+        _ => Arc::new(modulate_colors(&settings, analysis.features.value(settings.modulation_feature))),
+    };
+    let frame_delay = Duration::from_millis(1_000 / settings.fps as u64);
 
+    let geometry = &settings.geometry;
+    let mut buf = vec![0; geometry.frame_len()];
+
+    if settings.display_mode == DisplayMode::Oscilloscope {
+        let columns = oscilloscope_columns(&analysis.samples, geometry.strips, settings.sample_rate);
+        for (strip, amplitude) in columns.into_iter().enumerate() {
+            // Per-band gains do not apply to time slices, only the global gain.
+            let mut strip_colors = get_strip_colors(amplitude * settings.gain, 0.0, &settings, strip);
+            beat_flash(&mut strip_colors, &settings, beat.phase);
+            output_colors_to_buffer(&mut buf, &strip_colors, strip, geometry);
+        }
+    } else if settings.display_mode == DisplayMode::Chroma {
+        let chroma = &analysis.chroma;
+        let triad = chroma.chord.map(|chord| chord.triad());
+        for strip in 0..geometry.strips {
+            let pitch_class = strip_pitch_class(strip, geometry.strips);
+            let mut strip_colors = get_strip_colors(chroma.classes[pitch_class], 0.0, &settings, strip);
+            if triad.is_some_and(|triad| !triad.contains(&pitch_class)) {
+                for color in strip_colors.iter_mut() {
+                    *color = color.brightness(OUT_OF_CHORD_LEVEL);
+                }
+            }
+            beat_flash(&mut strip_colors, &settings, beat.phase);
+            output_colors_to_buffer(&mut buf, &strip_colors, strip, geometry);
+        }
     } else {
-        for freq in 0..nb_frequency_levels {
-            let level = frequency_levels[freq].average(sample_to_average);
-            let max = frequency_levels[freq].max(sample_to_average);
-            let strip_colors = get_strip_colors(level, max, &settings.clone(), freq);
-            output_colors_to_buffer(&mut buf, &strip_colors, freq);
+        let now = Instant::now();
+        let peak_hold = Duration::from_millis(settings.peak_hold_ms as u64);
+        let peaks = &mut state.peaks;
+        peaks.resize(geometry.strips, PeakHold::default());
+        let flash = state.strobe.update(beat.kicks, now, &settings.strobe);
+        let gain = if settings.agc.enabled { analysis.agc_gain } else { settings.gain };
+        for (strip, (level, max, band_gain)) in strip_bands(&analysis, &settings).into_iter().enumerate() {
+            let mut level = level * gain * band_gain;
+            let mut max = max * gain * band_gain;
+            if settings.db_scale {
+                level = db_scale(level, settings.db_floor, settings.db_ceiling);
+                max = db_scale(max, settings.db_floor, settings.db_ceiling);
+            }
+            if settings.beat_effect == BeatEffect::Pulse {
+                level *= 1.0 - PULSE_DEPTH * beat.phase;
+            }
+            let peak = match peaks.get_mut(strip) {
+                Some(peak_hold_state) => peak_hold_state.update(max, now, peak_hold, settings.peak_decay),
+                None => max,
+            };
+            let mut strip_colors = get_strip_colors(level, peak, &settings, strip);
+            beat_flash(&mut strip_colors, &settings, beat.phase);
+            strobe_flash(&mut strip_colors, &settings, flash, level);
+            output_colors_to_buffer(&mut buf, &strip_colors, strip, geometry);
         }
     }
 
-    buf.push(END_MARKER);
-    settings_arc.lock().unwrap().led_buffer[..buf.len()].copy_from_slice(&buf);
+    buf[geometry.frame_len() - 1] = END_MARKER;
+    let mut calibrated = None;
+    {
+        let mut shared_settings = settings_arc.lock().unwrap();
+        shared_settings.led_buffer.clone_from(&buf);
+        shared_settings.agc_gain = analysis.agc_gain;
+        shared_settings.bpm = beat.bpm;
+        shared_settings.beat_count = beat.count;
+        shared_settings.chord = analysis.chroma.chord;
+        shared_settings.key = analysis.chroma.key;
+        shared_settings.features = analysis.features;
+        shared_settings.stereo = analysis.stereo;
+        shared_settings.calibration_status = analysis.calibration.clone();
+        if let Some(result) = analysis.calibration.result.as_ref().filter(|result| result.run != state.calibrated_run) {
+            calibrated = apply_calibration(result, &mut shared_settings);
+            state.calibrated_run = result.run;
+        }
+    }
+    if let Some(preset) = calibrated {
+        // Off the render thread and the settings lock: the frame and BLE do not wait for the disk
+        thread::spawn(move || save_calibration(preset));
+    }
 
-    port.write_all(&buf).unwrap();
-    port.flush().unwrap();
+    if let Err(e) = sink.write_frame(&buf) {
+        eprintln!("LED output error: {}", e);
+    }
+    report_overruns(pipeline, state);
 
     sleep(frame_delay);
 }
 
-fn output_colors_to_buffer(buf: &mut Vec<u8>, colors: &Vec<Color>, index: usize) {
-    let is_reversed = index % 2 == 1;
+/// Log the audio buffers dropped since the last report, at most every `OVERRUN_REPORT_MS`.
+fn report_overruns(pipeline: &Pipeline, state: &mut RenderState) {
+    let now = Instant::now();
+    if state.overruns_checked.is_some_and(|checked| now - checked < Duration::from_millis(OVERRUN_REPORT_MS)) {
+        return;
+    }
+    state.overruns_checked = Some(now);
+    let overruns = pipeline.overruns.load(Ordering::Relaxed);
+    if overruns > state.overruns_reported {
+        eprintln!("Audio dropped: {} buffers did not fit in the queue, the DSP thread is falling behind", overruns - state.overruns_reported);
+        state.overruns_reported = overruns;
+    }
+}
 
-    let ordered_colors = if is_reversed {
-        colors.iter().rev().cloned().collect::<Vec<_>>()
-    } else {
-        colors.to_vec()
+/// Level, maximum and gain of the band on each strip.
+///
+/// With the split stereo display, the left channel fills the left half of the strips from the
+/// centre outwards and the right channel the right half, so the bass of both meets in the middle.
+/// With an odd number of strips the middle one stays dark.
+fn strip_bands(analysis: &Analysis, settings: &Settings) -> Vec<(f32, f32, f32)> {
+    let strips = settings.geometry.strips;
+    if !settings.stereo_split || analysis.channel_levels.len() < 2 {
+        return (0..strips)
+            .map(|strip| (
+                analysis.levels.get(strip).copied().unwrap_or(0.0),
+                analysis.maxima.get(strip).copied().unwrap_or(0.0),
+                settings.gains.get(strip).copied().unwrap_or(1.0),
+            ))
+            .collect();
+    }
+    let half = strips / 2;
+    let gains = resample_bands(&settings.gains, half, false);
+    let mut bands = vec![(0.0, 0.0, 1.0); strips];
+    for band in 0..half {
+        for (channel, strip) in [half - 1 - band, strips - half + band].into_iter().enumerate() {
+            bands[strip] = (
+                analysis.channel_levels[channel].get(band).copied().unwrap_or(0.0),
+                analysis.channel_maxima[channel].get(band).copied().unwrap_or(0.0),
+                gains.get(band).copied().unwrap_or(1.0),
+            );
+        }
+    }
+    bands
+}
+
+/// `settings` with `color1`, `color2` and `color3` rotated by `beats` steps.
+fn rotate_colors(settings: &Settings, beats: u32) -> Settings {
+    let mut rotated = settings.clone();
+    let colors = [&settings.color1, &settings.color2, &settings.color3];
+    let shift = beats as usize % 3;
+    rotated.color1 = colors[shift].clone();
+    rotated.color2 = colors[(shift + 1) % 3].clone();
+    rotated.color3 = colors[(shift + 2) % 3].clone();
+    rotated
+}
+
+/// `settings` with the palette following `feature` (0..1): turned around the colour wheel by up
+/// to `MODULATION_HUE_RANGE` degrees, or a single colour mixed from `color1` to `color2`.
+fn modulate_colors(settings: &Settings, feature: f32) -> Settings {
+    let mut modulated = settings.clone();
+    match settings.color_modulation {
+        ColorModulation::Off => {}
+        ColorModulation::Hue => {
+            let degrees = feature * MODULATION_HUE_RANGE;
+            modulated.color1 = settings.color1.rotate_hue(degrees);
+            modulated.color2 = settings.color2.rotate_hue(degrees);
+            modulated.color3 = settings.color3.rotate_hue(degrees);
+        }
+        ColorModulation::Mix => {
+            let color = settings.color1.mix(&settings.color2, feature);
+            modulated.color1 = color.clone();
+            modulated.color2 = color;
+        }
+    }
+    modulated
+}
+
+/// `BeatEffect::Flash`: wash the strip towards `color3` for the first `FLASH_LENGTH` of each beat.
+fn beat_flash(strip_colors: &mut [Color], settings: &Settings, phase: f32) {
+    if settings.beat_effect != BeatEffect::Flash {
+        return;
+    }
+    let amount = FLASH_LEVEL * (1.0 - phase / FLASH_LENGTH).max(0.0);
+    if amount > 0.0 {
+        let flash = settings.color3.brightness(settings.brightness);
+        for color in strip_colors.iter_mut() {
+            *color = color.mix(&flash, amount);
+        }
+    }
+}
+
+/// Strobe modes: wash the strip towards `color3` by `flash`, all of it or as far as `level` reaches.
+fn strobe_flash(strip_colors: &mut [Color], settings: &Settings, flash: f32, level: f32) {
+    if settings.display_mode != DisplayMode::Spectrum || flash <= 0.0 {
+        return;
+    }
+    let amount = match settings.animation_mode {
+        AnimationMode::Strobe => flash,
+        AnimationMode::StrobeStrips => flash * level.clamp(0.0, 1.0),
+        _ => return,
     };
+    let color = settings.color3.brightness(settings.brightness);
+    for led in strip_colors.iter_mut() {
+        *led = flash_color(led, &color, amount, &settings.strobe);
+    }
+}
 
-    for color in ordered_colors {
-        buf.extend_from_slice(&color.to_slice());
+fn output_colors_to_buffer(buf: &mut [u8], colors: &[Color], strip: usize, geometry: &LedGeometry) {
+    for (led, color) in colors.iter().enumerate() {
+        let offset = geometry.physical_index(strip, led) * 3;
+        buf[offset..offset + 3].copy_from_slice(&color.to_slice());
     }
 }
 
-fn get_strip_colors(level: f32, max: f32, settings: &Settings, index: usize) -> Vec<Color> {
+/// Colours of one strip. `level` and `peak` are already scaled by the gains: 1.0 = full strip.
+fn get_strip_colors(level: f32, peak: f32, settings: &Settings, index: usize) -> Vec<Color> {
 
-    let mut strip_colors = vec![BLACK; LEDS_PER_STRIP];
-    let freq_gain = settings.gains[index];
-    let level_adjusted = level * settings.gain * freq_gain;
-    let max_adjusted = max * settings.gain * freq_gain;
+    let leds_per_strip = settings.geometry.leds_per_strip;
+    let mut strip_colors = vec![BLACK; leds_per_strip];
 
     match settings.display_mode
     {
@@ -63,37 +265,43 @@ fn get_strip_colors(level: f32, max: f32, settings: &Settings, index: usize) ->
 
             match settings.animation_mode
             {
-                AnimationMode::Full =>
+                AnimationMode::Full | AnimationMode::Strobe | AnimationMode::StrobeStrips =>
                     {
-                        full_spectrum(level_adjusted, index, settings, &mut strip_colors);
+                        full_spectrum(level, index, settings, &mut strip_colors);
                     }
                 AnimationMode::FullWithMax =>
                     {
-                        full_spectrum_with_max(level_adjusted, max_adjusted, index, settings, &mut strip_colors);
+                        full_spectrum_with_max(level, peak, index, settings, &mut strip_colors);
                     }
                 AnimationMode::Points =>
                     {
-                        points_spectrum(level_adjusted, index, settings, &mut strip_colors);
+                        points_spectrum(level, index, settings, &mut strip_colors);
                     }
                 AnimationMode::FullMiddle =>
                     {
-                        spectrum_middle(level_adjusted, index, settings, &mut strip_colors);
+                        spectrum_middle(level, index, settings, &mut strip_colors);
                     }
                 AnimationMode::FullMiddleWithMax =>
                     {
-                        spectrum_middle_with_max(level_adjusted, max_adjusted, index, settings, &mut strip_colors);
+                        spectrum_middle_with_max(level, peak, index, settings, &mut strip_colors);
+                    }
+                AnimationMode::PointsMiddle =>
+                    {
+                        points_middle(level, settings, &mut strip_colors);
                     }
-                _ => {
-                    full_spectrum(level_adjusted, index, settings, &mut strip_colors);
-                }
             }
         }
         DisplayMode::Oscilloscope => {
-
+            oscilloscope_trace(level, settings, &mut strip_colors);
+        }
+        DisplayMode::Chroma => {
+            let pitch_class = strip_pitch_class(index, settings.geometry.strips);
+            let color = Color::from_hue(settings.chroma_hues[pitch_class]);
+            chroma_bar(level, &color, settings, &mut strip_colors);
         }
         DisplayMode::ColorGradient => {
-            for i in 0..LEDS_PER_STRIP {
-                let mix_factor = (i+1) as f32 / LEDS_PER_STRIP as f32;
+            for i in 0..leds_per_strip {
+                let mix_factor = (i+1) as f32 / leds_per_strip as f32;
                 let color = settings.color1.clone().mix(&settings.color2.clone(), mix_factor).brightness(settings.brightness.clone());
                 strip_colors[i] = color.clone();
             }
@@ -111,7 +319,8 @@ pub fn full_spectrum(
 ) {
     let color1 = settings_arc.color1.clone();
     let color2 = settings_arc.color2.clone();
-    let num_leds_to_light_float = (level * LEDS_PER_STRIP as f32).min(LEDS_PER_STRIP as f32);
+    let leds_per_strip = strip_colors.len();
+    let num_leds_to_light_float = (level * leds_per_strip as f32).min(leds_per_strip as f32);
     let num_leds_to_light = num_leds_to_light_float.ceil() as usize;
     let leftover_value = 1.0 - (num_leds_to_light as f32 - num_leds_to_light_float).max(0.0);
     for i in 0..num_leds_to_light {
@@ -137,7 +346,13 @@ pub fn full_spectrum_with_max(
     strip_colors: &mut Vec<Color>,
 ) {
     full_spectrum(level, index, settings_arc, strip_colors);
-    strip_colors[LEDS_PER_STRIP-1] = settings_arc.color3.clone().brightness(level.min(1.0)).brightness(settings_arc.brightness.clone());
+    if max <= 0.0 {
+        return;
+    }
+    // Peak marker at the height of the held / falling peak
+    let leds_per_strip = strip_colors.len();
+    let peak_led_index = ((max * leds_per_strip as f32) as usize).min(leds_per_strip - 1);
+    strip_colors[peak_led_index] = settings_arc.color3.brightness(settings_arc.brightness);
 }
 
 pub fn points_spectrum(
@@ -146,15 +361,16 @@ pub fn points_spectrum(
     settings_arc: &Settings,
     strip_colors: &mut Vec<Color>,
 ) {
-    let mut first_led_index = (level * LEDS_PER_STRIP as f32).floor() as usize;
-    let last_led_index = (level * LEDS_PER_STRIP as f32).ceil() as usize;
-    let factor = (level * LEDS_PER_STRIP as f32) - first_led_index as f32;
+    let leds_per_strip = strip_colors.len();
+    let mut first_led_index = (level * leds_per_strip as f32).floor() as usize;
+    let last_led_index = (level * leds_per_strip as f32).ceil() as usize;
+    let factor = (level * leds_per_strip as f32) - first_led_index as f32;
 
-    first_led_index = first_led_index.min(LEDS_PER_STRIP - 1);
+    first_led_index = first_led_index.min(leds_per_strip - 1);
     let color_to_use = settings_arc.color1.clone().mix(&settings_arc.color2.clone(), level);
 
     strip_colors[first_led_index] = color_to_use.brightness(1.0 - factor).brightness(settings_arc.brightness.clone());
-    if last_led_index < LEDS_PER_STRIP {
+    if last_led_index < leds_per_strip {
         strip_colors[last_led_index] = color_to_use.brightness(factor).brightness(settings_arc.brightness.clone());
     }
 }
@@ -167,7 +383,7 @@ pub fn spectrum_middle(
 ) {
     let color1 = settings_arc.color1.clone();
     let color2 = settings_arc.color2.clone();
-    let middle_index = LEDS_PER_STRIP / 2;
+    let middle_index = strip_colors.len() / 2;
 
     let num_leds_to_light_float = (level * middle_index as f32).min(middle_index as f32);
     let num_leds_to_light = num_leds_to_light_float.ceil() as usize;
@@ -200,6 +416,326 @@ pub fn spectrum_middle_with_max(
     strip_colors: &mut Vec<Color>,
 ) {
     spectrum_middle(level, index, settings_arc, strip_colors);
-    strip_colors[LEDS_PER_STRIP-1] = settings_arc.color3.clone().brightness(level.min(1.0)).brightness(settings_arc.brightness.clone());
-    strip_colors[0] = settings_arc.color3.clone().brightness(level.min(1.0)).brightness(settings_arc.brightness.clone());
-}
\ No newline at end of file
+    let middle_index = strip_colors.len() / 2;
+    if max <= 0.0 || middle_index == 0 {
+        return;
+    }
+    // Peak markers on both sides, at the distance of the held / falling peak from the middle
+    let peak_offset = ((max * middle_index as f32) as usize).min(middle_index - 1);
+    strip_colors[middle_index + peak_offset] = settings_arc.color3.brightness(settings_arc.brightness);
+    strip_colors[middle_index - 1 - peak_offset] = settings_arc.color3.brightness(settings_arc.brightness);
+}
+
+/// `points_spectrum` mirrored around the middle of the strip: two dots moving outwards with the level.
+pub fn points_middle(
+    level: f32,
+    settings_arc: &Settings,
+    strip_colors: &mut [Color],
+) {
+    let middle_index = strip_colors.len() / 2;
+    if middle_index == 0 {
+        return;
+    }
+    let position = level.max(0.0) * middle_index as f32;
+    let mut first_led_index = position.floor() as usize;
+    let last_led_index = position.ceil() as usize;
+    let factor = position - first_led_index as f32;
+
+    first_led_index = first_led_index.min(middle_index - 1);
+    let color_to_use = settings_arc.color1.mix(&settings_arc.color2, level);
+
+    let first_color = color_to_use.brightness(1.0 - factor).brightness(settings_arc.brightness);
+    strip_colors[middle_index + first_led_index] = first_color.clone();
+    strip_colors[middle_index - 1 - first_led_index] = first_color;
+    if last_led_index < middle_index && last_led_index != first_led_index {
+        let last_color = color_to_use.brightness(factor).brightness(settings_arc.brightness);
+        strip_colors[middle_index + last_led_index] = last_color.clone();
+        strip_colors[middle_index - 1 - last_led_index] = last_color;
+    }
+}
+
+/// Pitch class shown on `strip`: C to B from the first strip to the last. With fewer than 12
+/// strips some pitch classes are left out, with more some get several strips.
+fn strip_pitch_class(strip: usize, strips: usize) -> usize {
+    strip * PITCH_CLASSES / strips.max(1)
+}
+
+/// A bar of a single colour up to `level`, the top LED dimmed by how far into it the level reaches.
+pub fn chroma_bar(
+    level: f32,
+    color: &Color,
+    settings_arc: &Settings,
+    strip_colors: &mut [Color],
+) {
+    let position = (level.max(0.0) * strip_colors.len() as f32).min(strip_colors.len() as f32);
+    for (led, strip_color) in strip_colors.iter_mut().enumerate() {
+        let fill = (position - led as f32).clamp(0.0, 1.0);
+        if fill > 0.0 {
+            *strip_color = color.brightness(fill).brightness(settings_arc.brightness);
+        }
+    }
+}
+
+/// Fraction of the window peak the signal must drop below before a rising zero crossing
+/// can trigger again; keeps noise around zero from making the trace jitter.
+const TRIGGER_HYSTERESIS: f32 = 0.1;
+
+/// Reduce the newest `OSCILLOSCOPE_WINDOW_MS` of audio to one signed amplitude per column.
+///
+/// The window starts on the latest rising zero crossing that still leaves a full window after
+/// it, so a periodic signal is drawn at the same phase every frame instead of scrolling.
+/// Each column keeps the sample of largest magnitude in its time slice, so short peaks stay visible.
+pub fn oscilloscope_columns(samples: &[f32], columns: usize, sample_rate: u32) -> Vec<f32> {
+    let span = oscilloscope_span(sample_rate, columns);
+    if columns == 0 || samples.len() < span {
+        return vec![0.0; columns];
+    }
+
+    // Look for the trigger in the window just before the newest displayable one.
+    let latest_start = samples.len() - span;
+    let search_from = latest_start.saturating_sub(span);
+    let search = &samples[search_from..=latest_start];
+    let peak = search.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+    let threshold = peak * TRIGGER_HYSTERESIS;
+
+    let mut armed = false;
+    let mut start = latest_start;
+    for (i, &sample) in search.iter().enumerate() {
+        if sample < -threshold {
+            armed = true;
+        } else if armed && sample >= 0.0 {
+            start = search_from + i;
+            armed = false;
+        }
+    }
+
+    let window = &samples[start..start + span];
+    (0..columns)
+        .map(|column| {
+            let from = column * span / columns;
+            let to = ((column + 1) * span / columns).max(from + 1);
+            window[from..to].iter().fold(0.0f32, |acc, &s| if s.abs() > acc.abs() { s } else { acc })
+        })
+        .collect()
+}
+
+/// Samples in one oscilloscope window.
+fn oscilloscope_span(sample_rate: u32, columns: usize) -> usize {
+    ((sample_rate as f32 * OSCILLOSCOPE_WINDOW_MS / 1000.0) as usize).max(columns)
+}
+
+/// How many of the newest samples `oscilloscope_columns` looks at: the window plus the trigger search before it.
+pub fn oscilloscope_history(sample_rate: u32, columns: usize) -> usize {
+    2 * oscilloscope_span(sample_rate, columns) + 1
+}
+
+/// One dot per strip at the height of `amplitude` (-1 = bottom, 0 = middle, 1 = top),
+/// spread over the two nearest LEDs and coloured from color1 to color2 as it moves away from zero.
+pub fn oscilloscope_trace(
+    amplitude: f32,
+    settings_arc: &Settings,
+    strip_colors: &mut [Color],
+) {
+    let leds_per_strip = strip_colors.len();
+    let amplitude = amplitude.clamp(-1.0, 1.0);
+    let position = (amplitude + 1.0) / 2.0 * (leds_per_strip - 1) as f32;
+    let lower_led_index = position.floor() as usize;
+    let factor = position - lower_led_index as f32;
+
+    let color_to_use = settings_arc.color1.mix(&settings_arc.color2, amplitude.abs());
+
+    strip_colors[lower_led_index] = color_to_use.brightness(1.0 - factor).brightness(settings_arc.brightness);
+    if factor > 0.0 && lower_led_index + 1 < leds_per_strip {
+        strip_colors[lower_led_index + 1] = color_to_use.brightness(factor).brightness(settings_arc.brightness);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{BLUE, GREEN, RED};
+    use crate::values::{Analysis, PeakHold};
+
+    const ALL_MODES: [AnimationMode; 8] = [
+        AnimationMode::Full,
+        AnimationMode::FullWithMax,
+        AnimationMode::Points,
+        AnimationMode::FullMiddle,
+        AnimationMode::FullMiddleWithMax,
+        AnimationMode::PointsMiddle,
+        AnimationMode::Strobe,
+        AnimationMode::StrobeStrips,
+    ];
+
+    /// 12 LEDs per strip, unity gains and pure colours so levels map 1:1 to LED positions.
+    fn test_settings(animation_mode: AnimationMode) -> Settings {
+        let mut settings = Settings::default();
+        settings.gain = 1.0;
+        settings.gains = vec![1.0; settings.geometry.strips];
+        settings.color1 = BLUE;
+        settings.color2 = RED;
+        settings.color3 = GREEN;
+        settings.display_mode = DisplayMode::Spectrum;
+        settings.animation_mode = animation_mode;
+        settings
+    }
+
+    fn render(animation_mode: AnimationMode, level: f32, max: f32) -> Vec<[u8; 3]> {
+        let settings = test_settings(animation_mode);
+        get_strip_colors(level, max, &settings, 0).iter().map(|c| c.to_rgb_slice()).collect()
+    }
+
+    fn lit(animation_mode: AnimationMode, level: f32) -> Vec<usize> {
+        lit_with_peak(animation_mode, level, level)
+    }
+
+    fn lit_with_peak(animation_mode: AnimationMode, level: f32, peak: f32) -> Vec<usize> {
+        render(animation_mode, level, peak)
+            .iter()
+            .enumerate()
+            .filter(|(_, rgb)| **rgb != [0, 0, 0])
+            .map(|(led, _)| led)
+            .collect()
+    }
+
+    #[test]
+    fn every_mode_round_trips_through_its_code_and_name() {
+        let names = ["full", "full_with_max", "points", "full_middle", "full_middle_with_max", "points_middle", "strobe", "strobe_strips"];
+        for (code, mode) in ALL_MODES.iter().enumerate() {
+            assert_eq!(AnimationMode::from_u8(code as u8).as_ref(), Some(mode));
+            assert_eq!(mode.clone() as u8, code as u8);
+            assert_eq!(names[code].parse::<AnimationMode>().as_ref(), Ok(mode));
+        }
+        assert_eq!(AnimationMode::from_u8(ALL_MODES.len() as u8), None);
+    }
+
+    #[test]
+    fn no_two_modes_render_the_same() {
+        // The strobe modes draw `Full` under their flash, which is not part of `get_strip_colors`
+        let frames: Vec<_> = ALL_MODES[..6].iter().map(|mode| render(mode.clone(), 0.45, 0.8)).collect();
+        for a in 0..frames.len() {
+            for b in a + 1..frames.len() {
+                assert_ne!(frames[a], frames[b], "{:?} renders like {:?}", ALL_MODES[a], ALL_MODES[b]);
+            }
+        }
+    }
+
+    #[test]
+    fn full_fills_from_the_bottom() {
+        assert_eq!(lit(AnimationMode::Full, 0.5), (0..6).collect::<Vec<_>>());
+        assert_eq!(lit(AnimationMode::Full, 0.0), Vec::<usize>::new());
+        assert_eq!(lit(AnimationMode::Full, 2.0), (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn full_with_max_adds_a_color3_marker_above_the_bar() {
+        let frame = render(AnimationMode::FullWithMax, 0.5, 0.5);
+        assert_eq!(frame[..6], render(AnimationMode::Full, 0.5, 0.5)[..6]);
+        let marker = frame.iter().rposition(|rgb| *rgb != [0, 0, 0]).unwrap();
+        assert!(marker >= 6);
+        assert_eq!(frame[marker][0], 0);
+        assert_eq!(frame[marker][2], 0);
+        assert!(frame[marker][1] > 0);
+    }
+
+    #[test]
+    fn with_max_markers_follow_the_peak_not_the_level() {
+        assert_eq!(lit_with_peak(AnimationMode::FullWithMax, 0.25, 0.75), vec![0, 1, 2, 9]);
+        assert_eq!(lit_with_peak(AnimationMode::FullWithMax, 0.25, 0.0), vec![0, 1, 2]);
+        assert_eq!(lit_with_peak(AnimationMode::FullMiddleWithMax, 0.0, 0.5), vec![2, 9]);
+        assert_eq!(lit_with_peak(AnimationMode::FullMiddleWithMax, 0.0, 2.0), vec![0, 11]);
+    }
+
+    #[test]
+    fn peak_holds_then_decays() {
+        let start = Instant::now();
+        let hold = Duration::from_millis(500);
+        let mut peak = PeakHold::default();
+
+        assert_eq!(peak.update(0.8, start, hold, 2.0), 0.8);
+        // Lower levels do not pull the marker down during the hold time
+        assert_eq!(peak.update(0.2, start + Duration::from_millis(400), hold, 2.0), 0.8);
+        // Then it falls at 2 strip heights per second: 0.8 - 2 × 0.25 s
+        let falling = peak.update(0.2, start + Duration::from_millis(750), hold, 2.0);
+        assert!((falling - 0.3).abs() < 1e-4);
+        // Until it meets the level again, which restarts the hold
+        assert_eq!(peak.update(0.2, start + Duration::from_millis(800), hold, 2.0), 0.2);
+        assert_eq!(peak.update(0.0, start + Duration::from_millis(1200), hold, 2.0), 0.2);
+        assert_eq!(peak.level(start + Duration::from_secs(5), hold, 2.0), 0.0);
+    }
+
+    #[test]
+    fn points_lights_the_two_leds_around_the_level() {
+        assert_eq!(lit(AnimationMode::Points, 0.45), vec![5, 6]);
+        assert_eq!(lit(AnimationMode::Points, 2.0), vec![11]);
+    }
+
+    #[test]
+    fn full_middle_grows_symmetrically_from_the_centre() {
+        assert_eq!(lit(AnimationMode::FullMiddle, 0.5), (3..9).collect::<Vec<_>>());
+        assert_eq!(lit(AnimationMode::FullMiddle, 0.0), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn full_middle_with_max_adds_markers_at_both_ends() {
+        let frame = render(AnimationMode::FullMiddleWithMax, 0.5, 0.5);
+        assert_eq!(frame[3..9], render(AnimationMode::FullMiddle, 0.5, 0.5)[3..9]);
+        let lit: Vec<usize> = (0..frame.len()).filter(|&led| frame[led] != [0, 0, 0]).collect();
+        assert_eq!(lit.len(), 8);
+        assert_eq!(frame[*lit.first().unwrap()], frame[*lit.last().unwrap()]);
+    }
+
+    #[test]
+    fn points_middle_mirrors_points_around_the_centre() {
+        assert_eq!(lit(AnimationMode::PointsMiddle, 0.45), vec![2, 3, 8, 9]);
+        assert_eq!(lit(AnimationMode::PointsMiddle, 0.0), vec![5, 6]);
+        assert_eq!(lit(AnimationMode::PointsMiddle, 2.0), vec![0, 11]);
+
+        let frame = render(AnimationMode::PointsMiddle, 0.45, 0.45);
+        for led in 0..6 {
+            assert_eq!(frame[led], frame[11 - led]);
+        }
+    }
+
+    #[test]
+    fn stereo_split_puts_the_bass_of_both_channels_in_the_middle() {
+        let mut settings = test_settings(AnimationMode::Full);
+        settings.geometry.strips = 5;
+        settings.gains = vec![1.0; 5];
+        settings.stereo_split = true;
+        let analysis = Analysis {
+            channel_levels: vec![vec![0.1, 0.2], vec![0.3, 0.4]],
+            channel_maxima: vec![vec![0.1, 0.2], vec![0.3, 0.4]],
+            ..Analysis::default()
+        };
+        let levels: Vec<f32> = strip_bands(&analysis, &settings).iter().map(|band| band.0).collect();
+        assert_eq!(levels, vec![0.2, 0.1, 0.0, 0.3, 0.4]);
+
+        settings.stereo_split = false;
+        let levels: Vec<f32> = strip_bands(&analysis, &settings).iter().map(|band| band.0).collect();
+        assert_eq!(levels, vec![0.0; 5]);
+    }
+
+    #[test]
+    fn oscilloscope_trigger_draws_a_sine_at_the_same_phase() {
+        // 100 Hz at 44.1 kHz: a period of exactly 441 samples
+        let sine = |offset: usize| -> Vec<f32> {
+            (0..oscilloscope_history(44100, 22))
+                .map(|n| (std::f32::consts::TAU * ((n + offset) % 441) as f32 / 441.0).sin())
+                .collect()
+        };
+        let columns = oscilloscope_columns(&sine(0), 22, 44100);
+        assert_eq!(columns, oscilloscope_columns(&sine(150), 22, 44100));
+        // Starts on the rising edge
+        assert!(columns[0] >= 0.0 && columns[1] > columns[0], "{:?}", columns);
+    }
+
+    #[test]
+    fn oscilloscope_is_flat_until_a_window_of_samples_arrives() {
+        let span = oscilloscope_span(44100, 22);
+        assert_eq!(oscilloscope_columns(&vec![0.5; span - 1], 22, 44100), vec![0.0; 22]);
+        assert_eq!(oscilloscope_columns(&[], 22, 44100), vec![0.0; 22]);
+        // Just a window: no trigger search, the columns show it as is
+        assert_eq!(oscilloscope_columns(&vec![0.5; span], 22, 44100), vec![0.5; 22]);
+    }
+}
@@ -0,0 +1,845 @@
+//! Audio sources.
+//!
+//! An `AudioSource` delivers interleaved `f32` sample buffers to a callback, exactly like the
+//! cpal input stream callback does, so `dsp::process_audio_data` does not care where the audio
+//! comes from. Whatever the native sample format, buffers are converted to `f32` before the
+//! callback; `AudioFormat` tells the DSP the real rate and channel layout.
+//! The source is selected once at startup:
+//!
+//! ```text
+//! (default)              Default cpal capture device
+//! --device "USB Audio"   Capture device whose name contains the given text
+//! --device 2             Capture device by its index in --list_devices
+//! --host jack            Audio host to capture from (alsa, or jack with the `jack` feature)
+//! --input song.wav       WAV file (PCM 8/16/24/32-bit or 32-bit float)
+//! --input dump.raw       Headerless little-endian PCM, described by --raw_format/--raw_rate/--raw_channels
+//! --input -              Live PCM on stdin, described the same way
+//! --input fifo:<path>    Live PCM from a named pipe (any path that is a pipe works too)
+//! --input udp:<addr>     Live PCM datagrams received on <address>:<port>
+//! --input rtp:<addr>     RTP L16 packets (16-bit big-endian PCM) received on <address>:<port>
+//! --fast                 Feed files as fast as possible instead of in real time
+//! ```
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, StreamConfig, StreamError};
+use crate::constants::{CAPTURE_POLL_MS, CAPTURE_RETRY_MS, CAPTURE_STALL_MS};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, ErrorKind, Read};
+use std::net::UdpSocket;
+use std::os::unix::fs::FileTypeExt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Number of frames handed to the callback per buffer by the file sources.
+const FILE_CHUNK_FRAMES: usize = 512;
+
+pub type AudioCallback = Box<dyn FnMut(&[f32]) + Send + 'static>;
+
+/// Shape of the interleaved buffers a source delivers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Native format of the source, before conversion to `f32`.
+    pub sample_format: SampleFormat,
+}
+
+pub trait AudioSource {
+    /// Format of the buffers passed to the callback.
+    fn format(&self) -> AudioFormat;
+
+    /// Start delivering buffers. The source keeps running until it is dropped
+    /// (or, for files, until the end of the file).
+    fn start(&mut self, on_data: AudioCallback) -> Result<(), Box<dyn Error>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RawSampleFormat {
+    F32,
+    S16,
+    /// Network byte order, as in RTP L16.
+    S16Be,
+}
+
+impl RawSampleFormat {
+    fn bytes_per_sample(&self) -> usize {
+        match self {
+            RawSampleFormat::F32 => 4,
+            RawSampleFormat::S16 | RawSampleFormat::S16Be => 2,
+        }
+    }
+
+    pub fn sample_format(&self) -> SampleFormat {
+        match self {
+            RawSampleFormat::F32 => SampleFormat::F32,
+            RawSampleFormat::S16 | RawSampleFormat::S16Be => SampleFormat::I16,
+        }
+    }
+
+    /// One sample from its `bytes_per_sample` bytes.
+    fn decode(&self, bytes: &[u8]) -> f32 {
+        match self {
+            RawSampleFormat::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            RawSampleFormat::S16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+            RawSampleFormat::S16Be => i16::from_be_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        }
+    }
+}
+
+impl FromStr for RawSampleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f32" => Ok(RawSampleFormat::F32),
+            "s16" => Ok(RawSampleFormat::S16),
+            "s16be" => Ok(RawSampleFormat::S16Be),
+            _ => Err(format!("Invalid raw sample format '{}' (expected f32, s16 or s16be)", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pacing {
+    /// Deliver buffers at the rate they would arrive from a sound card.
+    Realtime,
+    /// Deliver buffers as fast as the callback consumes them.
+    Fast,
+}
+
+impl AudioInput {
+    /// How the source delivers its buffers; live sources always arrive in real time.
+    pub fn pacing(&self) -> Pacing {
+        match self {
+            AudioInput::Wav { pacing, .. } | AudioInput::Raw { pacing, .. } => *pacing,
+            AudioInput::Device { .. } | AudioInput::Pcm { .. } => Pacing::Realtime,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AudioInput {
+    /// Capture device selected by name or index on the named host, or the defaults.
+    Device { host: Option<String>, device: Option<String> },
+    Wav { path: String, pacing: Pacing },
+    Raw { path: String, sample_format: RawSampleFormat, format: AudioFormat, pacing: Pacing },
+    /// Live PCM, delivered as it arrives.
+    Pcm { stream: PcmStream, sample_format: RawSampleFormat, format: AudioFormat },
+}
+
+impl fmt::Display for AudioInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AudioInput::Device { host, device } => {
+                match device {
+                    None => write!(f, "default capture device")?,
+                    Some(device) => write!(f, "capture device '{}'", device)?,
+                }
+                match host {
+                    None => Ok(()),
+                    Some(host) => write!(f, " on {}", host),
+                }
+            }
+            AudioInput::Wav { path, pacing } => write!(f, "WAV file {} ({:?})", path, pacing),
+            AudioInput::Raw { path, sample_format, format, pacing } => write!(
+                f,
+                "raw {:?} file {} ({} Hz, {} ch, {:?})",
+                sample_format, path, format.sample_rate, format.channels, pacing
+            ),
+            AudioInput::Pcm { stream, sample_format, format } => write!(
+                f,
+                "{:?} PCM from {} ({} Hz, {} ch)",
+                sample_format, stream, format.sample_rate, format.channels
+            ),
+        }
+    }
+}
+
+/// Open the source described by `input`.
+pub fn open_audio_source(input: &AudioInput) -> Result<Box<dyn AudioSource>, Box<dyn Error>> {
+    let source: Box<dyn AudioSource> = match input {
+        AudioInput::Device { host, device } => Box::new(CpalSource::open(host.as_deref(), device.as_deref())?),
+        AudioInput::Wav { path, pacing } => Box::new(FileSource::open_wav(path, *pacing)?),
+        AudioInput::Raw { path, sample_format, format, pacing } => {
+            Box::new(FileSource::open_raw(path, *sample_format, *format, *pacing)?)
+        }
+        AudioInput::Pcm { stream, sample_format, format } => {
+            Box::new(PcmStreamSource::new(stream.clone(), *sample_format, *format)?)
+        }
+    };
+    Ok(source)
+}
+
+// ---------------------------------------------------------------------------
+// cpal capture device
+// ---------------------------------------------------------------------------
+
+/// Captures from a cpal input device.
+///
+/// The stream lives on a supervisor thread (cpal streams cannot move between threads). When the
+/// device disappears, reported by cpal or noticed because no buffer arrived for
+/// `CAPTURE_STALL_MS`, the supervisor drops the stream and rebuilds it with the same format
+/// once the device is back, so unplugging the sound card never stops the daemon.
+pub struct CpalSource {
+    host_id: cpal::HostId,
+    /// Name of the device opened, looked up again when it comes back.
+    device_name: String,
+    /// Opened as the host default, so re-plugging follows the default instead of the name.
+    follow_default: bool,
+    config: StreamConfig,
+    sample_format: SampleFormat,
+    stop: Arc<AtomicBool>,
+    supervisor: Option<JoinHandle<()>>,
+}
+
+impl CpalSource {
+    /// Open the capture device selected by `device` (see `find_input_device`) on the host named
+    /// `host`, or the defaults of either when `None`.
+    pub fn open(host: Option<&str>, device: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let host = select_host(host)?;
+        let follow_default = device.is_none();
+        let device = match device {
+            None => host.default_input_device().ok_or("no capture device found")?,
+            Some(selector) => find_input_device(&host, selector)?,
+        };
+        let device_name = device.name()?;
+        println!("Using device: {} ({})", device_name, host.id().name());
+        let supported = device.default_input_config()?;
+        let sample_format = supported.sample_format();
+        let config: StreamConfig = supported.into();
+        println!("Default input config: {:?} ({})", config, sample_format);
+        Ok(CpalSource {
+            host_id: host.id(),
+            device_name,
+            follow_default,
+            config,
+            sample_format,
+            stop: Arc::new(AtomicBool::new(false)),
+            supervisor: None,
+        })
+    }
+}
+
+/// Host named `name` (case-insensitive, e.g. `alsa` or `jack`), or the default host.
+fn select_host(name: Option<&str>) -> Result<cpal::Host, Box<dyn Error>> {
+    let Some(name) = name else {
+        return Ok(cpal::default_host());
+    };
+    let available = cpal::available_hosts();
+    match available.iter().find(|id| id.name().eq_ignore_ascii_case(name)) {
+        Some(&id) => Ok(cpal::host_from_id(id)?),
+        None => {
+            let names = available.iter().map(|id| id.name()).collect::<Vec<_>>().join(", ");
+            Err(format!("No audio host '{}' in this build (available: {})", name, names).into())
+        }
+    }
+}
+
+/// The input device whose name matches `selector` exactly, or else as a case-insensitive
+/// substring; a number that matches no name is the index shown by `--list_devices`.
+fn find_input_device(host: &cpal::Host, selector: &str) -> Result<cpal::Device, Box<dyn Error>> {
+    let devices: Vec<(String, cpal::Device)> = host
+        .input_devices()?
+        .filter_map(|device| device.name().ok().map(|device_name| (device_name, device)))
+        .collect();
+
+    let wanted = selector.to_lowercase();
+    let position = devices
+        .iter()
+        .position(|(device_name, _)| device_name == selector)
+        .or_else(|| devices.iter().position(|(device_name, _)| device_name.to_lowercase().contains(&wanted)))
+        .or_else(|| selector.parse::<usize>().ok().filter(|&index| index < devices.len()));
+
+    match position {
+        Some(position) => Ok(devices.into_iter().nth(position).unwrap().1),
+        None => {
+            let available = devices.iter().map(|(device_name, _)| device_name.as_str()).collect::<Vec<_>>().join(", ");
+            Err(format!("No capture device matching '{}' (available: {})", selector, available).into())
+        }
+    }
+}
+
+/// Print the capture devices of every host compiled in, with the index `--device` accepts.
+pub fn list_devices() -> Result<(), Box<dyn Error>> {
+    let default_host = cpal::default_host().id();
+    for host_id in cpal::available_hosts() {
+        let host = cpal::host_from_id(host_id)?;
+        let default_device = host.default_input_device().and_then(|device| device.name().ok());
+        println!("{}{}:", host_id.name(), if host_id == default_host { " (default host)" } else { "" });
+        for (index, device) in host.input_devices()?.enumerate() {
+            let name = device.name().unwrap_or_else(|_| "?".to_string());
+            let format = match device.default_input_config() {
+                Ok(config) => format!("{} Hz, {} ch, {}", config.sample_rate().0, config.channels(), config.sample_format()),
+                Err(e) => e.to_string(),
+            };
+            let default = if default_device.as_deref() == Some(name.as_str()) { ", default" } else { "" };
+            println!("  [{}] {} ({}{})", index, name, format, default);
+        }
+    }
+    Ok(())
+}
+
+/// What the callbacks of the current stream tell the supervisor.
+#[derive(Default)]
+struct StreamHealth {
+    buffers: AtomicUsize,
+    lost: AtomicBool,
+}
+
+/// Everything the supervisor needs to rebuild the stream.
+struct StreamSpec {
+    host_id: cpal::HostId,
+    device_name: String,
+    follow_default: bool,
+    config: StreamConfig,
+    sample_format: SampleFormat,
+}
+
+impl StreamSpec {
+    fn device(&self) -> Result<cpal::Device, Box<dyn Error>> {
+        let host = cpal::host_from_id(self.host_id)?;
+        if !self.follow_default {
+            let device = host.input_devices()?.find(|device| device.name().is_ok_and(|name| name == self.device_name));
+            return Ok(device.ok_or("device not present")?);
+        }
+
+        // The default may now be another device: it must capture in the format the
+        // analysis was set up for, or the bands would be computed at the wrong rate.
+        let device = host.default_input_device().ok_or("no default device")?;
+        let name = device.name().unwrap_or_else(|_| "?".to_string());
+        let rate = self.config.sample_rate;
+        let supported = device.supported_input_configs()?.any(|range| {
+            range.channels() == self.config.channels
+                && range.sample_format() == self.sample_format
+                && range.min_sample_rate() <= rate
+                && rate <= range.max_sample_rate()
+        });
+        if !supported {
+            let default = device.default_input_config()?;
+            return Err(format!(
+                "the default device is now '{}' ({} Hz, {} ch, {}), which cannot capture {} Hz, {} ch, {}; restart to use it",
+                name, default.sample_rate().0, default.channels(), default.sample_format(),
+                rate.0, self.config.channels, self.sample_format,
+            ).into());
+        }
+        if name != self.device_name {
+            println!("Default capture device is now '{}'", name);
+        }
+        Ok(device)
+    }
+
+    fn build(&self, on_data: &Arc<Mutex<AudioCallback>>, health: &Arc<StreamHealth>) -> Result<cpal::Stream, Box<dyn Error>> {
+        let device = self.device()?;
+        let stream = match self.sample_format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &self.config, on_data.clone(), health.clone())?,
+            SampleFormat::I16 => build_stream::<i16>(&device, &self.config, on_data.clone(), health.clone())?,
+            SampleFormat::U16 => build_stream::<u16>(&device, &self.config, on_data.clone(), health.clone())?,
+            SampleFormat::I32 => build_stream::<i32>(&device, &self.config, on_data.clone(), health.clone())?,
+            SampleFormat::U8 => build_stream::<u8>(&device, &self.config, on_data.clone(), health.clone())?,
+            other => return Err(format!("Unsupported capture sample format: {}", other).into()),
+        };
+        stream.play()?;
+        Ok(stream)
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    on_data: Arc<Mutex<AudioCallback>>,
+    health: Arc<StreamHealth>,
+) -> Result<cpal::Stream, Box<dyn Error>>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let mut converted: Vec<f32> = Vec::new();
+    let errors = health.clone();
+    let stream = device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| {
+            health.buffers.fetch_add(1, Ordering::Relaxed);
+            converted.clear();
+            converted.extend(data.iter().map(|&sample| sample.to_sample::<f32>()));
+            // Only held by the callback of the stream being replaced, never wait for it
+            if let Ok(mut on_data) = on_data.try_lock() {
+                on_data(&converted);
+            }
+        },
+        move |err| {
+            eprintln!("an error occurred on stream: {}", err);
+            if matches!(err, StreamError::DeviceNotAvailable) {
+                errors.lost.store(true, Ordering::Relaxed);
+            }
+        },
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// Keep a stream running until `stop`: build it, watch it, rebuild it when the device is lost.
+/// The first build result goes to `started`; later failures are retried every `CAPTURE_RETRY_MS`.
+fn supervise(spec: StreamSpec, on_data: AudioCallback, stop: Arc<AtomicBool>, started: mpsc::Sender<Result<(), String>>) {
+    let on_data = Arc::new(Mutex::new(on_data));
+    let mut started = Some(started);
+    let mut waiting = false;
+    while !stop.load(Ordering::Relaxed) {
+        let health = Arc::new(StreamHealth::default());
+        match spec.build(&on_data, &health) {
+            Ok(stream) => {
+                match started.take() {
+                    Some(started) => _ = started.send(Ok(())),
+                    None => println!("Capture device '{}' is back", spec.device_name),
+                }
+                waiting = false;
+                watch(&health, &stop);
+                drop(stream);
+                if !stop.load(Ordering::Relaxed) {
+                    eprintln!("Capture device '{}' lost, waiting for it to come back", spec.device_name);
+                }
+            }
+            Err(e) => {
+                if let Some(started) = started.take() {
+                    _ = started.send(Err(e.to_string()));
+                    return;
+                }
+                if !waiting {
+                    eprintln!("Cannot reopen '{}' yet: {}", spec.device_name, e);
+                    waiting = true;
+                }
+            }
+        }
+        thread::sleep(Duration::from_millis(CAPTURE_RETRY_MS));
+    }
+}
+
+/// Return once the stream reports its device gone, stops delivering buffers, or `stop` is set.
+fn watch(health: &StreamHealth, stop: &AtomicBool) {
+    let mut buffers = 0;
+    let mut last_buffer = Instant::now();
+    while !stop.load(Ordering::Relaxed) && !health.lost.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(CAPTURE_POLL_MS));
+        let count = health.buffers.load(Ordering::Relaxed);
+        if count != buffers {
+            buffers = count;
+            last_buffer = Instant::now();
+        } else if last_buffer.elapsed() >= Duration::from_millis(CAPTURE_STALL_MS) {
+            return;
+        }
+    }
+}
+
+impl AudioSource for CpalSource {
+    fn format(&self) -> AudioFormat {
+        AudioFormat {
+            sample_rate: self.config.sample_rate.0,
+            channels: self.config.channels,
+            sample_format: self.sample_format,
+        }
+    }
+
+    fn start(&mut self, on_data: AudioCallback) -> Result<(), Box<dyn Error>> {
+        if self.supervisor.is_some() {
+            return Err("capture already started".into());
+        }
+        let spec = StreamSpec {
+            host_id: self.host_id,
+            device_name: self.device_name.clone(),
+            follow_default: self.follow_default,
+            config: self.config.clone(),
+            sample_format: self.sample_format,
+        };
+        let stop = self.stop.clone();
+        let (started, result) = mpsc::channel();
+        let supervisor = thread::Builder::new()
+            .name("capture".to_string())
+            .spawn(move || supervise(spec, on_data, stop, started))?;
+        self.supervisor = Some(supervisor);
+        result.recv().map_err(|_| "capture thread exited")??;
+        Ok(())
+    }
+}
+
+impl Drop for CpalSource {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(supervisor) = self.supervisor.take() {
+            _ = supervisor.join();
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// WAV / raw PCM files
+// ---------------------------------------------------------------------------
+
+enum FileReader {
+    Wav(hound::WavReader<BufReader<File>>),
+    Raw(BufReader<File>, RawSampleFormat),
+}
+
+impl FileReader {
+    /// Fill `out` with up to `out.len()` samples, returning how many were read (0 at end of file).
+    fn read_samples(&mut self, out: &mut [f32]) -> Result<usize, Box<dyn Error>> {
+        match self {
+            FileReader::Wav(reader) => {
+                let spec = reader.spec();
+                let mut n = 0;
+                match spec.sample_format {
+                    hound::SampleFormat::Float => {
+                        for (slot, sample) in out.iter_mut().zip(reader.samples::<f32>()) {
+                            *slot = sample?;
+                            n += 1;
+                        }
+                    }
+                    hound::SampleFormat::Int => {
+                        let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+                        for (slot, sample) in out.iter_mut().zip(reader.samples::<i32>()) {
+                            *slot = sample? as f32 * scale;
+                            n += 1;
+                        }
+                    }
+                }
+                Ok(n)
+            }
+            FileReader::Raw(reader, sample_format) => {
+                let width = sample_format.bytes_per_sample();
+                let mut bytes = vec![0u8; out.len() * width];
+                let mut filled = 0;
+                while filled < bytes.len() {
+                    match reader.read(&mut bytes[filled..]) {
+                        Ok(0) => break,
+                        Ok(count) => filled += count,
+                        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                let n = filled / width;
+                for (slot, chunk) in out.iter_mut().zip(bytes[..n * width].chunks_exact(width)) {
+                    *slot = sample_format.decode(chunk);
+                }
+                Ok(n)
+            }
+        }
+    }
+}
+
+/// Plays a file through the callback from a background thread.
+pub struct FileSource {
+    path: String,
+    format: AudioFormat,
+    pacing: Pacing,
+    reader: Option<FileReader>,
+}
+
+impl FileSource {
+    pub fn open_wav(path: &str, pacing: Pacing) -> Result<Self, Box<dyn Error>> {
+        let reader = hound::WavReader::open(path)?;
+        let spec = reader.spec();
+        let sample_format = match (spec.sample_format, spec.bits_per_sample) {
+            (hound::SampleFormat::Float, _) => SampleFormat::F32,
+            (hound::SampleFormat::Int, 8) => SampleFormat::I8,
+            (hound::SampleFormat::Int, 16) => SampleFormat::I16,
+            (hound::SampleFormat::Int, _) => SampleFormat::I32,
+        };
+        Ok(FileSource {
+            path: path.to_string(),
+            format: AudioFormat { sample_rate: spec.sample_rate, channels: spec.channels, sample_format },
+            pacing,
+            reader: Some(FileReader::Wav(reader)),
+        })
+    }
+
+    pub fn open_raw(path: &str, sample_format: RawSampleFormat, format: AudioFormat, pacing: Pacing) -> Result<Self, Box<dyn Error>> {
+        if format.sample_rate == 0 || format.channels == 0 {
+            return Err(format!("Invalid raw PCM format: {} Hz, {} channels", format.sample_rate, format.channels).into());
+        }
+        let file = File::open(path)?;
+        Ok(FileSource {
+            path: path.to_string(),
+            format,
+            pacing,
+            reader: Some(FileReader::Raw(BufReader::new(file), sample_format)),
+        })
+    }
+}
+
+impl AudioSource for FileSource {
+    fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    fn start(&mut self, mut on_data: AudioCallback) -> Result<(), Box<dyn Error>> {
+        let mut reader = self.reader.take().ok_or("file source already started")?;
+        let path = self.path.clone();
+        let format = self.format;
+        let pacing = self.pacing;
+
+        thread::spawn(move || {
+            let mut buffer = vec![0.0f32; FILE_CHUNK_FRAMES * format.channels as usize];
+            let started = Instant::now();
+            let mut frames_played: u64 = 0;
+
+            loop {
+                let n = match reader.read_samples(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(e) => {
+                        eprintln!("Error reading {}: {}", path, e);
+                        break;
+                    }
+                };
+                on_data(&buffer[..n]);
+                frames_played += (n / format.channels as usize) as u64;
+
+                if pacing == Pacing::Realtime {
+                    let due = Duration::from_secs_f64(frames_played as f64 / format.sample_rate as f64);
+                    if let Some(wait) = due.checked_sub(started.elapsed()) {
+                        thread::sleep(wait);
+                    }
+                }
+            }
+            println!("Finished playing {} ({} frames)", path, frames_played);
+        });
+
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Live PCM: stdin, named pipes and UDP
+// ---------------------------------------------------------------------------
+
+/// Where a live PCM stream comes from, e.g. the FIFO or UDP output of MPD or snapcast.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PcmStream {
+    /// `-`
+    Stdin,
+    /// `fifo:<path>`, or any path that is a named pipe. Reopened whenever its writer closes it.
+    Fifo(String),
+    /// `udp:<address>:<port>`: datagrams of raw PCM, each holding whole frames.
+    Udp(String),
+    /// `rtp:<address>:<port>`: RTP packets carrying L16 (16-bit big-endian PCM).
+    Rtp(String),
+}
+
+impl PcmStream {
+    /// The stream named by an `--input` value, `None` for a file.
+    pub fn from_input(input: &str) -> Option<PcmStream> {
+        if input == "-" {
+            return Some(PcmStream::Stdin);
+        }
+        if let Some(path) = input.strip_prefix("fifo:") {
+            return Some(PcmStream::Fifo(path.to_string()));
+        }
+        if let Some(address) = input.strip_prefix("udp:") {
+            return Some(PcmStream::Udp(address.to_string()));
+        }
+        if let Some(address) = input.strip_prefix("rtp:") {
+            return Some(PcmStream::Rtp(address.to_string()));
+        }
+        let is_fifo = std::fs::metadata(input).is_ok_and(|metadata| metadata.file_type().is_fifo());
+        is_fifo.then(|| PcmStream::Fifo(input.to_string()))
+    }
+}
+
+impl fmt::Display for PcmStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcmStream::Stdin => write!(f, "stdin"),
+            PcmStream::Fifo(path) => write!(f, "named pipe {}", path),
+            PcmStream::Udp(address) => write!(f, "UDP {}", address),
+            PcmStream::Rtp(address) => write!(f, "RTP {}", address),
+        }
+    }
+}
+
+/// Turns bytes into whole interleaved frames, keeping a partial frame for the next read.
+struct PcmDecoder {
+    sample_format: RawSampleFormat,
+    frame_bytes: usize,
+    pending: Vec<u8>,
+    samples: Vec<f32>,
+}
+
+impl PcmDecoder {
+    fn new(sample_format: RawSampleFormat, channels: u16) -> Self {
+        PcmDecoder {
+            sample_format,
+            frame_bytes: sample_format.bytes_per_sample() * channels.max(1) as usize,
+            pending: Vec::new(),
+            samples: Vec::new(),
+        }
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> &[f32] {
+        self.pending.extend_from_slice(bytes);
+        let whole = self.pending.len() / self.frame_bytes * self.frame_bytes;
+        let sample_format = self.sample_format;
+        self.samples.clear();
+        self.samples.extend(
+            self.pending[..whole]
+                .chunks_exact(sample_format.bytes_per_sample())
+                .map(|sample| sample_format.decode(sample)),
+        );
+        self.pending.drain(..whole);
+        &self.samples
+    }
+
+    /// Forget a partial frame, when the stream starts over.
+    fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Reads live PCM on a background thread and passes it on as soon as it arrives.
+pub struct PcmStreamSource {
+    stream: PcmStream,
+    sample_format: RawSampleFormat,
+    format: AudioFormat,
+    started: bool,
+}
+
+impl PcmStreamSource {
+    pub fn new(stream: PcmStream, sample_format: RawSampleFormat, format: AudioFormat) -> Result<Self, Box<dyn Error>> {
+        if format.sample_rate == 0 || format.channels == 0 {
+            return Err(format!("Invalid PCM format: {} Hz, {} channels", format.sample_rate, format.channels).into());
+        }
+        Ok(PcmStreamSource { stream, sample_format, format, started: false })
+    }
+}
+
+impl AudioSource for PcmStreamSource {
+    fn format(&self) -> AudioFormat {
+        self.format
+    }
+
+    fn start(&mut self, mut on_data: AudioCallback) -> Result<(), Box<dyn Error>> {
+        if self.started {
+            return Err("PCM stream already started".into());
+        }
+        // Bind now, so a busy port is reported at startup
+        let socket = match &self.stream {
+            PcmStream::Udp(address) | PcmStream::Rtp(address) => Some(UdpSocket::bind(address)?),
+            _ => None,
+        };
+        let stream = self.stream.clone();
+        let mut decoder = PcmDecoder::new(self.sample_format, self.format.channels);
+        thread::Builder::new().name("pcm".to_string()).spawn(move || {
+            let result = match (&stream, socket) {
+                (PcmStream::Stdin, _) => read_stream(std::io::stdin().lock(), &mut decoder, &mut on_data),
+                (PcmStream::Fifo(path), _) => read_fifo(path, &mut decoder, &mut on_data),
+                (PcmStream::Rtp(_), Some(socket)) => receive(&socket, true, &mut decoder, &mut on_data),
+                (_, Some(socket)) => receive(&socket, false, &mut decoder, &mut on_data),
+                (_, None) => Ok(()),
+            };
+            match result {
+                Ok(()) => println!("End of {}", stream),
+                Err(e) => eprintln!("Error reading {}: {}", stream, e),
+            }
+        })?;
+        self.started = true;
+        Ok(())
+    }
+}
+
+/// Pass everything `reader` delivers to `on_data`, until the end of the stream.
+fn read_stream(mut reader: impl Read, decoder: &mut PcmDecoder, on_data: &mut AudioCallback) -> std::io::Result<()> {
+    let mut bytes = vec![0u8; FILE_CHUNK_FRAMES * decoder.frame_bytes];
+    loop {
+        match reader.read(&mut bytes) {
+            Ok(0) => return Ok(()),
+            Ok(count) => {
+                let samples = decoder.decode(&bytes[..count]);
+                if !samples.is_empty() {
+                    on_data(samples);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Read the pipe at `path` forever, waiting for the next writer whenever one closes it.
+fn read_fifo(path: &str, decoder: &mut PcmDecoder, on_data: &mut AudioCallback) -> std::io::Result<()> {
+    loop {
+        let pipe = File::open(path)?; // Blocks until a writer opens the pipe
+        read_stream(pipe, decoder, on_data)?;
+        decoder.reset();
+        println!("{} closed by its writer, waiting for the next one", path);
+    }
+}
+
+/// Receive datagrams forever; with `rtp`, strip the RTP header and skip anything that is not RTP.
+fn receive(socket: &UdpSocket, rtp: bool, decoder: &mut PcmDecoder, on_data: &mut AudioCallback) -> std::io::Result<()> {
+    let mut packet = vec![0u8; 65_536];
+    loop {
+        let (count, _) = socket.recv_from(&mut packet)?;
+        let payload = if rtp {
+            match rtp_payload(&packet[..count]) {
+                Some(payload) => payload,
+                None => continue,
+            }
+        } else {
+            &packet[..count]
+        };
+        let samples = decoder.decode(payload);
+        if !samples.is_empty() {
+            on_data(samples);
+        }
+        decoder.reset(); // Each datagram stands alone
+    }
+}
+
+/// Payload of an RTP packet (RFC 3550): after the fixed header, the CSRC list and any header
+/// extension, and before any padding. `None` if `packet` is not RTP version 2.
+fn rtp_payload(packet: &[u8]) -> Option<&[u8]> {
+    if packet.len() < 12 || packet[0] >> 6 != 2 {
+        return None;
+    }
+    let mut start = 12 + 4 * (packet[0] & 0x0F) as usize;
+    if packet[0] & 0x10 != 0 {
+        let words = packet.get(start + 2..start + 4)?;
+        start += 4 + 4 * u16::from_be_bytes([words[0], words[1]]) as usize;
+    }
+    let mut end = packet.len();
+    if packet[0] & 0x20 != 0 {
+        end = end.checked_sub(*packet.last()? as usize)?;
+    }
+    packet.get(start..end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_frames_wait_for_the_next_read() {
+        let mut decoder = PcmDecoder::new(RawSampleFormat::S16, 2);
+        // One and a half stereo frames, then the rest
+        assert_eq!(decoder.decode(&[0x00, 0x40, 0x00, 0xC0, 0x00, 0x20]), &[0.5, -0.5]);
+        assert_eq!(decoder.decode(&[0x00, 0x00]), &[0.25, 0.0]);
+        assert_eq!(PcmDecoder::new(RawSampleFormat::S16Be, 1).decode(&[0x40, 0x00]), &[0.5]);
+    }
+
+    #[test]
+    fn rtp_payload_skips_the_header_csrcs_extension_and_padding() {
+        let mut packet = vec![0x80, 96, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+        packet.extend_from_slice(&[0x40, 0x00]);
+        assert_eq!(rtp_payload(&packet), Some(&[0x40, 0x00][..]));
+
+        // One CSRC, a one-word extension and two bytes of padding
+        let mut packet = vec![0xB1, 96, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4];
+        packet.extend_from_slice(&[0xBE, 0xDE, 0, 1, 9, 9, 9, 9]);
+        packet.extend_from_slice(&[0x40, 0x00, 0, 2]);
+        assert_eq!(rtp_payload(&packet), Some(&[0x40, 0x00][..]));
+
+        assert_eq!(rtp_payload(&[0x40, 0x00]), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Band layouts generated from a frequency scale.
+//!
+//! Instead of typing one centre frequency per strip, a layout names a scale and a range,
+//! e.g. `log:30-16000`, and the centres are spread over it for however many strips the
+//! panel has. The DSP integrates each band between edges halfway (on a log scale) between
+//! neighbouring centres, so hand-typed and generated lists are treated the same way.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Reference of the ISO 266 third-octave series.
+const THIRD_OCTAVE_REFERENCE_HZ: f32 = 1000.0;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BandScale {
+    /// Equal ratio between neighbouring centres.
+    Logarithmic = 0,
+    /// Centres picked from the standard 1/3-octave series (…, 800, 1000, 1250, … Hz).
+    ThirdOctave = 1,
+    /// Equal steps in mel: finer in the bass, closer to pitch perception than log.
+    Mel = 2,
+    /// Equal steps in Bark, i.e. one band per critical band width of the ear.
+    Bark = 3,
+}
+
+impl BandScale {
+    pub fn from_u8(value: u8) -> Option<BandScale> {
+        match value {
+            0 => Some(BandScale::Logarithmic),
+            1 => Some(BandScale::ThirdOctave),
+            2 => Some(BandScale::Mel),
+            3 => Some(BandScale::Bark),
+            _ => None,
+        }
+    }
+
+    /// Map a frequency onto the scale, where equal distances are equal band widths.
+    fn warp(self, hz: f32) -> f32 {
+        match self {
+            BandScale::Logarithmic | BandScale::ThirdOctave => hz.ln(),
+            BandScale::Mel => 2595.0 * (1.0 + hz / 700.0).log10(),
+            BandScale::Bark => 26.81 * hz / (1960.0 + hz) - 0.53, // Traunmüller
+        }
+    }
+
+    fn unwarp(self, value: f32) -> f32 {
+        match self {
+            BandScale::Logarithmic | BandScale::ThirdOctave => value.exp(),
+            BandScale::Mel => 700.0 * (10f32.powf(value / 2595.0) - 1.0),
+            BandScale::Bark => 1960.0 * (value + 0.53) / (26.28 - value),
+        }
+    }
+}
+
+impl FromStr for BandScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "log" => Ok(BandScale::Logarithmic),
+            "third_octave" => Ok(BandScale::ThirdOctave),
+            "mel" => Ok(BandScale::Mel),
+            "bark" => Ok(BandScale::Bark),
+            _ => Err(format!("Invalid band scale '{}' (expected log, third_octave, mel or bark)", s)),
+        }
+    }
+}
+
+impl fmt::Display for BandScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BandScale::Logarithmic => "log",
+            BandScale::ThirdOctave => "third_octave",
+            BandScale::Mel => "mel",
+            BandScale::Bark => "bark",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A scale and the range it covers, written `<scale>:<min Hz>-<max Hz>`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BandLayout {
+    pub scale: BandScale,
+    pub min_hz: f32,
+    pub max_hz: f32,
+}
+
+impl BandLayout {
+    pub fn new(scale: BandScale, min_hz: f32, max_hz: f32) -> Result<Self, String> {
+        if !(min_hz.is_finite() && max_hz.is_finite() && min_hz > 0.0 && min_hz < max_hz) {
+            return Err(format!("Invalid band range {}-{} Hz (expected 0 < min < max)", min_hz, max_hz));
+        }
+        Ok(BandLayout { scale, min_hz, max_hz })
+    }
+
+    /// `count` band centres, lowest first.
+    ///
+    /// The range is cut into `count` bands of equal width on the scale and each band is
+    /// represented by its middle. Third-octave layouts pick `count` centres of the standard
+    /// series inside the range, spread as evenly as possible.
+    pub fn centres(&self, count: usize) -> Result<Vec<f32>, String> {
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        if self.scale == BandScale::ThirdOctave {
+            // ISO 266: f = 1000 · 10^(n/10), one third of an octave apart
+            let first = (10.0 * (self.min_hz / THIRD_OCTAVE_REFERENCE_HZ).log10()).ceil() as i32;
+            let last = (10.0 * (self.max_hz / THIRD_OCTAVE_REFERENCE_HZ).log10()).floor() as i32;
+            let available = (last - first + 1).max(0) as usize;
+            if available < count {
+                return Err(format!(
+                    "Only {} third-octave bands between {} and {} Hz, {} needed",
+                    available, self.min_hz, self.max_hz, count
+                ));
+            }
+            let step = if count > 1 { (available - 1) as f32 / (count - 1) as f32 } else { 0.0 };
+            return Ok((0..count)
+                .map(|i| {
+                    let n = first + (i as f32 * step).round() as i32;
+                    THIRD_OCTAVE_REFERENCE_HZ * 10f32.powf(n as f32 / 10.0)
+                })
+                .collect());
+        }
+
+        let low = self.scale.warp(self.min_hz);
+        let width = (self.scale.warp(self.max_hz) - low) / count as f32;
+        Ok((0..count)
+            .map(|i| self.scale.unwarp(low + (i as f32 + 0.5) * width))
+            .collect())
+    }
+}
+
+impl FromStr for BandLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expected = || format!("Invalid band layout '{}' (expected <scale>:<min Hz>-<max Hz>, e.g. log:30-16000)", s);
+        let (scale, range) = s.split_once(':').ok_or_else(expected)?;
+        let (min_hz, max_hz) = range.split_once('-').ok_or_else(expected)?;
+        let min_hz = min_hz.trim().parse::<f32>().map_err(|_| expected())?;
+        let max_hz = max_hz.trim().parse::<f32>().map_err(|_| expected())?;
+        BandLayout::new(BandScale::from_str(scale.trim())?, min_hz, max_hz)
+    }
+}
+
+impl fmt::Display for BandLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}-{}", self.scale, self.min_hz, self.max_hz)
+    }
+}
+
+/// Edges of the bands around `centres` (ascending): one more than there are centres.
+///
+/// Inner edges are the geometric mean of neighbouring centres; the outer ones mirror the
+/// nearest inner edge, so the first and last bands are as wide (in octaves) as their neighbours.
+/// A single band spans half an octave either side.
+pub fn band_edges(centres: &[f32]) -> Vec<f32> {
+    match centres {
+        [] => Vec::new(),
+        [centre] => vec![centre / 2f32.sqrt(), centre * 2f32.sqrt()],
+        _ => {
+            let inner: Vec<f32> = centres.windows(2).map(|pair| (pair[0] * pair[1]).sqrt()).collect();
+            let first = centres[0] * centres[0] / inner[0];
+            let last = centres[centres.len() - 1] * centres[centres.len() - 1] / inner[inner.len() - 1];
+            std::iter::once(first).chain(inner).chain(std::iter::once(last)).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= b.abs() * 1e-3
+    }
+
+    #[test]
+    fn log_centres_have_a_constant_ratio_and_fill_the_range() {
+        let centres = "log:30-16000".parse::<BandLayout>().unwrap().centres(22).unwrap();
+        assert_eq!(centres.len(), 22);
+        let ratio = centres[1] / centres[0];
+        assert!(centres.windows(2).all(|pair| close(pair[1] / pair[0], ratio)));
+        let edges = band_edges(&centres);
+        assert!(close(edges[0], 30.0) && close(edges[22], 16000.0), "{:?}", edges);
+    }
+
+    #[test]
+    fn mel_and_bark_centres_stay_in_range_and_widen_with_frequency() {
+        for scale in [BandScale::Mel, BandScale::Bark] {
+            let centres = BandLayout::new(scale, 30.0, 16000.0).unwrap().centres(16).unwrap();
+            assert!(centres[0] > 30.0 && centres[15] < 16000.0, "{:?}: {:?}", scale, centres);
+            let steps: Vec<f32> = centres.windows(2).map(|pair| pair[1] - pair[0]).collect();
+            assert!(steps.windows(2).all(|pair| pair[1] > pair[0]), "{:?}: {:?}", scale, centres);
+        }
+    }
+
+    #[test]
+    fn third_octave_centres_come_from_the_standard_series() {
+        let layout = "third_octave:100-1000".parse::<BandLayout>().unwrap();
+        let centres = layout.centres(11).unwrap();
+        let nominal = [100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0, 500.0, 630.0, 800.0, 1000.0];
+        for (centre, nominal) in centres.iter().zip(nominal) {
+            assert!((centre / nominal - 1.0).abs() < 0.02, "{} vs {}", centre, nominal);
+        }
+        assert!(layout.centres(12).is_err());
+    }
+
+    #[test]
+    fn layouts_round_trip_and_reject_bad_ranges() {
+        let layout = "bark:20-20000".parse::<BandLayout>().unwrap();
+        assert_eq!(layout.to_string().parse::<BandLayout>(), Ok(layout));
+        assert!("log:500-100".parse::<BandLayout>().is_err());
+        assert!("log:0-100".parse::<BandLayout>().is_err());
+        assert!("octave:20-200".parse::<BandLayout>().is_err());
+        assert!("log30-16000".parse::<BandLayout>().is_err());
+    }
+}
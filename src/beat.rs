@@ -0,0 +1,259 @@
+//! Onset detection and tempo tracking.
+//!
+//! Each spectrum is compared with the previous one: the spectral flux, how much the bins rose
+//! on a log scale, spikes on drum hits and note attacks, and a flux well above its recent median
+//! is an onset. The tempo comes from the autocorrelation of the flux over the last seconds. A
+//! beat clock runs at that tempo and is nudged towards the onsets, so beats keep coming through
+//! quiet bars and stay on time through busy ones. Onsets in the lowest bins alone are counted
+//! as kicks, for effects that should follow the drums rather than the tempo.
+
+use std::collections::VecDeque;
+
+/// Flux above this multiple of its recent median (plus `MIN_FLUX`) is an onset.
+const ONSET_RATIO: f32 = 1.5;
+/// Mean log rise per bin below which nothing counts as an onset, so silence stays quiet.
+const MIN_FLUX: f32 = 0.02;
+/// Seconds of flux the onset threshold is taken from.
+const THRESHOLD_WINDOW_S: f32 = 0.5;
+/// Shortest time between two onsets.
+const MIN_ONSET_INTERVAL_S: f32 = 0.1;
+/// Highest frequency of the bins kicks are detected in.
+const KICK_MAX_HZ: f32 = 150.0;
+/// Seconds of flux the tempo is estimated from, and how often it is re-estimated.
+const TEMPO_WINDOW_S: f32 = 6.0;
+const TEMPO_UPDATE_S: f32 = 0.5;
+const MIN_BPM: f32 = 60.0;
+const MAX_BPM: f32 = 180.0;
+/// Tempo favoured between candidates an octave apart, and how strongly (octaves of spread).
+const PREFERRED_BPM: f32 = 120.0;
+const PREFERRED_SPREAD: f32 = 1.0;
+/// Share of a new tempo estimate taken each update.
+const TEMPO_SMOOTHING: f32 = 0.3;
+/// Share of the phase error corrected by each onset.
+const PHASE_CORRECTION: f32 = 0.2;
+
+/// The rhythm as the render loop sees it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Beat {
+    /// Estimated tempo, 0 until there is one.
+    pub bpm: f32,
+    /// Position within the current beat: 0 on the beat, nearly 1 just before the next one.
+    pub phase: f32,
+    /// Beats since start; comparing with the last value seen catches every beat.
+    pub count: u32,
+    /// Kicks since start, counted the same way.
+    pub kicks: u32,
+}
+
+/// Spectral flux of a range of bins and the onsets that stand out of it.
+#[derive(Debug, Clone, Default)]
+struct Onsets {
+    /// Log magnitudes of the previous spectrum.
+    previous: Vec<f32>,
+    /// Flux of the last `THRESHOLD_WINDOW_S`, newest last.
+    recent: VecDeque<f32>,
+    since_onset_s: f32,
+}
+
+impl Onsets {
+    fn reset(&mut self, magnitudes: &[f32]) {
+        self.previous = magnitudes.iter().map(|m| m.ln_1p()).collect();
+        self.recent.clear();
+    }
+
+    /// Flux of `magnitudes` against the previous spectrum, and whether it stands out from the flux of the last `THRESHOLD_WINDOW_S`.
+    fn update(&mut self, magnitudes: &[f32], elapsed: f32) -> (f32, bool) {
+        let flux = spectral_flux(&mut self.previous, magnitudes);
+        self.since_onset_s += elapsed;
+        let window = ((THRESHOLD_WINDOW_S / elapsed) as usize).max(1);
+        let mut onset = false;
+        if self.recent.len() >= window && self.since_onset_s >= MIN_ONSET_INTERVAL_S {
+            let mut recent: Vec<f32> = self.recent.iter().copied().collect();
+            recent.sort_by(f32::total_cmp);
+            let threshold = recent[window / 2] * ONSET_RATIO + MIN_FLUX;
+            if flux > threshold {
+                self.since_onset_s = 0.0;
+                onset = true;
+            }
+        }
+        self.recent.push_back(flux);
+        while self.recent.len() > window {
+            self.recent.pop_front();
+        }
+        (flux, onset)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BeatTracker {
+    pub beat: Beat,
+    onsets: Onsets,
+    /// Onsets of the bins up to `KICK_MAX_HZ`.
+    kicks: Onsets,
+    /// Flux of the last `TEMPO_WINDOW_S`, newest last.
+    flux: VecDeque<f32>,
+    /// Seconds between spectra the history was recorded at.
+    frame_s: f32,
+    since_tempo_s: f32,
+}
+
+impl BeatTracker {
+    /// Feed one magnitude spectrum of bins `df` Hz apart, `elapsed` seconds after the previous one.
+    /// Returns whether a beat falls on it.
+    pub fn update(&mut self, magnitudes: &[f32], df: f32, elapsed: f32) -> bool {
+        // Bin 0 is DC, not bass
+        let kick_bins = &magnitudes[1.min(magnitudes.len())..((KICK_MAX_HZ / df) as usize + 1).min(magnitudes.len())];
+        if elapsed != self.frame_s || magnitudes.len() != self.onsets.previous.len() || kick_bins.len() != self.kicks.previous.len() {
+            // New hop or FFT size: the history no longer lines up, start over from this spectrum
+            self.flux.clear();
+            self.onsets.reset(magnitudes);
+            self.kicks.reset(kick_bins);
+            self.frame_s = elapsed;
+        }
+
+        let (flux, onset) = self.onsets.update(magnitudes, elapsed);
+        if self.kicks.update(kick_bins, elapsed).1 {
+            self.beat.kicks = self.beat.kicks.wrapping_add(1);
+        }
+        self.flux.push_back(flux);
+        let capacity = (TEMPO_WINDOW_S / elapsed) as usize;
+        while self.flux.len() > capacity {
+            self.flux.pop_front();
+        }
+
+        self.since_tempo_s += elapsed;
+        if self.since_tempo_s >= TEMPO_UPDATE_S {
+            self.since_tempo_s = 0.0;
+            if let Some(bpm) = self.estimate_tempo() {
+                self.beat.bpm = if self.beat.bpm > 0.0 { self.beat.bpm + (bpm - self.beat.bpm) * TEMPO_SMOOTHING } else { bpm };
+            }
+        }
+
+        self.advance(onset, elapsed)
+    }
+
+    /// Tempo whose beat period best lines the flux history up with itself.
+    fn estimate_tempo(&self) -> Option<f32> {
+        let frames_per_minute = 60.0 / self.frame_s;
+        let min_lag = ((frames_per_minute / MAX_BPM).floor() as usize).max(1);
+        let max_lag = (frames_per_minute / MIN_BPM).ceil() as usize;
+        if self.flux.len() < 2 * max_lag {
+            return None;
+        }
+
+        let mean = self.flux.iter().sum::<f32>() / self.flux.len() as f32;
+        let signal: Vec<f32> = self.flux.iter().map(|flux| flux - mean).collect();
+        let scores: Vec<f32> = (min_lag..=max_lag)
+            .map(|lag| {
+                let correlation = signal[lag..].iter().zip(&signal).map(|(a, b)| a * b).sum::<f32>() / (signal.len() - lag) as f32;
+                let octaves = (frames_per_minute / lag as f32 / PREFERRED_BPM).log2() / PREFERRED_SPREAD;
+                correlation * (-0.5 * octaves * octaves).exp()
+            })
+            .collect();
+
+        let (best, &score) = scores.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1))?;
+        if score <= 0.0 {
+            return None;
+        }
+        // Parabola through the best lag and its neighbours, for a tempo between whole frames
+        let mut lag = (min_lag + best) as f32;
+        if best > 0 && best + 1 < scores.len() {
+            let (before, after) = (scores[best - 1], scores[best + 1]);
+            let curvature = before - 2.0 * score + after;
+            if curvature < 0.0 {
+                lag += 0.5 * (before - after) / curvature;
+            }
+        }
+        Some(frames_per_minute / lag)
+    }
+
+    /// Run the beat clock for `elapsed` seconds; returns whether it ticked.
+    fn advance(&mut self, onset: bool, elapsed: f32) -> bool {
+        let beat = &mut self.beat;
+        if beat.bpm <= 0.0 {
+            // No tempo yet: every onset is a beat
+            beat.phase = (beat.phase + elapsed * PREFERRED_BPM / 60.0).min(1.0);
+            if onset {
+                beat.phase = 0.0;
+                beat.count = beat.count.wrapping_add(1);
+            }
+            return onset;
+        }
+
+        if onset {
+            // An onset at phase 0.9 is a beat arriving early, at 0.1 one arriving late
+            let error = if beat.phase >= 0.5 { beat.phase - 1.0 } else { beat.phase };
+            beat.phase -= error * PHASE_CORRECTION;
+        }
+        beat.phase += elapsed * beat.bpm / 60.0;
+        if beat.phase >= 1.0 {
+            beat.phase = beat.phase.fract();
+            beat.count = beat.count.wrapping_add(1);
+            return true;
+        }
+        false
+    }
+}
+
+/// Mean rise of the log magnitudes since `previous`, which then takes the new values.
+fn spectral_flux(previous: &mut [f32], magnitudes: &[f32]) -> f32 {
+    let mut flux = 0.0;
+    for (previous, magnitude) in previous.iter_mut().zip(magnitudes) {
+        let current = magnitude.ln_1p();
+        flux += (current - *previous).max(0.0);
+        *previous = current;
+    }
+    flux / magnitudes.len().max(1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `seconds` of spectra at 86 per second with a loud hit every `period` seconds.
+    fn clicks(tracker: &mut BeatTracker, period: f32, seconds: f32) -> u32 {
+        clicks_in(tracker, 0..64, period, seconds)
+    }
+
+    /// Same, with the hits only in `bins` (20 Hz apart).
+    fn clicks_in(tracker: &mut BeatTracker, bins: std::ops::Range<usize>, period: f32, seconds: f32) -> u32 {
+        let elapsed = 512.0 / 44100.0;
+        let start = tracker.beat.count;
+        for n in 0..(seconds / elapsed) as usize {
+            let t = n as f32 * elapsed;
+            let hit = (t / period).floor() != ((t - elapsed) / period).floor();
+            let magnitudes: Vec<f32> = (0..64).map(|bin| if hit && bins.contains(&bin) { 5.0 } else { 0.05 }).collect();
+            tracker.update(&magnitudes, 20.0, elapsed);
+        }
+        tracker.beat.count - start
+    }
+
+    #[test]
+    fn finds_the_tempo_of_a_click_track() {
+        let mut tracker = BeatTracker::default();
+        clicks(&mut tracker, 0.5, 10.0);
+        assert!((tracker.beat.bpm - 120.0).abs() < 2.0, "{}", tracker.beat.bpm);
+        let beats = clicks(&mut tracker, 0.5, 4.0);
+        assert!((7..=9).contains(&beats), "{}", beats);
+    }
+
+    #[test]
+    fn silence_has_no_onsets() {
+        let mut tracker = BeatTracker::default();
+        for _ in 0..1000 {
+            assert!(!tracker.update(&[0.001; 64], 20.0, 0.01));
+        }
+        assert_eq!(tracker.beat.count, 0);
+        assert_eq!(tracker.beat.bpm, 0.0);
+    }
+
+    #[test]
+    fn only_hits_in_the_bass_count_as_kicks() {
+        let mut tracker = BeatTracker::default();
+        clicks_in(&mut tracker, 20..64, 0.5, 4.0);
+        assert!(tracker.beat.count >= 6, "{}", tracker.beat.count);
+        assert_eq!(tracker.beat.kicks, 0);
+        clicks_in(&mut tracker, 1..4, 0.5, 4.0);
+        assert!((7..=9).contains(&tracker.beat.kicks), "{}", tracker.beat.kicks);
+    }
+}
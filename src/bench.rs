@@ -0,0 +1,135 @@
+//! `--bench_callback`: latency of the audio callback before and after the lock-free pipeline.
+//!
+//! Both runs feed the same synthetic stereo buffers at a steady pace while a render thread
+//! reads the shared state at the configured fps, the way the render loop does.
+//! "before" is what `main.rs` used to run in the callback: clone the settings under their lock
+//! and process the buffer on the state behind a mutex. "after" is `pipeline::audio_callback`,
+//! with the DSP thread and the real render loop running next to it.
+
+use crate::animations::{animate_leds, RenderState};
+use crate::audio::{AudioFormat, Pacing};
+use crate::dsp::process_audio_data;
+use crate::pipeline::{audio_callback, sample_queue, spawn_dsp, Pipeline};
+use crate::settings::Settings;
+use crate::sinks::NullSink;
+use crate::values::StateValues;
+use cpal::SampleFormat;
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const BUFFER_FRAMES: usize = 512;
+const BUFFERS: usize = 2_000;
+const BUFFER_INTERVAL: Duration = Duration::from_millis(2);
+
+pub fn bench_callback(settings: Settings) {
+    let format = AudioFormat { sample_rate: settings.sample_rate, channels: 2, sample_format: SampleFormat::F32 };
+    let buffers = test_buffers(&format);
+
+    println!("Audio callback latency, {} buffers of {} frames every {:?}:", BUFFERS, BUFFER_FRAMES, BUFFER_INTERVAL);
+    let before = bench_before(&settings, format, &buffers);
+    print_stats("before (settings clone + DSP under mutex)", before);
+    let (after, overruns) = bench_after(&settings, format, &buffers);
+    print_stats("after (ring buffer push)", after);
+    println!("  buffers dropped by the ring: {}", overruns);
+}
+
+/// A few seconds of two tones in stereo, cut into callback-sized buffers.
+fn test_buffers(format: &AudioFormat) -> Vec<Vec<f32>> {
+    let rate = format.sample_rate as f32;
+    (0..BUFFERS)
+        .map(|buffer| {
+            (0..BUFFER_FRAMES)
+                .flat_map(|frame| {
+                    let t = (buffer * BUFFER_FRAMES + frame) as f32 / rate;
+                    let sample = 0.5 * (TAU * 440.0 * t).sin() + 0.25 * (TAU * 3_000.0 * t).sin();
+                    [sample, sample]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn bench_before(settings: &Settings, format: AudioFormat, buffers: &[Vec<f32>]) -> Vec<Duration> {
+    let settings_mutex = Arc::new(Mutex::new(settings.clone()));
+    let state_mutex = Arc::new(Mutex::new(StateValues::new(settings, format)));
+    let running = Arc::new(AtomicBool::new(true));
+
+    let renderer = {
+        let (settings_mutex, state_mutex, running) = (settings_mutex.clone(), state_mutex.clone(), running.clone());
+        let frame_delay = Duration::from_millis(1_000 / settings.fps as u64);
+        thread::spawn(move || {
+            while running.load(Ordering::Relaxed) {
+                let _frequencies = state_mutex.lock().unwrap().frequencies.clone();
+                let _settings = settings_mutex.lock().unwrap().clone();
+                thread::sleep(frame_delay);
+            }
+        })
+    };
+
+    let timings = time_callback(buffers, |data| {
+        let settings = settings_mutex.lock().unwrap().clone();
+        process_audio_data(data, &mut state_mutex.lock().unwrap(), &settings);
+    });
+
+    running.store(false, Ordering::Relaxed);
+    renderer.join().unwrap();
+    timings
+}
+
+fn bench_after(settings: &Settings, format: AudioFormat, buffers: &[Vec<f32>]) -> (Vec<Duration>, usize) {
+    let settings_mutex = Arc::new(Mutex::new(settings.clone()));
+    let pipeline = Pipeline::new(settings.clone());
+    let (producer, consumer) = sample_queue(&format);
+    let dsp = spawn_dsp(consumer, pipeline.clone(), format).expect("failed to start the DSP thread");
+    let running = Arc::new(AtomicBool::new(true));
+
+    let renderer = {
+        let (settings_mutex, pipeline, running) = (settings_mutex.clone(), pipeline.clone(), running.clone());
+        thread::spawn(move || {
+            let mut render_state = RenderState::default();
+            while running.load(Ordering::Relaxed) {
+                animate_leds(&pipeline, &settings_mutex, &mut render_state, &mut NullSink);
+            }
+        })
+    };
+
+    let mut callback = audio_callback(producer, pipeline.clone(), Pacing::Realtime);
+    let timings = time_callback(buffers, |data| callback(data));
+
+    // Dropping the callback closes the ring, which stops the DSP thread once it is drained.
+    drop(callback);
+    dsp.join().unwrap();
+    running.store(false, Ordering::Relaxed);
+    renderer.join().unwrap();
+    (timings, pipeline.overruns.load(Ordering::Relaxed))
+}
+
+fn time_callback(buffers: &[Vec<f32>], mut callback: impl FnMut(&[f32])) -> Vec<Duration> {
+    buffers
+        .iter()
+        .map(|buffer| {
+            let start = Instant::now();
+            callback(buffer);
+            let elapsed = start.elapsed();
+            thread::sleep(BUFFER_INTERVAL);
+            elapsed
+        })
+        .collect()
+}
+
+fn print_stats(label: &str, mut timings: Vec<Duration>) {
+    timings.sort();
+    let percentile = |p: f64| timings[((timings.len() - 1) as f64 * p).round() as usize];
+    let mean = timings.iter().sum::<Duration>() / timings.len() as u32;
+    println!(
+        "  {:<42} mean {:>9.1?}  p50 {:>9.1?}  p99 {:>9.1?}  max {:>9.1?}",
+        label,
+        mean,
+        percentile(0.5),
+        percentile(0.99),
+        timings[timings.len() - 1],
+    );
+}
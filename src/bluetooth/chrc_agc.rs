@@ -0,0 +1,128 @@
+﻿//! LED-Visualizer – “AGC” characteristic
+//!
+//! Read: nine bytes, whether automatic gain control is on (u8 0/1), its target strip height
+//! (f32 little-endian, 0–1) and the gain it applied to the last frame (f32 little-endian).
+//! Write: the first five bytes only; the applied gain is reported, not set.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_AGC_UUID; // 3E0E001D-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::Settings;
+
+/// Holds the characteristic metadata plus the raw 9-byte value.
+#[derive(Debug)]
+pub struct AgcChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[enabled, target (4 B), applied gain (4 B)]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut value = vec![settings.agc.enabled as u8];
+    value.extend_from_slice(&settings.agc.target.to_le_bytes());
+    value.extend_from_slice(&settings.agc_gain.to_le_bytes());
+    value
+}
+
+object_path! {
+    impl AgcChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_AGC_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct AgcChrcInterface(pub Arc<Mutex<AgcChrc>>);
+
+#[gatt_characteristic()]
+impl AgcChrcInterface {
+    /// ReadValue handler – returns enabled flag, target and applied gain.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("AGC read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 5 bytes (u8 enabled, f32 LE target).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 5 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "AGC expects exactly 5 bytes (u8 enabled, f32 LE target)".into(),
+            ));
+        }
+        let enabled = match value[0] {
+            0 => false,
+            1 => true,
+            other => return Err(zbus::fdo::Error::InvalidArgs(format!("Invalid AGC enabled flag {}", other))),
+        };
+        let target = f32::from_le_bytes([value[1], value[2], value[3], value[4]]);
+        if !(target > 0.0 && target <= 1.0) {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                format!("AGC target {} is outside 0..=1", target),
+            ));
+        }
+        println!("AGC write ← enabled {}, target {}", enabled, target);
+        let chrc = self.0.lock().unwrap();
+        let mut settings = chrc.settings.lock().unwrap();
+        settings.agc.enabled = enabled;
+        settings.agc.target = target;
+        Ok(())
+    }
+}
+
+pub async fn get_agc_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<AgcChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(AgcChrc::new(
+        format!("{}/agc_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = AgcChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
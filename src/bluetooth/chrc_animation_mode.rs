@@ -1,7 +1,8 @@
 ﻿//! LED-Visualizer – “Animation Mode” characteristic
 //!
 //! An 8-bit unsigned integer representing the animation mode:
-//! 0: Full, 1: FullWithMax, 2: Points, 3: FullMiddle, 4: FullMiddleWithMax, 5: PointsMiddle.
+//! 0: Full, 1: FullWithMax, 2: Points, 3: FullMiddle, 4: FullMiddleWithMax, 5: PointsMiddle,
+//! 6: Strobe, 7: StrobeStrips.
 //!
 //! Flags: **read** | **write-without-response**
 //
@@ -26,6 +27,8 @@ fn animation_mode_to_u8(mode: &AnimationMode) -> u8 {
         AnimationMode::FullMiddle => 3,
         AnimationMode::FullMiddleWithMax => 4,
         AnimationMode::PointsMiddle => 5,
+        AnimationMode::Strobe => 6,
+        AnimationMode::StrobeStrips => 7,
     }
 }
 
@@ -38,6 +41,8 @@ fn u8_to_animation_mode(value: u8) -> Result<AnimationMode, zbus::fdo::Error> {
         3 => Ok(AnimationMode::FullMiddle),
         4 => Ok(AnimationMode::FullMiddleWithMax),
         5 => Ok(AnimationMode::PointsMiddle),
+        6 => Ok(AnimationMode::Strobe),
+        7 => Ok(AnimationMode::StrobeStrips),
         _ => Err(zbus::fdo::Error::InvalidArgs(
             format!("Invalid value for AnimationMode: {}", value),
         )),
@@ -0,0 +1,103 @@
+﻿//! LED-Visualizer – “Band Layout” characteristic
+//!
+//! Regenerates the band centres from a scale and a range instead of uploading every frequency:
+//! a u8 scale (0 log, 1 third-octave, 2 mel, 3 Bark) followed by the lowest and highest
+//! frequency as two f32 little-endian values, 9 bytes in total. Gains are left untouched;
+//! read the Frequencies characteristic to see the result.
+//!
+//! Flags: **write-without-response**
+//
+use crate::bands::{BandLayout, BandScale};
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_BAND_LAYOUT_UUID; // 3E0E001C-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::Settings;
+
+#[derive(Debug)]
+pub struct BandLayoutChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+object_path! {
+    impl BandLayoutChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_BAND_LAYOUT_UUID.to_string();
+            let flags = vec!["write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let owned = OwnedValue::try_from(Value::from(Vec::<u8>::new())).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct BandLayoutChrcInterface(pub Arc<Mutex<BandLayoutChrc>>);
+
+#[gatt_characteristic()]
+impl BandLayoutChrcInterface {
+    /// WriteValue handler – expects exactly 9 bytes (u8 scale, f32 LE min Hz, f32 LE max Hz).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 9 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Band Layout expects exactly 9 bytes (u8 scale, f32 LE min Hz, f32 LE max Hz)".into(),
+            ));
+        }
+        let scale = BandScale::from_u8(value[0])
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Invalid band scale code {}", value[0])))?;
+        let min_hz = f32::from_le_bytes([value[1], value[2], value[3], value[4]]);
+        let max_hz = f32::from_le_bytes([value[5], value[6], value[7], value[8]]);
+        let layout = BandLayout::new(scale, min_hz, max_hz).map_err(zbus::fdo::Error::InvalidArgs)?;
+
+        println!("Band Layout write ← {}", layout);
+        let chrc = self.0.lock().unwrap();
+        chrc.settings.lock().unwrap().set_band_layout(&layout).map_err(zbus::fdo::Error::InvalidArgs)?;
+        Ok(())
+    }
+}
+
+pub async fn get_band_layout_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<BandLayoutChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(BandLayoutChrc::new(
+        format!("{}/band_layout_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = BandLayoutChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
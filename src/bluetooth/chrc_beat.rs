@@ -0,0 +1,117 @@
+﻿//! LED-Visualizer – “Beat” characteristic
+//!
+//! Read: nine bytes, the beat effect (u8: 0 off, 1 flash, 2 pulse, 3 advance), the tempo
+//! found in the music (f32 little-endian BPM, 0 while unknown) and the number of beats
+//! counted since start (u32 little-endian, wrapping).
+//! Write: the beat effect alone, one byte; tempo and count are reported, not set.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_BEAT_UUID; // 3E0E001F-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::{BeatEffect, Settings};
+
+/// Holds the characteristic metadata plus the raw 9-byte value.
+#[derive(Debug)]
+pub struct BeatChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[effect, bpm (4 B), beat count (4 B)]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut value = vec![settings.beat_effect as u8];
+    value.extend_from_slice(&settings.bpm.to_le_bytes());
+    value.extend_from_slice(&settings.beat_count.to_le_bytes());
+    value
+}
+
+object_path! {
+    impl BeatChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_BEAT_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct BeatChrcInterface(pub Arc<Mutex<BeatChrc>>);
+
+#[gatt_characteristic()]
+impl BeatChrcInterface {
+    /// ReadValue handler – returns effect code, BPM and beat count.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Beat read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 1 byte (u8 effect).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 1 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Beat expects exactly 1 byte (u8 effect)".into(),
+            ));
+        }
+        let beat_effect = BeatEffect::from_u8(value[0])
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Invalid beat effect code {}", value[0])))?;
+        println!("Beat write ← {:?}", beat_effect);
+        self.0.lock().unwrap().settings.lock().unwrap().beat_effect = beat_effect;
+        Ok(())
+    }
+}
+
+pub async fn get_beat_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<BeatChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(BeatChrc::new(
+        format!("{}/beat_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = BeatChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
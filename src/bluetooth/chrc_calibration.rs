@@ -0,0 +1,135 @@
+﻿//! LED-Visualizer – “Calibration” characteristic
+//!
+//! Write 2 bytes to start or cancel a calibration run: `[command u8: 0 cancel, 1 start]`
+//! `[seconds u8: length of each phase, 1..=120, 0 for the default]`. A run measures the room
+//! noise first, then reference music, and sets the gains and noise gates (and those of the active preset).
+//!
+//! Read: `[phase u8: 0 idle, 1 noise, 2 music, 3 done, 4 cancelled, 5 failed]`
+//! `[progress u8: % of the current phase]` `[count u8]` `[count × i8: noise floor of each band, dBFS]`.
+//! The noise floors are those of the last successful run, none before one.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_CALIBRATION_UUID; // 3E0E0025-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::calibration::CalibrationRequest;
+use crate::constants::{DEFAULT_CALIBRATION_SECONDS, MAX_CALIBRATION_SECONDS};
+use crate::settings::Settings;
+
+/// Holds the characteristic metadata plus the shared settings.
+#[derive(Debug)]
+pub struct CalibrationChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[phase][progress][count][noise floors…]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let status = &settings.calibration_status;
+    let noise_db = status.result.as_ref().map(|result| result.noise_db.as_slice()).unwrap_or(&[]);
+    let count = noise_db.len().min(u8::MAX as usize);
+    let mut value = Vec::with_capacity(3 + count);
+    value.push(status.phase as u8);
+    value.push((status.progress * 100.0).round() as u8);
+    value.push(count as u8);
+    value.extend(noise_db[..count].iter().map(|db| db.round().clamp(i8::MIN as f32, i8::MAX as f32) as i8 as u8));
+    value
+}
+
+object_path! {
+    impl CalibrationChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_CALIBRATION_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct CalibrationChrcInterface(pub Arc<Mutex<CalibrationChrc>>);
+
+#[gatt_characteristic()]
+impl CalibrationChrcInterface {
+    /// ReadValue handler – returns the phase, progress and noise floors.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Calibration read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 2 bytes (u8 command, u8 seconds).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 2 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Calibration expects exactly 2 bytes (u8 command, u8 seconds)".into(),
+            ));
+        }
+        let seconds = match (value[0], value[1]) {
+            (0, _) => 0,
+            (1, 0) => DEFAULT_CALIBRATION_SECONDS,
+            (1, seconds) if seconds <= MAX_CALIBRATION_SECONDS => seconds,
+            (1, seconds) => return Err(zbus::fdo::Error::InvalidArgs(format!("Calibration seconds out of range: {}", seconds))),
+            (command, _) => return Err(zbus::fdo::Error::InvalidArgs(format!("Invalid calibration command: {}", command))),
+        };
+        println!("Calibration write ← {} s", seconds);
+        let chrc = self.0.lock().unwrap();
+        let mut settings = chrc.settings.lock().unwrap();
+        let run = settings.calibration.run.wrapping_add(1);
+        settings.calibration = CalibrationRequest { run, seconds: seconds as f32 };
+        Ok(())
+    }
+}
+
+pub async fn get_calibration_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<CalibrationChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(CalibrationChrc::new(
+        format!("{}/calibration_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = CalibrationChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
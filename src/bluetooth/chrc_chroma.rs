@@ -0,0 +1,128 @@
+﻿//! LED-Visualizer – “Chroma” characteristic
+//!
+//! Read: twenty-six bytes, the hue of each pitch class from C to B in degrees (12 × u16
+//! little-endian, 0–360), then the chord and the key last named (u8 each: root pitch class,
+//! plus 12 for minor, 255 for none).
+//! Write: the first twenty-four bytes only; chord and key are reported, not set.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_CHROMA_UUID; // 3E0E0021-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::Settings;
+use crate::chroma::{Tonality, PITCH_CLASSES};
+
+/// Holds the characteristic metadata plus the raw 26-byte value.
+#[derive(Debug)]
+pub struct ChromaChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[12 hues (2 B each), chord, key]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut value = Vec::with_capacity(2 * PITCH_CLASSES + 2);
+    for hue in settings.chroma_hues {
+        value.extend_from_slice(&(hue.round() as u16).to_le_bytes());
+    }
+    value.push(Tonality::to_u8(settings.chord));
+    value.push(Tonality::to_u8(settings.key));
+    value
+}
+
+object_path! {
+    impl ChromaChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_CHROMA_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct ChromaChrcInterface(pub Arc<Mutex<ChromaChrc>>);
+
+#[gatt_characteristic()]
+impl ChromaChrcInterface {
+    /// ReadValue handler – returns the palette, chord and key.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Chroma read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 24 bytes (12 × u16 LE hue).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 2 * PITCH_CLASSES {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Chroma expects exactly 24 bytes (12 × u16 LE hue)".into(),
+            ));
+        }
+        let mut hues = [0.0; PITCH_CLASSES];
+        for (hue, bytes) in hues.iter_mut().zip(value.chunks_exact(2)) {
+            let degrees = u16::from_le_bytes([bytes[0], bytes[1]]);
+            if degrees > 360 {
+                return Err(zbus::fdo::Error::InvalidArgs(format!("Chroma hue {} is outside 0..=360", degrees)));
+            }
+            *hue = degrees as f32;
+        }
+        println!("Chroma write ← hues {:?}", hues);
+        let chrc = self.0.lock().unwrap();
+        chrc.settings.lock().unwrap().chroma_hues = hues;
+        Ok(())
+    }
+}
+
+pub async fn get_chroma_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<ChromaChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(ChromaChrc::new(
+        format!("{}/chroma_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = ChromaChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
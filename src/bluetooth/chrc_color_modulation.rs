@@ -0,0 +1,125 @@
+﻿//! LED-Visualizer – “Color Modulation” characteristic
+//!
+//! Read: eighteen bytes, the modulation (u8: 0 off, 1 hue, 2 mix) and the feature it follows
+//! (u8: 0 centroid, 1 rolloff, 2 flatness, 3 RMS), then the features of the last frame:
+//! centroid and rolloff in Hz, flatness (0–1) and RMS (1.0 = full-scale sine), f32 little-endian each.
+//! Write: the first two bytes only; the features are reported, not set.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_COLOR_MODULATION_UUID; // 3E0E0022-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::{ColorModulation, Settings, SpectralFeature};
+
+/// Holds the characteristic metadata plus the raw 18-byte value.
+#[derive(Debug)]
+pub struct ColorModulationChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[modulation, feature, centroid (4 B), rolloff (4 B), flatness (4 B), rms (4 B)]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let features = &settings.features;
+    let mut value = vec![settings.color_modulation as u8, settings.modulation_feature as u8];
+    value.extend_from_slice(&features.centroid_hz.to_le_bytes());
+    value.extend_from_slice(&features.rolloff_hz.to_le_bytes());
+    value.extend_from_slice(&features.flatness.to_le_bytes());
+    value.extend_from_slice(&features.rms.to_le_bytes());
+    value
+}
+
+object_path! {
+    impl ColorModulationChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_COLOR_MODULATION_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct ColorModulationChrcInterface(pub Arc<Mutex<ColorModulationChrc>>);
+
+#[gatt_characteristic()]
+impl ColorModulationChrcInterface {
+    /// ReadValue handler – returns modulation, feature and the latest features.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Color Modulation read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 2 bytes (u8 modulation, u8 feature).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 2 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Color Modulation expects exactly 2 bytes (u8 modulation, u8 feature)".into(),
+            ));
+        }
+        let modulation = ColorModulation::from_u8(value[0])
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Invalid value for ColorModulation: {}", value[0])))?;
+        let feature = SpectralFeature::from_u8(value[1])
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Invalid value for SpectralFeature: {}", value[1])))?;
+        println!("Color Modulation write ← {:?} by {:?}", modulation, feature);
+        let chrc = self.0.lock().unwrap();
+        let mut settings = chrc.settings.lock().unwrap();
+        settings.color_modulation = modulation;
+        settings.modulation_feature = feature;
+        Ok(())
+    }
+}
+
+pub async fn get_color_modulation_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<ColorModulationChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(ColorModulationChrc::new(
+        format!("{}/color_modulation_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = ColorModulationChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
@@ -1,7 +1,7 @@
 ﻿//! LED-Visualizer – “Display Mode” characteristic
 //!
 //! An 8-bit unsigned integer representing the display mode:
-//! 0: Spectrum, 1: Oscilloscope, 2: ColorGradient.
+//! 0: Spectrum, 1: Oscilloscope, 2: ColorGradient, 3: Chroma.
 //!
 //! Flags: **read** | **write-without-response**
 //
@@ -23,6 +23,7 @@ fn display_mode_to_u8(mode: &DisplayMode) -> u8 {
         DisplayMode::Spectrum => 0,
         DisplayMode::Oscilloscope => 1,
         DisplayMode::ColorGradient => 2,
+        DisplayMode::Chroma => 3,
     }
 }
 
@@ -32,6 +33,7 @@ fn u8_to_display_mode(value: u8) -> Result<DisplayMode, zbus::fdo::Error> {
         0 => Ok(DisplayMode::Spectrum),
         1 => Ok(DisplayMode::Oscilloscope),
         2 => Ok(DisplayMode::ColorGradient),
+        3 => Ok(DisplayMode::Chroma),
         _ => Err(zbus::fdo::Error::InvalidArgs(
             format!("Invalid value for DisplayMode: {}", value),
         )),
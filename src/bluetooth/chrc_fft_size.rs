@@ -5,7 +5,7 @@
 //! Flags: **read** | **write-without-response**
 //
 use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
-use crate::constants::GATT_FFT_SIZE_UUID; // Example: "3E0E0007-7C7A-47B0-9FD5-1FC3044C3E63"
+use crate::constants::{GATT_FFT_SIZE_UUID, MAX_FFT_SIZE, MIN_FFT_SIZE}; // Example: "3E0E0007-7C7A-47B0-9FD5-1FC3044C3E63"
 use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
 use crate::{extend_chrc_props, object_path};
 
@@ -14,7 +14,7 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use zbus::{interface, Connection, Error};
 use zbus::zvariant::{OwnedValue, Value};
-use crate::settings::Settings;
+use crate::settings::{is_valid_fft_size, Settings};
 
 /// Holds the characteristic metadata plus the raw 2-byte value (little-endian).
 #[derive(Debug)]
@@ -79,9 +79,14 @@ impl FftSizeChrcInterface {
             ));
         }
         let new_fft_size = u16::from_le_bytes([value[0], value[1]]);
+        if !is_valid_fft_size(new_fft_size as usize) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Invalid FFT Size {} (expected a power of two between {} and {})", new_fft_size, MIN_FFT_SIZE, MAX_FFT_SIZE,
+            )));
+        }
         println!("FFT Size write ← {}", new_fft_size);
         // Assuming settings.fft_size is a u16
-        self.0.lock().unwrap().settings.lock().unwrap().fft_size = new_fft_size as usize;
+        self.0.lock().unwrap().settings.lock().unwrap().set_fft_size(new_fft_size as usize);
         Ok(())
     }
 }
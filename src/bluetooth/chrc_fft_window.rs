@@ -0,0 +1,125 @@
+﻿//! LED-Visualizer – “FFT Window” characteristic
+//!
+//! Three bytes: the window applied to each FFT frame (u8: 0 rectangular, 1 Hann, 2 Hamming,
+//! 3 Blackman-Harris) followed by the hop size, the number of new samples between two FFTs
+//! (u16 little-endian, 32–8192).
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::{GATT_FFT_WINDOW_UUID, MAX_HOP_SIZE, MIN_HOP_SIZE}; // 3E0E001B-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::{FftWindow, Settings};
+
+/// Holds the characteristic metadata plus the raw 3-byte value.
+#[derive(Debug)]
+pub struct FftWindowChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[window, hop_lo, hop_hi]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let hop_size = settings.hop_size.min(u16::MAX as usize) as u16;
+    let mut value = vec![settings.fft_window as u8];
+    value.extend_from_slice(&hop_size.to_le_bytes());
+    value
+}
+
+object_path! {
+    impl FftWindowChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_FFT_WINDOW_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct FftWindowChrcInterface(pub Arc<Mutex<FftWindowChrc>>);
+
+#[gatt_characteristic()]
+impl FftWindowChrcInterface {
+    /// ReadValue handler – returns window code + hop size.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("FFT Window read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 3 bytes (u8 window, u16 LE hop size).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 3 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "FFT Window expects exactly 3 bytes (u8 window, u16 LE hop size)".into(),
+            ));
+        }
+        let fft_window = FftWindow::from_u8(value[0])
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Invalid FFT window code {}", value[0])))?;
+        let hop_size = u16::from_le_bytes([value[1], value[2]]) as usize;
+        if !(MIN_HOP_SIZE..=MAX_HOP_SIZE).contains(&hop_size) {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                format!("Hop size {} is outside {}..={}", hop_size, MIN_HOP_SIZE, MAX_HOP_SIZE),
+            ));
+        }
+        println!("FFT Window write ← {:?}, hop {}", fft_window, hop_size);
+        let chrc = self.0.lock().unwrap();
+        let mut settings = chrc.settings.lock().unwrap();
+        settings.fft_window = fft_window;
+        settings.hop_size = hop_size;
+        Ok(())
+    }
+}
+
+pub async fn get_fft_window_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<FftWindowChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(FftWindowChrc::new(
+        format!("{}/fft_window_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = FftWindowChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
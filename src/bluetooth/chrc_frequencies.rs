@@ -1,7 +1,7 @@
 ﻿//! LED-Visualizer – “Frequencies” characteristic
 //!
-//! A fixed-length array of 22 little-endian 32-bit IEEE-754 floats (Hz).
-//! Total size: 88 bytes.
+//! One little-endian 32-bit IEEE-754 float (Hz) per strip (22 for the default panel).
+//! Total size: 4 × strip count bytes.
 //!
 //! Flags: **read** | **write-without-response**
 //
@@ -18,10 +18,7 @@ use zbus::{interface, Connection, Error};
 use zbus::zvariant::{OwnedValue, Value};
 use crate::settings::Settings;
 
-const NUM_FREQUENCIES: usize = 22;
-const FREQUENCIES_BYTE_LENGTH: usize = NUM_FREQUENCIES * 4;
-
-/// Holds the characteristic metadata plus the raw 88-byte value (22 LE f32s).
+/// Holds the characteristic metadata; the value is one LE f32 per strip.
 #[derive(Debug)]
 pub struct FrequenciesChrc {
     pub base:  BaseGattCharacteristic,
@@ -45,7 +42,7 @@ object_path! {
         pub fn get_properties(&self) -> ObjectInterfaces {
             let mut props = HashMap::new();
             let settings_guard = self.settings.lock().unwrap();
-            let mut bytes_vec = Vec::with_capacity(FREQUENCIES_BYTE_LENGTH);
+            let mut bytes_vec = Vec::with_capacity(settings_guard.frequencies.len() * 4);
             for &float_val in settings_guard.frequencies.iter() {
                 bytes_vec.extend_from_slice(&float_val.to_le_bytes());
             }
@@ -64,14 +61,14 @@ pub(crate) struct FrequenciesChrcInterface(pub Arc<Mutex<FrequenciesChrc>>);
 
 #[gatt_characteristic()]
 impl FrequenciesChrcInterface {
-    /// ReadValue handler – returns the 88-byte LE float array.
+    /// ReadValue handler – returns the LE float array.
     fn read_value(
         &self,
         _opts: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<Vec<u8>> {
         let locked_chrc = self.0.lock().unwrap();
         let settings_guard = locked_chrc.settings.lock().unwrap();
-        let mut val_bytes = Vec::with_capacity(FREQUENCIES_BYTE_LENGTH);
+        let mut val_bytes = Vec::with_capacity(settings_guard.frequencies.len() * 4);
         for &f_val in settings_guard.frequencies.iter() {
             val_bytes.extend_from_slice(&f_val.to_le_bytes());
         }
@@ -79,19 +76,20 @@ impl FrequenciesChrcInterface {
         Ok(val_bytes)
     }
 
-    /// WriteValue handler – expects exactly 88 bytes (22 little-endian f32s).
+    /// WriteValue handler – expects exactly one little-endian f32 per strip.
     fn write_value(
         &mut self,
         value: Vec<u8>,
         _opts: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<()> {
-        if value.len() != FREQUENCIES_BYTE_LENGTH {
+        let num_frequencies = self.0.lock().unwrap().settings.lock().unwrap().geometry.strips;
+        if value.len() != num_frequencies * 4 {
             return Err(zbus::fdo::Error::InvalidArgs(
-                format!("Frequencies expects exactly {} bytes ({} x f32 LE)", FREQUENCIES_BYTE_LENGTH, NUM_FREQUENCIES),
+                format!("Frequencies expects exactly {} bytes ({} x f32 LE)", num_frequencies * 4, num_frequencies),
             ));
         }
-        let mut new_frequencies = vec![0.0f32; NUM_FREQUENCIES];
-        for i in 0..NUM_FREQUENCIES {
+        let mut new_frequencies = vec![0.0f32; num_frequencies];
+        for i in 0..num_frequencies {
             let start = i * 4;
             let end = start + 4;
             // Ensure the slice is exactly 4 bytes before trying to convert
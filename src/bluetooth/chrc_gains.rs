@@ -1,8 +1,8 @@
 ﻿//! LED-Visualizer – “Gains” characteristic
 //!
-//! A fixed-length array of 22 little-endian 32-bit IEEE-754 floats.
+//! One little-endian 32-bit IEEE-754 float per strip (22 for the default panel).
 //! These are one-to-one per-band gains (linear).
-//! Total size: 88 bytes.
+//! Total size: 4 × strip count bytes.
 //!
 //! Flags: **read** | **write-without-response**
 //
@@ -19,10 +19,7 @@ use zbus::{interface, Connection, Error};
 use zbus::zvariant::{OwnedValue, Value};
 use crate::settings::Settings;
 
-const NUM_GAINS: usize = 22;
-const GAINS_BYTE_LENGTH: usize = NUM_GAINS * 4;
-
-/// Holds the characteristic metadata plus the raw 88-byte value (22 LE f32s).
+/// Holds the characteristic metadata; the value is one LE f32 per strip.
 #[derive(Debug)]
 pub struct GainsChrc {
     pub base:  BaseGattCharacteristic,
@@ -46,7 +43,7 @@ object_path! {
         pub fn get_properties(&self) -> ObjectInterfaces {
             let mut props = HashMap::new();
             let settings_guard = self.settings.lock().unwrap();
-            let mut bytes_vec = Vec::with_capacity(GAINS_BYTE_LENGTH);
+            let mut bytes_vec = Vec::with_capacity(settings_guard.gains.len() * 4);
             for &float_val in settings_guard.gains.iter() {
                 bytes_vec.extend_from_slice(&float_val.to_le_bytes());
             }
@@ -65,14 +62,14 @@ pub(crate) struct GainsChrcInterface(pub Arc<Mutex<GainsChrc>>);
 
 #[gatt_characteristic()]
 impl GainsChrcInterface {
-    /// ReadValue handler – returns the 88-byte LE float array.
+    /// ReadValue handler – returns the LE float array.
     fn read_value(
         &self,
         _opts: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<Vec<u8>> {
         let locked_chrc = self.0.lock().unwrap();
         let settings_guard = locked_chrc.settings.lock().unwrap();
-        let mut val_bytes = Vec::with_capacity(GAINS_BYTE_LENGTH);
+        let mut val_bytes = Vec::with_capacity(settings_guard.gains.len() * 4);
         for &f_val in settings_guard.gains.iter() {
             val_bytes.extend_from_slice(&f_val.to_le_bytes());
         }
@@ -80,19 +77,20 @@ impl GainsChrcInterface {
         Ok(val_bytes)
     }
 
-    /// WriteValue handler – expects exactly 88 bytes (22 little-endian f32s).
+    /// WriteValue handler – expects exactly one little-endian f32 per strip.
     fn write_value(
         &mut self,
         value: Vec<u8>,
         _opts: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<()> {
-        if value.len() != GAINS_BYTE_LENGTH {
+        let num_gains = self.0.lock().unwrap().settings.lock().unwrap().geometry.strips;
+        if value.len() != num_gains * 4 {
             return Err(zbus::fdo::Error::InvalidArgs(
-                format!("Gains expects exactly {} bytes ({} x f32 LE)", GAINS_BYTE_LENGTH, NUM_GAINS),
+                format!("Gains expects exactly {} bytes ({} x f32 LE)", num_gains * 4, num_gains),
             ));
         }
-        let mut new_gains = vec![0.0f32; NUM_GAINS];
-        for i in 0..NUM_GAINS {
+        let mut new_gains = vec![0.0f32; num_gains];
+        for i in 0..num_gains {
             let start = i * 4;
             let end = start + 4;
             // Ensure the slice is exactly 4 bytes before trying to convert
@@ -1,7 +1,7 @@
 ﻿//! LED-Visualizer – “LED Count” characteristic
 //!
-//! A `u16` that reports the number of LEDs the visualizer is configured for.
-//! This value is fixed to 264 for the current build.
+//! A `u16` that reports the number of LEDs the visualizer is configured for
+//! (strips × LEDs per strip of the panel geometry, 264 by default).
 //!
 //! Flags: **read**
 //
@@ -15,33 +15,36 @@ use std::sync::{Arc, Mutex};
 use zbus::{interface, Connection, Error};
 use zbus::zvariant::{OwnedValue, Value};
 use crate::constants::GATT_LED_COUNT_UUID;
+use crate::settings::Settings;
 
-/// The fixed value for LED count.
-const LED_COUNT_VALUE: u16 = 264;
-
-/// Characteristic wrapper. Since the value is fixed and read-only,
-/// it doesn't need to store the value itself or settings.
+/// Characteristic wrapper holding a reference to settings containing the panel geometry.
 #[derive(Debug)]
 pub struct LedCountChrc {
     pub base: BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>,
 }
 
 object_path! {
     impl LedCountChrc {
         /// Build the characteristic object.
-        pub fn new(path: String, service: String) -> Self {
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
             let uuid  = GATT_LED_COUNT_UUID.to_string();
             let flags = vec!["read".to_string()]; // Read-only
 
             Self {
                 base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
             }
         }
 
+        fn led_count(&self) -> u16 {
+            self.settings.lock().unwrap().geometry.num_leds() as u16
+        }
+
         /// Expose properties for `GetManagedObjects`.
         pub fn get_properties(&self) -> ObjectInterfaces {
             let mut props = HashMap::new();
-            let owned_val = OwnedValue::try_from(Value::from(LED_COUNT_VALUE.to_le_bytes().to_vec())).unwrap();
+            let owned_val = OwnedValue::try_from(Value::from(self.led_count().to_le_bytes().to_vec())).unwrap();
             extend_chrc_props!(&self, props, owned_val);
             props
         }
@@ -56,15 +59,14 @@ pub(crate) struct LedCountChrcInterface(pub Arc<Mutex<LedCountChrc>>);
 
 #[gatt_characteristic()]
 impl LedCountChrcInterface {
-    /// ReadValue – returns the fixed 16-bit LED count value.
+    /// ReadValue – returns the 16-bit LED count of the configured panel.
     fn read_value(
         &self,
         _options: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<Vec<u8>> {
-        // self.0.lock().unwrap() could be used if we needed to access LedCountChrc fields
-        let val_bytes = LED_COUNT_VALUE.to_le_bytes().to_vec();
-        println!("LED Count read → {}", LED_COUNT_VALUE);
-        Ok(val_bytes)
+        let led_count = self.0.lock().unwrap().led_count();
+        println!("LED Count read → {}", led_count);
+        Ok(led_count.to_le_bytes().to_vec())
     }
 
     // No WriteValue method as this characteristic is read-only.
@@ -73,10 +75,12 @@ impl LedCountChrcInterface {
 pub async fn get_led_count_chrc(
     connection: &Connection,
     service_path: String,
+    settings: Arc<Mutex<Settings>>,
 ) -> Result<Arc<Mutex<LedCountChrc>>, Error> {
     let led_count_chrc = Arc::new(Mutex::new(LedCountChrc::new(
         format!("{}/led_count_ch", service_path.clone()),
         service_path.clone(),
+        settings.clone(),
     )));
     let led_count_object_path = led_count_chrc.lock().unwrap().object_path().clone();
     let led_count_chrc_interface = LedCountChrcInterface(led_count_chrc.clone());
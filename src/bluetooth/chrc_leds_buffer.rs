@@ -1,13 +1,15 @@
 ﻿//! LED-Visualizer – “LED Buffer” characteristic
 //!
 //! A `500` byte buffer that provides a snapshot of the first 500 bytes of the LED buffer.
+//! The full buffer is `3 × LED count + 1` bytes, depending on the panel geometry; the rest of
+//! it is read through "LED Buffer (2)", one `LED_BUFFER_CHUNK` at a time.
 //!
 //! Flags: **read**
 //
 use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
 // Assuming GATT_LED_BUFFER_UUID would be defined in crate::constants
 // For this example, we'll define it locally.
-// use crate::constants::GATT_LED_BUFFER_UUID;
+// use crate::constants::{GATT_LED_BUFFER_UUID, LED_BUFFER_CHUNK};
 use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
 use crate::{extend_chrc_props, object_path};
 use crate::settings::Settings; // Assuming Settings struct holds the LED buffer
@@ -17,11 +19,12 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use zbus::{interface, Connection, Error};
 use zbus::zvariant::{OwnedValue, Value};
-use crate::constants::GATT_LED_BUFFER_UUID;
-
-/// UUID for the LED Buffer characteristic.
-/// (Typically, this would be in a central constants file like `src/constants.rs`)
+use crate::constants::{GATT_LED_BUFFER_UUID, LED_BUFFER_CHUNK};
 
+/// `LED_BUFFER_CHUNK` bytes of `buffer` from `offset`, fewer at its end and none past it.
+pub(crate) fn buffer_chunk(buffer: &[u8], offset: usize) -> Vec<u8> {
+    buffer.iter().skip(offset).take(LED_BUFFER_CHUNK).copied().collect()
+}
 
 /// Characteristic wrapper holding a reference to settings containing the LED buffer.
 #[derive(Debug)]
@@ -46,8 +49,8 @@ object_path! {
         /// Expose properties for `GetManagedObjects`.
         pub fn get_properties(&self) -> ObjectInterfaces {
             let mut props = HashMap::new();
-            // Assuming settings.led_buffer is Vec<u8> of 792 bytes
-            let buffer_value = self.settings.lock().unwrap().led_buffer.clone();
+            // settings.led_buffer is 3 bytes per LED plus the end marker
+            let buffer_value = buffer_chunk(&self.settings.lock().unwrap().led_buffer, 0);
             let owned_val = OwnedValue::try_from(Value::from(buffer_value)).unwrap();
             extend_chrc_props!(&self, props, owned_val);
             props
@@ -63,14 +66,13 @@ pub(crate) struct LedBufferChrcInterface(pub Arc<Mutex<LedBufferChrc>>);
 
 #[gatt_characteristic()]
 impl LedBufferChrcInterface {
-    /// ReadValue – returns the current 792-byte LED buffer.
+    /// ReadValue – returns the first 500 bytes of the current LED buffer.
     fn read_value(
         &self,
         _options: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<Vec<u8>> {
         // Return the first 500 bytes of the LED buffer
-        let buffer_value = self.0.lock().unwrap().settings.lock().unwrap().led_buffer.clone();
-        let buffer_value = buffer_value.into_iter().take(500).collect::<Vec<u8>>();
+        let buffer_value = buffer_chunk(&self.0.lock().unwrap().settings.lock().unwrap().led_buffer, 0);
         println!("LED Buffer read ({} bytes)", buffer_value.len());
         // println!("LED Buffer content (HEX): {:?}", buffer_value.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>());
         Ok(buffer_value)
@@ -1,8 +1,11 @@
 ﻿//! LED-Visualizer – “LED Buffer” characteristic
 //!
-//! The rest of the buffer from byte 500 that provides a snapshot of all pixels in physical order.
+//! Up to `LED_BUFFER_CHUNK` (500) bytes of the LED buffer from an offset, 500 by default: the
+//! rest of a 12 × 22 panel after "LED Buffer". Larger panels do not fit in one BLE value (512 bytes
+//! at most), so the client writes the offset (u16 LE: 500, 1000, …) and reads each chunk in turn,
+//! until a chunk ends with the end marker.
 //!
-//! Flags: **read**
+//! Flags: **read** | **write**
 //
 use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
 use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
@@ -14,17 +17,16 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use zbus::{interface, Connection, Error};
 use zbus::zvariant::{OwnedValue, Value};
-use crate::constants::GATT_LED_BUFFER2_UUID;
-
-/// UUID for the LED Buffer characteristic.
-/// (Typically, this would be in a central constants file like `src/constants.rs`)
-
+use crate::bluetooth::chrc_leds_buffer::buffer_chunk;
+use crate::constants::{GATT_LED_BUFFER2_UUID, LED_BUFFER_CHUNK};
 
 /// Characteristic wrapper holding a reference to settings containing the LED buffer.
 #[derive(Debug)]
 pub struct LedBuffer2Chrc {
     pub base: BaseGattCharacteristic,
     pub settings: Arc<Mutex<Settings>>,
+    /// Byte of the LED buffer the next read starts at.
+    pub offset: usize,
 }
 
 object_path! {
@@ -32,10 +34,12 @@ object_path! {
         /// Build the characteristic object.
         pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
             let uuid  = GATT_LED_BUFFER2_UUID.to_string();
-            let flags = vec!["read".to_string()]; // Read-only
+            // Acknowledged writes, so the offset is in place before the next read
+            let flags = vec!["read".to_string(), "write".to_string()];
 
             Self {
                 settings,
+                offset: LED_BUFFER_CHUNK,
                 base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
             }
         }
@@ -43,8 +47,8 @@ object_path! {
         /// Expose properties for `GetManagedObjects`.
         pub fn get_properties(&self) -> ObjectInterfaces {
             let mut props = HashMap::new();
-            // Assuming settings.led_buffer is Vec<u8> of 792 bytes
-            let buffer_value = self.settings.lock().unwrap().led_buffer.clone();
+            // settings.led_buffer is 3 bytes per LED plus the end marker
+            let buffer_value = buffer_chunk(&self.settings.lock().unwrap().led_buffer, self.offset);
             let owned_val = OwnedValue::try_from(Value::from(buffer_value)).unwrap();
             extend_chrc_props!(&self, props, owned_val);
             props
@@ -60,25 +64,35 @@ pub(crate) struct LedBuffer2ChrcInterface(pub Arc<Mutex<LedBuffer2Chrc>>);
 
 #[gatt_characteristic()]
 impl LedBuffer2ChrcInterface {
-    /// ReadValue – returns the current 792-byte LED buffer.
+    /// ReadValue – returns up to 500 bytes of the LED buffer from the offset last written.
     fn read_value(
         &self,
         _options: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<Vec<u8>> {
-        // Return only the bytes after byte 500
-        let buffer_value = self.0.lock().unwrap().settings.lock().unwrap().led_buffer.clone();
-        if buffer_value.len() < 500 {
-            // Return empty list
-            return Ok(vec![]);
-        }
-
-        let buffer_value = buffer_value[500..].to_vec(); // Get bytes after 500
-        println!("LED Buffer read ({} bytes)", buffer_value.len());
+        let chrc = self.0.lock().unwrap();
+        // Empty once the offset is past the end of the buffer
+        let buffer_value = buffer_chunk(&chrc.settings.lock().unwrap().led_buffer, chrc.offset);
+        println!("LED Buffer read at {} ({} bytes)", chrc.offset, buffer_value.len());
         // println!("LED Buffer content (HEX): {:?}", buffer_value.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>());
         Ok(buffer_value)
     }
 
-    // No WriteValue method as this characteristic is read-only.
+    /// WriteValue – expects exactly 2 bytes (u16 LE offset of the next read).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _options: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 2 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "LED Buffer (2) expects exactly 2 bytes (u16 offset)".into(),
+            ));
+        }
+        let offset = u16::from_le_bytes([value[0], value[1]]) as usize;
+        println!("LED Buffer offset write ← {}", offset);
+        self.0.lock().unwrap().offset = offset;
+        Ok(())
+    }
 }
 
 pub async fn get_led_buffer2_chrc(
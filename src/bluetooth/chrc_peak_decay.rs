@@ -0,0 +1,112 @@
+﻿//! LED-Visualizer – “Peak Decay” characteristic
+//!
+//! A 32-bit IEEE-754 float: how fast the peak markers fall once their hold time is over,
+//! in strip heights per second.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_PEAK_DECAY_UUID;      // 3E0E001A-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::Settings;
+
+/// Holds the characteristic metadata plus the raw 4-byte value (little-endian).
+#[derive(Debug)]
+pub struct PeakDecayChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+object_path! {
+    impl PeakDecayChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_PEAK_DECAY_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let owned = OwnedValue::try_from(Value::from(self.settings.lock().unwrap().peak_decay.to_le_bytes().to_vec())).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct PeakDecayChrcInterface(pub Arc<Mutex<PeakDecayChrc>>);
+
+#[gatt_characteristic()]
+impl PeakDecayChrcInterface {
+    /// ReadValue handler – returns the 4-byte LE float.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let val = self.0.lock().unwrap().settings.lock().unwrap().peak_decay.to_le_bytes().to_vec();
+        let f   = f32::from_le_bytes([val[0], val[1], val[2], val[3]]);
+        println!("Peak Decay read → {:.3}", f);
+        Ok(val)
+    }
+
+    /// WriteValue handler – expects exactly 4 bytes (little-endian f32).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 4 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Peak Decay expects exactly 4 bytes (f32 LE)".into(),
+            ));
+        }
+        let new_peak_decay = f32::from_le_bytes([value[0], value[1], value[2], value[3]]);
+        if !new_peak_decay.is_finite() || new_peak_decay < 0.0 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Peak Decay must be a positive number".into(),
+            ));
+        }
+        println!("Peak Decay write ← {:.3}", new_peak_decay);
+        self.0.lock().unwrap().settings.lock().unwrap().peak_decay = new_peak_decay;
+        Ok(())
+    }
+}
+
+pub async fn get_peak_decay_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<PeakDecayChrc>>, Error> {
+    let peak_decay_chrc = Arc::new(Mutex::new(PeakDecayChrc::new(
+        format!("{}/peak_decay_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let peak_decay_object_path = peak_decay_chrc.lock().unwrap().object_path().clone();
+    let peak_decay_chrc_interface = PeakDecayChrcInterface(peak_decay_chrc.clone());
+    register_object_with_path(
+        connection,
+        peak_decay_object_path.clone(),
+        peak_decay_chrc_interface,
+    ).await?;
+
+    Ok(peak_decay_chrc)
+}
+
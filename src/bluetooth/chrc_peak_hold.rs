@@ -0,0 +1,106 @@
+﻿//! LED-Visualizer – “Peak Hold” characteristic
+//!
+//! A 16-bit unsigned integer: how long (ms) the peak markers of the *WithMax animation modes
+//! stay at their highest point before they start falling.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_PEAK_HOLD_UUID; // 3E0E0019-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::Settings;
+
+/// Holds the characteristic metadata plus the raw 2-byte value (little-endian).
+#[derive(Debug)]
+pub struct PeakHoldChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+object_path! {
+    impl PeakHoldChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_PEAK_HOLD_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let peak_hold_ms = self.settings.lock().unwrap().peak_hold_ms.min(u16::MAX as usize) as u16;
+            let owned = OwnedValue::try_from(Value::from(peak_hold_ms.to_le_bytes().to_vec())).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct PeakHoldChrcInterface(pub Arc<Mutex<PeakHoldChrc>>);
+
+#[gatt_characteristic()]
+impl PeakHoldChrcInterface {
+    /// ReadValue handler – returns the 2-byte LE u16.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let peak_hold_ms = self.0.lock().unwrap().settings.lock().unwrap().peak_hold_ms.min(u16::MAX as usize) as u16;
+        println!("Peak Hold read → {} ms", peak_hold_ms);
+        Ok(peak_hold_ms.to_le_bytes().to_vec())
+    }
+
+    /// WriteValue handler – expects exactly 2 bytes (little-endian u16).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 2 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Peak Hold expects exactly 2 bytes (u16 LE)".into(),
+            ));
+        }
+        let new_peak_hold_ms = u16::from_le_bytes([value[0], value[1]]);
+        println!("Peak Hold write ← {} ms", new_peak_hold_ms);
+        self.0.lock().unwrap().settings.lock().unwrap().peak_hold_ms = new_peak_hold_ms as usize;
+        Ok(())
+    }
+}
+
+pub async fn get_peak_hold_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<PeakHoldChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(PeakHoldChrc::new(
+        format!("{}/peak_hold_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = PeakHoldChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
\ No newline at end of file
@@ -1,6 +1,8 @@
 ﻿//! LED-Visualizer – “Smooth Size” characteristic
 //!
-//! A 16-bit unsigned integer representing the rolling-average window length.
+//! Three 16-bit little-endian unsigned integers: the rolling-average window length, then the
+//! attack and release times (ms) of the band envelopes. Writes of the window length alone
+//! (2 bytes) leave the envelope times as they are.
 //!
 //! Flags: **read** | **write-without-response**
 //
@@ -16,7 +18,16 @@ use zbus::{interface, Connection, Error};
 use zbus::zvariant::{OwnedValue, Value};
 use crate::settings::Settings;
 
-/// Holds the characteristic metadata plus the raw 2-byte value (little-endian).
+/// `[smooth_size, attack_ms, release_ms]`, each u16 LE.
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut value = Vec::with_capacity(6);
+    for field in [settings.smooth_size, settings.attack_ms, settings.release_ms] {
+        value.extend_from_slice(&(field.min(u16::MAX as usize) as u16).to_le_bytes());
+    }
+    value
+}
+
+/// Holds the characteristic metadata plus the raw 6-byte value (little-endian).
 #[derive(Debug)]
 pub struct SmoothSizeChrc {
     pub base:  BaseGattCharacteristic,
@@ -39,8 +50,7 @@ object_path! {
         /// Expose D-Bus properties for ObjectManager.
         pub fn get_properties(&self) -> ObjectInterfaces {
             let mut props = HashMap::new();
-            // Assuming settings.smooth_size is a u16
-            let owned = OwnedValue::try_from(Value::from(self.settings.lock().unwrap().smooth_size.to_le_bytes().to_vec())).unwrap();
+            let owned = OwnedValue::try_from(Value::from(encode(&self.settings.lock().unwrap()))).unwrap();
             extend_chrc_props!(&self, props, owned);
             props
         }
@@ -55,33 +65,36 @@ pub(crate) struct SmoothSizeChrcInterface(pub Arc<Mutex<SmoothSizeChrc>>);
 
 #[gatt_characteristic()]
 impl SmoothSizeChrcInterface {
-    /// ReadValue handler – returns the 2-byte LE u16.
+    /// ReadValue handler – returns smooth size, attack and release as LE u16s.
     fn read_value(
         &self,
         _opts: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<Vec<u8>> {
-        // Assuming settings.smooth_size is a u16
-        let val_bytes = self.0.lock().unwrap().settings.lock().unwrap().smooth_size.to_le_bytes().to_vec();
-        let val_u16 = u16::from_le_bytes([val_bytes[0], val_bytes[1]]);
-        println!("Smooth Size read → {}", val_u16);
+        let val_bytes = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Smooth Size read → {:?}", val_bytes);
         Ok(val_bytes)
     }
 
-    /// WriteValue handler – expects exactly 2 bytes (little-endian u16).
+    /// WriteValue handler – expects 2 bytes (u16 LE smooth size) or 6 (plus u16 LE attack and release ms).
     fn write_value(
         &mut self,
         value: Vec<u8>,
         _opts: HashMap<String, OwnedValue>,
     ) -> zbus::fdo::Result<()> {
-        if value.len() != 2 {
+        if value.len() != 2 && value.len() != 6 {
             return Err(zbus::fdo::Error::InvalidArgs(
-                "Smooth Size expects exactly 2 bytes (u16 LE)".into(),
+                "Smooth Size expects 2 bytes (u16 LE) or 6 bytes (u16 LE smooth size, attack ms, release ms)".into(),
             ));
         }
         let new_smooth_size = u16::from_le_bytes([value[0], value[1]]);
-        println!("Smooth Size write ← {}", new_smooth_size);
-        // Assuming settings.smooth_size is a u16
-        self.0.lock().unwrap().settings.lock().unwrap().smooth_size = new_smooth_size as usize;
+        println!("Smooth Size write ← {:?}", value);
+        let chrc = self.0.lock().unwrap();
+        let mut settings = chrc.settings.lock().unwrap();
+        settings.smooth_size = new_smooth_size as usize;
+        if value.len() == 6 {
+            settings.attack_ms = u16::from_le_bytes([value[2], value[3]]) as usize;
+            settings.release_ms = u16::from_le_bytes([value[4], value[5]]) as usize;
+        }
         Ok(())
     }
 }
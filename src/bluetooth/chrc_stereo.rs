@@ -0,0 +1,118 @@
+﻿//! LED-Visualizer – “Stereo” characteristic
+//!
+//! Read: nine bytes, the split display flag (u8: 0 mono spectrum, 1 left and right halves),
+//! then the balance (-1 all left, 0 centred, 1 all right) and width (0 mono, 0.5 unrelated
+//! channels, 1 out of phase) of the last frame, f32 little-endian each.
+//! Write: the first byte only; balance and width are reported, not set.
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_STEREO_UUID; // 3E0E0023-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::Settings;
+
+/// Holds the characteristic metadata plus the raw 9-byte value.
+#[derive(Debug)]
+pub struct StereoChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[split, balance (4 B), width (4 B)]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut value = vec![settings.stereo_split as u8];
+    value.extend_from_slice(&settings.stereo.balance.to_le_bytes());
+    value.extend_from_slice(&settings.stereo.width.to_le_bytes());
+    value
+}
+
+object_path! {
+    impl StereoChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_STEREO_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct StereoChrcInterface(pub Arc<Mutex<StereoChrc>>);
+
+#[gatt_characteristic()]
+impl StereoChrcInterface {
+    /// ReadValue handler – returns the split flag and the latest balance and width.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Stereo read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 1 byte (u8 split: 0 or 1).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        let stereo_split = match value.as_slice() {
+            [0] => false,
+            [1] => true,
+            _ => return Err(zbus::fdo::Error::InvalidArgs(
+                "Stereo expects exactly 1 byte (u8 split: 0 or 1)".into(),
+            )),
+        };
+        println!("Stereo write ← split {}", stereo_split);
+        let chrc = self.0.lock().unwrap();
+        chrc.settings.lock().unwrap().stereo_split = stereo_split;
+        Ok(())
+    }
+}
+
+pub async fn get_stereo_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<StereoChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(StereoChrc::new(
+        format!("{}/stereo_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = StereoChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
@@ -0,0 +1,136 @@
+﻿//! LED-Visualizer – “Strobe” characteristic
+//!
+//! Read / Write: eleven bytes, whether the photosensitivity limiter is on (u8 0/1), the flash
+//! fade-out time in ms (u16 little-endian, 10–5000), then the most flashes per second and the
+//! largest luminance step (0–1) the limiter allows (f32 little-endian each).
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_STROBE_UUID; // 3E0E0020-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::Settings;
+use crate::strobe::StrobeSettings;
+
+/// Holds the characteristic metadata plus the raw 11-byte value.
+#[derive(Debug)]
+pub struct StrobeChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[limiter, decay ms (2 B), max rate (4 B), max delta (4 B)]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let strobe = &settings.strobe;
+    let mut value = vec![strobe.limiter as u8];
+    value.extend_from_slice(&(strobe.decay_ms.min(u16::MAX as usize) as u16).to_le_bytes());
+    value.extend_from_slice(&strobe.max_rate.to_le_bytes());
+    value.extend_from_slice(&strobe.max_delta.to_le_bytes());
+    value
+}
+
+object_path! {
+    impl StrobeChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_STROBE_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct StrobeChrcInterface(pub Arc<Mutex<StrobeChrc>>);
+
+#[gatt_characteristic()]
+impl StrobeChrcInterface {
+    /// ReadValue handler – returns limiter flag, decay time and limits.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Strobe read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 11 bytes (u8 limiter, u16 LE decay, f32 LE max rate, f32 LE max delta).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 11 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Strobe expects exactly 11 bytes (u8 limiter, u16 LE decay, f32 LE max rate, f32 LE max delta)".into(),
+            ));
+        }
+        let limiter = match value[0] {
+            0 => false,
+            1 => true,
+            other => return Err(zbus::fdo::Error::InvalidArgs(format!("Invalid strobe limiter flag {}", other))),
+        };
+        let decay_ms = u16::from_le_bytes([value[1], value[2]]) as usize;
+        let max_rate = f32::from_le_bytes([value[3], value[4], value[5], value[6]]);
+        let max_delta = f32::from_le_bytes([value[7], value[8], value[9], value[10]]);
+        if !(10..=5_000).contains(&decay_ms) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!("Strobe decay {} ms is outside 10..=5000", decay_ms)));
+        }
+        if !(max_rate > 0.0 && max_rate <= 30.0) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!("Strobe max rate {} is outside 0..=30", max_rate)));
+        }
+        if !(max_delta > 0.0 && max_delta <= 1.0) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!("Strobe max delta {} is outside 0..=1", max_delta)));
+        }
+        println!("Strobe write ← limiter {}, decay {} ms, max rate {}, max delta {}", limiter, decay_ms, max_rate, max_delta);
+        let chrc = self.0.lock().unwrap();
+        let mut settings = chrc.settings.lock().unwrap();
+        settings.strobe = StrobeSettings { decay_ms, limiter, max_rate, max_delta };
+        Ok(())
+    }
+}
+
+pub async fn get_strobe_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<StrobeChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(StrobeChrc::new(
+        format!("{}/strobe_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = StrobeChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
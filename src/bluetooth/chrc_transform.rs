@@ -0,0 +1,113 @@
+﻿//! LED-Visualizer – “Transform” characteristic
+//!
+//! One byte: how the band levels are measured (u8: 0 one FFT of the FFT size for every band,
+//! 1 constant-Q, an FFT sized per band: long in the bass, short in the treble).
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::GATT_TRANSFORM_UUID; // 3E0E0024-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::{Settings, Transform};
+
+/// Holds the characteristic metadata plus the raw 1-byte value.
+#[derive(Debug)]
+pub struct TransformChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[transform]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    vec![settings.transform as u8]
+}
+
+object_path! {
+    impl TransformChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_TRANSFORM_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct TransformChrcInterface(pub Arc<Mutex<TransformChrc>>);
+
+#[gatt_characteristic()]
+impl TransformChrcInterface {
+    /// ReadValue handler – returns the current transform.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Transform read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 1 byte (u8 transform).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 1 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Transform expects exactly 1 byte (u8 transform)".into(),
+            ));
+        }
+        let transform = Transform::from_u8(value[0])
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Invalid value for Transform: {}", value[0])))?;
+        println!("Transform write ← {:?}", transform);
+        let chrc = self.0.lock().unwrap();
+        chrc.settings.lock().unwrap().transform = transform;
+        Ok(())
+    }
+}
+
+pub async fn get_transform_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<TransformChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(TransformChrc::new(
+        format!("{}/transform_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = TransformChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
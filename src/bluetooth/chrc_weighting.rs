@@ -0,0 +1,124 @@
+﻿//! LED-Visualizer – “Weighting” characteristic
+//!
+//! Five bytes: the curve applied across the bands (u8: 0 skew power law, 1 A-weighting,
+//! 2 C-weighting, 3 ISO 226 equal loudness) followed by the loudness of the ISO 226 contour
+//! (f32 little-endian, 20–90 phon; kept but unused by the other curves).
+//!
+//! Flags: **read** | **write-without-response**
+//
+use crate::bluez::base_gatt_chrc::BaseGattCharacteristic;
+use crate::constants::{GATT_WEIGHTING_UUID, MAX_WEIGHTING_PHON, MIN_WEIGHTING_PHON}; // 3E0E001E-7C7A-47B0-9FD5-1FC3044C3E63
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::{extend_chrc_props, object_path};
+
+use macros::gatt_characteristic;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zbus::{interface, Connection, Error};
+use zbus::zvariant::{OwnedValue, Value};
+use crate::settings::{Settings, Weighting};
+
+/// Holds the characteristic metadata plus the raw 5-byte value.
+#[derive(Debug)]
+pub struct WeightingChrc {
+    pub base:  BaseGattCharacteristic,
+    pub settings: Arc<Mutex<Settings>>
+}
+
+/// `[curve, phon (4 B)]`
+fn encode(settings: &Settings) -> Vec<u8> {
+    let mut value = vec![settings.weighting as u8];
+    value.extend_from_slice(&settings.weighting_phon.to_le_bytes());
+    value
+}
+
+object_path! {
+    impl WeightingChrc {
+        /// Build the characteristic object.
+        pub fn new(path: String, service: String, settings: Arc<Mutex<Settings>>) -> Self {
+            let uuid  = GATT_WEIGHTING_UUID.to_string();
+            let flags = vec!["read".into(), "write-without-response".into()];
+
+            Self {
+                base:  BaseGattCharacteristic::new(path, uuid, flags, service, vec![]),
+                settings,
+            }
+        }
+
+        /// Expose D-Bus properties for ObjectManager.
+        pub fn get_properties(&self) -> ObjectInterfaces {
+            let mut props = HashMap::new();
+            let value = encode(&self.settings.lock().unwrap());
+            let owned = OwnedValue::try_from(Value::from(value)).unwrap();
+            extend_chrc_props!(&self, props, owned);
+            props
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// zbus interface wrapper
+// ---------------------------------------------------------------------------
+
+pub(crate) struct WeightingChrcInterface(pub Arc<Mutex<WeightingChrc>>);
+
+#[gatt_characteristic()]
+impl WeightingChrcInterface {
+    /// ReadValue handler – returns curve code + phon.
+    fn read_value(
+        &self,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<Vec<u8>> {
+        let value = encode(&self.0.lock().unwrap().settings.lock().unwrap());
+        println!("Weighting read → {:?}", value);
+        Ok(value)
+    }
+
+    /// WriteValue handler – expects exactly 5 bytes (u8 curve, f32 LE phon).
+    fn write_value(
+        &mut self,
+        value: Vec<u8>,
+        _opts: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<()> {
+        if value.len() != 5 {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                "Weighting expects exactly 5 bytes (u8 curve, f32 LE phon)".into(),
+            ));
+        }
+        let weighting = Weighting::from_u8(value[0])
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs(format!("Invalid weighting code {}", value[0])))?;
+        let phon = f32::from_le_bytes([value[1], value[2], value[3], value[4]]);
+        if !(MIN_WEIGHTING_PHON..=MAX_WEIGHTING_PHON).contains(&phon) {
+            return Err(zbus::fdo::Error::InvalidArgs(
+                format!("Phon {} is outside {}..={}", phon, MIN_WEIGHTING_PHON, MAX_WEIGHTING_PHON),
+            ));
+        }
+        println!("Weighting write ← {:?}, {} phon", weighting, phon);
+        let chrc = self.0.lock().unwrap();
+        let mut settings = chrc.settings.lock().unwrap();
+        settings.weighting = weighting;
+        settings.weighting_phon = phon;
+        Ok(())
+    }
+}
+
+pub async fn get_weighting_chrc(
+    connection: &Connection,
+    service_path: String,
+    settings: Arc<Mutex<Settings>>,
+) -> Result<Arc<Mutex<WeightingChrc>>, Error> {
+    let chrc = Arc::new(Mutex::new(WeightingChrc::new(
+        format!("{}/weighting_ch", service_path.clone()),
+        service_path.clone(),
+        settings.clone(),
+    )));
+    let object_path_str = chrc.lock().unwrap().object_path().clone();
+    let chrc_interface = WeightingChrcInterface(chrc.clone());
+    register_object_with_path(
+        connection,
+        object_path_str.clone(),
+        chrc_interface,
+    ).await?;
+
+    Ok(chrc)
+}
@@ -22,4 +22,17 @@ mod chrc_presets_save;
 mod chrc_presets_activate;
 mod chrc_presets_read_activated_index;
 mod chrc_presets_delete;
-mod chrc_read_settings_as_preset;
\ No newline at end of file
+mod chrc_read_settings_as_preset;
+mod chrc_peak_hold;
+mod chrc_peak_decay;
+mod chrc_fft_window;
+mod chrc_band_layout;
+mod chrc_agc;
+mod chrc_weighting;
+mod chrc_beat;
+mod chrc_strobe;
+mod chrc_chroma;
+mod chrc_color_modulation;
+mod chrc_stereo;
+mod chrc_transform;
+mod chrc_calibration;
\ No newline at end of file
@@ -1,7 +1,7 @@
 ﻿use crate::bluez::advertisment::Advertisement;
 use crate::constants::{ADV_APPEARANCE_GAMEPAD, GATT_SERVICE_VISUALIZER_UUID};
 
-pub fn create_advertisement(path: String) -> Advertisement {
+pub fn create_advertisement(path: String, local_name: &str) -> Advertisement {
     let adv = Advertisement::new(
         path,
         "peripheral".to_string(),
@@ -9,7 +9,7 @@ pub fn create_advertisement(path: String) -> Advertisement {
         None,
         None,
         None,
-        Some("LedVisualizer".to_string()),
+        Some(local_name.to_string()),
         true,
         None,
         Some(ADV_APPEARANCE_GAMEPAD),
@@ -31,6 +31,19 @@ use crate::bluetooth::chrc_presets_delete::{get_preset_delete_chrc, PresetDelete
 use crate::bluetooth::chrc_presets_read_activated_index::{get_preset_activated_index_chrc, PresetActivatedIndexChrc};
 use crate::bluetooth::chrc_skew::{get_skew_chrc, SkewChrc};
 use crate::bluetooth::chrc_read_settings_as_preset::{get_settings_as_preset_chrc, SettingsAsPresetChrc};
+use crate::bluetooth::chrc_peak_hold::{get_peak_hold_chrc, PeakHoldChrc};
+use crate::bluetooth::chrc_peak_decay::{get_peak_decay_chrc, PeakDecayChrc};
+use crate::bluetooth::chrc_fft_window::{get_fft_window_chrc, FftWindowChrc};
+use crate::bluetooth::chrc_band_layout::{get_band_layout_chrc, BandLayoutChrc};
+use crate::bluetooth::chrc_agc::{get_agc_chrc, AgcChrc};
+use crate::bluetooth::chrc_weighting::{get_weighting_chrc, WeightingChrc};
+use crate::bluetooth::chrc_beat::{get_beat_chrc, BeatChrc};
+use crate::bluetooth::chrc_strobe::{get_strobe_chrc, StrobeChrc};
+use crate::bluetooth::chrc_chroma::{get_chroma_chrc, ChromaChrc};
+use crate::bluetooth::chrc_color_modulation::{get_color_modulation_chrc, ColorModulationChrc};
+use crate::bluetooth::chrc_stereo::{get_stereo_chrc, StereoChrc};
+use crate::bluetooth::chrc_transform::{get_transform_chrc, TransformChrc};
+use crate::bluetooth::chrc_calibration::{get_calibration_chrc, CalibrationChrc};
 use crate::settings::Settings;
 
 // ---------------------------------------------------------------------------
@@ -66,6 +79,19 @@ pub struct VisualizerService {
     pub preset_delete_chrc: Option<Arc<Mutex<PresetDeleteChrc>>>,
     pub preset_activated_index_chrc: Option<Arc<Mutex<PresetActivatedIndexChrc>>>,
     pub preset_read_settings_as_preset_chrc: Option<Arc<Mutex<SettingsAsPresetChrc>>>,
+    pub peak_hold_chrc: Option<Arc<Mutex<PeakHoldChrc>>>,
+    pub peak_decay_chrc: Option<Arc<Mutex<PeakDecayChrc>>>,
+    pub fft_window_chrc: Option<Arc<Mutex<FftWindowChrc>>>,
+    pub band_layout_chrc: Option<Arc<Mutex<BandLayoutChrc>>>,
+    pub agc_chrc: Option<Arc<Mutex<AgcChrc>>>,
+    pub weighting_chrc: Option<Arc<Mutex<WeightingChrc>>>,
+    pub beat_chrc: Option<Arc<Mutex<BeatChrc>>>,
+    pub strobe_chrc: Option<Arc<Mutex<StrobeChrc>>>,
+    pub chroma_chrc: Option<Arc<Mutex<ChromaChrc>>>,
+    pub color_modulation_chrc: Option<Arc<Mutex<ColorModulationChrc>>>,
+    pub stereo_chrc: Option<Arc<Mutex<StereoChrc>>>,
+    pub transform_chrc: Option<Arc<Mutex<TransformChrc>>>,
+    pub calibration_chrc: Option<Arc<Mutex<CalibrationChrc>>>,
 }
 
 object_path! {
@@ -103,6 +129,19 @@ object_path! {
                 preset_delete_chrc: None,
                 preset_activated_index_chrc: None,
                 preset_read_settings_as_preset_chrc: None,
+                peak_hold_chrc: None,
+                peak_decay_chrc: None,
+                fft_window_chrc: None,
+                band_layout_chrc: None,
+                agc_chrc: None,
+                weighting_chrc: None,
+                beat_chrc: None,
+                strobe_chrc: None,
+                chroma_chrc: None,
+                color_modulation_chrc: None,
+                stereo_chrc: None,
+                transform_chrc: None,
+                calibration_chrc: None,
             }
         }
 
@@ -141,6 +180,19 @@ object_path! {
             extend_option_prop!(&self.preset_delete_chrc, properties); 
             extend_option_prop!(&self.preset_activated_index_chrc, properties);
             extend_option_prop!(&self.preset_read_settings_as_preset_chrc, properties);
+            extend_option_prop!(&self.peak_hold_chrc, properties);
+            extend_option_prop!(&self.peak_decay_chrc, properties);
+            extend_option_prop!(&self.fft_window_chrc, properties);
+            extend_option_prop!(&self.band_layout_chrc, properties);
+            extend_option_prop!(&self.agc_chrc, properties);
+            extend_option_prop!(&self.weighting_chrc, properties);
+            extend_option_prop!(&self.beat_chrc, properties);
+            extend_option_prop!(&self.strobe_chrc, properties);
+            extend_option_prop!(&self.chroma_chrc, properties);
+            extend_option_prop!(&self.color_modulation_chrc, properties);
+            extend_option_prop!(&self.stereo_chrc, properties);
+            extend_option_prop!(&self.transform_chrc, properties);
+            extend_option_prop!(&self.calibration_chrc, properties);
 
             properties
         }
@@ -183,6 +235,7 @@ pub async fn get_visualizer_service(
     let led_count_chrc = get_led_count_chrc(
         connection,
         visualizer_service_path.clone(),
+        settings.clone(),
     ).await?;
     visualizer_service
         .lock()
@@ -456,6 +509,162 @@ pub async fn get_visualizer_service(
         .unwrap()
         .add_characteristic_path(preset_read_settings_as_preset_chrc.lock().unwrap().object_path().clone());
     visualizer_service.lock().unwrap().preset_read_settings_as_preset_chrc = Some(preset_read_settings_as_preset_chrc.clone());
+
+    // ------ Peak Hold characteristic ------
+    let peak_hold_chrc = get_peak_hold_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(peak_hold_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().peak_hold_chrc = Some(peak_hold_chrc.clone());
+
+    // ------ Peak Decay characteristic ------
+    let peak_decay_chrc = get_peak_decay_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(peak_decay_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().peak_decay_chrc = Some(peak_decay_chrc.clone());
+
+    // ------ FFT Window characteristic ------
+    let fft_window_chrc = get_fft_window_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(fft_window_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().fft_window_chrc = Some(fft_window_chrc.clone());
+
+    // ------ Band Layout characteristic ------
+    let band_layout_chrc = get_band_layout_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(band_layout_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().band_layout_chrc = Some(band_layout_chrc.clone());
+
+    // ------ AGC characteristic ------
+    let agc_chrc = get_agc_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(agc_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().agc_chrc = Some(agc_chrc.clone());
+
+    // ------ Weighting characteristic ------
+    let weighting_chrc = get_weighting_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(weighting_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().weighting_chrc = Some(weighting_chrc.clone());
+
+    // ------ Beat characteristic ------
+    let beat_chrc = get_beat_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(beat_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().beat_chrc = Some(beat_chrc.clone());
+
+    // ------ Strobe characteristic ------
+    let strobe_chrc = get_strobe_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(strobe_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().strobe_chrc = Some(strobe_chrc.clone());
+
+    // ------ Chroma characteristic ------
+    let chroma_chrc = get_chroma_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(chroma_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().chroma_chrc = Some(chroma_chrc.clone());
+
+    // ------ Color Modulation characteristic ------
+    let color_modulation_chrc = get_color_modulation_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(color_modulation_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().color_modulation_chrc = Some(color_modulation_chrc.clone());
+
+    // ------ Stereo characteristic ------
+    let stereo_chrc = get_stereo_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(stereo_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().stereo_chrc = Some(stereo_chrc.clone());
+
+    // ------ Transform characteristic ------
+    let transform_chrc = get_transform_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(transform_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().transform_chrc = Some(transform_chrc.clone());
+
+    // ------ Calibration characteristic ------
+    let calibration_chrc = get_calibration_chrc(
+        connection,
+        visualizer_service_path.clone(),
+        settings.clone(),
+    ).await?;
+    visualizer_service
+        .lock()
+        .unwrap()
+        .add_characteristic_path(calibration_chrc.lock().unwrap().object_path().clone());
+    visualizer_service.lock().unwrap().calibration_chrc = Some(calibration_chrc.clone());
     
     // ------ Service registration ------
     let visualizer_service_interface = VisualizerServiceInterface(visualizer_service.clone());
@@ -9,7 +9,7 @@
 //! here we just surface them to BlueZ.
 
 use crate::extend_option_prop;
-use crate::bluez::utils::{find_adapter, register_object_with_path, ObjectInterfaces, ObjectPathTrait};
+use crate::bluez::utils::{register_object_with_path, ObjectInterfaces, ObjectPathTrait};
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -96,6 +96,7 @@ impl ObjectManagerInterface {
 pub async fn create_and_register_application(
     connection: &Connection,
     settings: Arc<Mutex<Settings>>,
+    adapter_path: &str,
 ) -> zbus::Result<(Arc<Mutex<VisualizerGattApplication>>)> {
     println!("Creating GattApplication");
 
@@ -115,14 +116,12 @@ pub async fn create_and_register_application(
         app_object_manager_interface,
     )
         .await?;
-    register_application(connection, app_object_path.clone().as_str()).await?;
+    register_application(connection, app_object_path.clone().as_str(), adapter_path).await?;
 
     Ok(app.clone())
 }
 
-async fn register_application(connection: &Connection, app_path: &str) -> zbus::Result<()> {
-    let adapter_path = find_adapter(connection).await?;
-
+async fn register_application(connection: &Connection, app_path: &str, adapter_path: &str) -> zbus::Result<()> {
     let gatt_manager: Proxy = Proxy::new(
         connection,
         "org.bluez",
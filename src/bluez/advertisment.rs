@@ -1,4 +1,4 @@
-﻿use crate::constants::{ADAPTER_PATH, BLUEZ_SERVICE, LE_ADVERTISING_MANAGER_IFACE};
+﻿use crate::constants::{BLUEZ_SERVICE, LE_ADVERTISING_MANAGER_IFACE};
 use crate::bluez::utils::ObjectPathTrait;
 use std::collections::HashMap;
 use zbus::{fdo, interface, Connection, Proxy};
@@ -144,9 +144,11 @@ impl Advertisement {
 ///
 /// * `connection` - An established zbus Connection.
 /// * `advertisement_path` - A string slice holding the advertisement's object path.
+/// * `adapter_path` - Object path of the adapter to advertise on (e.g. `/org/bluez/hci0`).
 pub async fn register_advertisement(
     connection: &Connection,
     advertisement_path: String,
+    adapter_path: &str,
 ) -> Result<(), zbus::Error> {
     // Obtain the unique name of the BlueZ service.
 
@@ -154,7 +156,7 @@ pub async fn register_advertisement(
     let ad_manager: Proxy = Proxy::new(
         connection,
         BLUEZ_SERVICE,
-        ADAPTER_PATH,
+        adapter_path,
         LE_ADVERTISING_MANAGER_IFACE,
     )
     .await?;
@@ -2,7 +2,7 @@
 use zbus::{fdo, interface, Connection, Proxy};
 use zbus::zvariant::{ObjectPath, Value};
 use crate::bluez::utils::ObjectPathTrait;
-use crate::constants::{ADAPTER_IFACE, ADAPTER_PATH, AGENT_MANAGER_IFACE, BLUEZ_SERVICE, BLUEZ_SERVICE_PATH, DBUS_PROPERTIES_IFACE};
+use crate::constants::{ADAPTER_IFACE, AGENT_MANAGER_IFACE, BLUEZ_SERVICE, BLUEZ_SERVICE_PATH, DBUS_PROPERTIES_IFACE};
 
 #[derive(Default, Debug)]
 pub struct Agent {
@@ -79,6 +79,7 @@ pub async fn register_agent(
     connection: &Connection,
     agent_object_path: &str,
     capability: &str,
+    adapter_path: &str,
 ) -> Result<(), zbus::Error> {
     // Create a proxy for the AgentManager interface on /org/bluez.
     let agent_manager = Proxy::new(
@@ -113,7 +114,7 @@ pub async fn register_agent(
     match Proxy::new(
         &connection,
         BLUEZ_SERVICE,
-        ADAPTER_PATH,
+        adapter_path,
         DBUS_PROPERTIES_IFACE,
     )
     .await
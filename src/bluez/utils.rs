@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use std::sync::Arc;
 use zbus::object_server::Interface;
 use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
-use zbus::{Connection, Error};
+use zbus::Connection;
 
 pub trait ObjectPathTrait {
     fn object_path(&self) -> String;
@@ -49,20 +49,6 @@ where
     result
 }
 
-pub async fn find_adapter(connection: &Connection) -> Result<String, Error> {
-    let proxy = zbus::fdo::ObjectManagerProxy::new(connection, "org.bluez", "/").await?;
-    let objects = proxy.get_managed_objects().await?;
-
-    for (path, interfaces) in objects {
-        if interfaces.contains_key("org.bluez.GattManager1") {
-            println!("Found GattManager1 at path: {}", path);
-            return Ok(path.to_string());
-        }
-    }
-
-    panic!("No adapter found");
-}
-
 pub type ObjectProperties = HashMap<String, OwnedValue>;
 
 pub type InterfaceProperties = HashMap<String, ObjectProperties>;
@@ -0,0 +1,258 @@
+//! Per-band calibration of the gains.
+//!
+//! The hand-tuned `gains` only suit the microphone, room and speakers they were tuned with.
+//! A calibration run measures the room instead: first `seconds` of silence, then `seconds` of
+//! reference music (anything with a balanced spectrum, or pink noise), both through the live
+//! input. The mean power of every band in the silence is its noise floor, and the band's noise
+//! gate is set just above it; what the music adds on top of it is the band's response, and the
+//! gains are chosen so every band responds alike.
+//!
+//! The DSP thread runs the measurement on the band levels it already computes, before the noise
+//! gate, the weighting and the gains; the render loop applies the result to the settings and
+//! has it saved to the active preset.
+
+use crate::constants::{CALIBRATION_GATE_MARGIN_DB, CALIBRATION_MAX_GAIN, CALIBRATION_MIN_GAIN, CALIBRATION_MIN_SNR_DB, DEFAULT_NOISE_GATE_DB};
+use crate::dsp::{band_weight, to_db};
+use crate::presets::{load_preset, save_preset, Preset};
+use crate::settings::{resample_bands, Settings};
+use std::sync::Arc;
+
+/// Asks the DSP thread for a run. Each new `run` starts one; a `seconds` of 0 cancels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CalibrationRequest {
+    pub run: u32,
+    /// Length of each of the two phases.
+    pub seconds: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum CalibrationPhase {
+    #[default]
+    Idle = 0,
+    /// Measuring the room with no music playing.
+    Noise = 1,
+    /// Measuring the reference music.
+    Music = 2,
+    Done = 3,
+    Cancelled = 4,
+    /// The music was not clearly louder than the noise in any band.
+    Failed = 5,
+}
+
+/// Gains and noise floors measured by a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalibrationResult {
+    pub run: u32,
+    pub gains: Vec<f32>,
+    /// Noise floor of every band, in dBFS like `Settings::noise_gate_db`.
+    pub noise_db: Vec<f32>,
+}
+
+/// Where the current or last run is, reported over BLE.
+#[derive(Debug, Clone, Default)]
+pub struct CalibrationStatus {
+    pub phase: CalibrationPhase,
+    /// Share of the current phase done, 0..1.
+    pub progress: f32,
+    pub result: Option<Arc<CalibrationResult>>,
+}
+
+/// Runs on the DSP thread, fed once per spectrum.
+#[derive(Debug, Clone, Default)]
+pub struct Calibrator {
+    pub status: CalibrationStatus,
+    run: u32,
+    seconds: f32,
+    elapsed: f32,
+    /// Summed power of every band over the noise then the music phase.
+    noise: Vec<f64>,
+    music: Vec<f64>,
+    noise_spectra: usize,
+    music_spectra: usize,
+}
+
+impl Calibrator {
+    /// Feed the `levels` of one spectrum, `elapsed` seconds after the previous one, following
+    /// `settings.calibration`. `full_scale` is the 0 dBFS level of a band. The new gains
+    /// flatten the response as weighted by `settings.weighting`.
+    pub fn update(&mut self, levels: &[f32], elapsed: f32, full_scale: f32, settings: &Settings) {
+        let request = &settings.calibration;
+        if request.run != self.run {
+            self.run = request.run;
+            if request.seconds > 0.0 {
+                println!("Calibration: measuring the noise for {} s, keep the room quiet", request.seconds);
+                *self = Calibrator {
+                    status: CalibrationStatus { phase: CalibrationPhase::Noise, progress: 0.0, result: self.status.result.clone() },
+                    run: request.run,
+                    seconds: request.seconds,
+                    noise: vec![0.0; levels.len()],
+                    music: vec![0.0; levels.len()],
+                    ..Calibrator::default()
+                };
+            } else if matches!(self.status.phase, CalibrationPhase::Noise | CalibrationPhase::Music) {
+                println!("Calibration cancelled");
+                self.status.phase = CalibrationPhase::Cancelled;
+            }
+        }
+
+        let (sums, spectra) = match self.status.phase {
+            CalibrationPhase::Noise => (&mut self.noise, &mut self.noise_spectra),
+            CalibrationPhase::Music => (&mut self.music, &mut self.music_spectra),
+            _ => return,
+        };
+        if sums.len() != levels.len() {
+            println!("Calibration cancelled: the bands changed");
+            self.status.phase = CalibrationPhase::Cancelled;
+            return;
+        }
+        for (sum, &level) in sums.iter_mut().zip(levels) {
+            *sum += (level as f64).powi(2);
+        }
+        *spectra += 1;
+        self.elapsed += elapsed;
+        self.status.progress = (self.elapsed / self.seconds).min(1.0);
+        if self.elapsed < self.seconds {
+            return;
+        }
+
+        self.elapsed = 0.0;
+        self.status.progress = 0.0;
+        if self.status.phase == CalibrationPhase::Noise {
+            println!("Calibration: play the reference music now, measuring for {} s", self.seconds);
+            self.status.phase = CalibrationPhase::Music;
+            return;
+        }
+        let mean = |sums: &[f64], spectra: usize| -> Vec<f32> {
+            sums.iter().map(|sum| (sum / spectra.max(1) as f64) as f32).collect()
+        };
+        let noise = mean(&self.noise, self.noise_spectra);
+        let music = mean(&self.music, self.music_spectra);
+        let weighted = |powers: &[f32]| -> Vec<f32> {
+            powers.iter().zip(&settings.frequencies)
+                .map(|(power, &centre)| power * band_weight(centre, settings).powi(2))
+                .collect()
+        };
+        match flattening_gains(&weighted(&noise), &weighted(&music), &settings.gains) {
+            Some(gains) => {
+                let noise_db = noise.iter().map(|power| to_db(power.sqrt() / full_scale)).collect();
+                println!("Calibration done, gains {:?}", gains);
+                self.status.phase = CalibrationPhase::Done;
+                self.status.result = Some(Arc::new(CalibrationResult { run: self.run, gains, noise_db }));
+            }
+            None => {
+                println!("Calibration failed: the music was not louder than the noise");
+                self.status.phase = CalibrationPhase::Failed;
+            }
+        }
+    }
+}
+
+/// Gains that bring every band's response to the music above the noise (`music` minus `noise`,
+/// mean powers) to the same level, their geometric mean, so the overall gain stays put. Bands
+/// without `CALIBRATION_MIN_SNR_DB` of music above their noise keep their `current` gain.
+/// `None` if no band qualifies.
+pub fn flattening_gains(noise: &[f32], music: &[f32], current: &[f32]) -> Option<Vec<f32>> {
+    let min_ratio = 10f32.powf(CALIBRATION_MIN_SNR_DB / 10.0);
+    let responses: Vec<Option<f32>> = noise.iter().zip(music)
+        .map(|(&noise, &music)| (music > 0.0 && music >= noise * min_ratio).then(|| (music - noise).sqrt()))
+        .collect();
+    let measured: Vec<f32> = responses.iter().flatten().copied().collect();
+    if measured.is_empty() {
+        return None;
+    }
+    let reference = (measured.iter().map(|response| response.ln()).sum::<f32>() / measured.len() as f32).exp();
+    Some(responses.iter().enumerate()
+        .map(|(band, response)| match response {
+            Some(response) => (reference / response).clamp(CALIBRATION_MIN_GAIN, CALIBRATION_MAX_GAIN),
+            None => current.get(band).copied().unwrap_or(1.0),
+        })
+        .collect())
+}
+
+/// Noise gates of a run: `CALIBRATION_GATE_MARGIN_DB` above each band's noise floor, so the
+/// room noise alone leaves the strips dark.
+pub fn calibrated_noise_gates(result: &CalibrationResult) -> Vec<f32> {
+    result.noise_db.iter().map(|floor| (floor + CALIBRATION_GATE_MARGIN_DB).max(DEFAULT_NOISE_GATE_DB)).collect()
+}
+
+/// Use the gains and noise gates of `result` from now on. Only touches memory, so it can run
+/// under the settings lock; returns the active preset as it now stands for `save_calibration`,
+/// or `None` without one.
+pub fn apply_calibration(result: &CalibrationResult, settings: &mut Settings) -> Option<Preset> {
+    let frequencies = settings.frequencies.clone();
+    settings.set_bands(&frequencies, &result.gains);
+    settings.set_noise_gates(&calibrated_noise_gates(result));
+    if settings.active_preset >= 255 {
+        println!("Calibration: no active preset to save the gains to");
+        return None;
+    }
+    Some(Preset::from_settings(settings, settings.active_preset as u8, *b"Calibrated\0\0\0\0\0\0"))
+}
+
+/// Store the gains and noise gates of `calibrated` in the saved preset of the same index, which
+/// keeps its other settings; `calibrated` is saved as is if there is none yet.
+/// Disk I/O: call it without holding the settings lock.
+pub fn save_calibration(calibrated: Preset) {
+    let index = calibrated.index;
+    let preset = match load_preset(index) {
+        Ok(mut preset) => {
+            let bands = preset.frequencies.len();
+            preset.gains = resample_bands(&calibrated.gains, bands, false);
+            preset.noise_gate_db = resample_bands(&calibrated.noise_gate_db, bands, false);
+            preset
+        }
+        Err(_) => calibrated,
+    };
+    if let Err(e) = save_preset(&preset) {
+        eprintln!("Calibration: cannot save preset {}: {}", index, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gains_even_out_the_response_above_the_noise() {
+        // Band 1 answers twice as loud as band 0; band 2 is lost in its noise
+        let gains = flattening_gains(&[0.01, 0.01, 1.0], &[1.01, 4.01, 1.1], &[1.0, 1.0, 3.0]).unwrap();
+        assert!((gains[0] * 1.0 - gains[1] * 2.0).abs() < 1e-4, "{:?}", gains);
+        assert!((gains[0] * gains[1] - 1.0).abs() < 1e-4, "{:?}", gains);
+        assert_eq!(gains[2], 3.0);
+        assert_eq!(flattening_gains(&[1.0], &[1.0], &[1.0]), None);
+    }
+
+    #[test]
+    fn a_run_measures_the_noise_then_the_music() {
+        let mut settings = Settings { skew: 0.0, ..Settings::default() }; // flat weighting
+        settings.frequencies = vec![100.0, 1000.0];
+        settings.gains = vec![1.0, 1.0];
+        settings.calibration = CalibrationRequest { run: 1, seconds: 1.0 };
+        let mut calibrator = Calibrator::default();
+        for _ in 0..4 {
+            calibrator.update(&[0.1, 0.1], 0.25, 1.0, &settings);
+        }
+        assert_eq!(calibrator.status.phase, CalibrationPhase::Music);
+        for _ in 0..4 {
+            calibrator.update(&[1.0, 2.0], 0.25, 1.0, &settings);
+        }
+        assert_eq!(calibrator.status.phase, CalibrationPhase::Done);
+        let result = calibrator.status.result.clone().unwrap();
+        assert!(result.gains[0] > 1.0 && result.gains[1] < 1.0, "{:?}", result.gains);
+        assert!((result.noise_db[0] + 20.0).abs() < 0.01, "{:?}", result.noise_db);
+
+        settings.calibration = CalibrationRequest { run: 2, seconds: 1.0 };
+        calibrator.update(&[0.1, 0.1], 0.1, 1.0, &settings);
+        settings.calibration = CalibrationRequest { run: 3, seconds: 0.0 };
+        calibrator.update(&[0.1, 0.1], 0.1, 1.0, &settings);
+        assert_eq!(calibrator.status.phase, CalibrationPhase::Cancelled);
+        // The last result stays available
+        assert_eq!(calibrator.status.result, Some(result.clone()));
+
+        // Gates just above the measured floors, gains as measured
+        let preset = apply_calibration(&result, &mut settings);
+        assert!(preset.is_none(), "no active preset");
+        assert_eq!(settings.noise_gate_db, vec![-20.0 + CALIBRATION_GATE_MARGIN_DB; settings.geometry.strips]);
+        assert_eq!(settings.gains.len(), settings.geometry.strips);
+    }
+}
@@ -0,0 +1,223 @@
+//! Pitch classes, chords and key.
+//!
+//! Every FFT bin between `MIN_HZ` and `MAX_HZ` is mapped to the nearest equal-tempered note and
+//! its energy added to that note's pitch class, C to B, whatever the octave. Bins closer together
+//! than a semitone are left out since they cannot tell neighbouring notes apart, so with larger
+//! FFTs the chroma reaches further down into the bass. The chroma is smoothed twice: over a
+//! fraction of a second to name the chord being played, by matching it against the major and
+//! minor triads, and over many seconds to name the key, by correlating it with the
+//! Krumhansl-Kessler key profiles.
+
+use crate::values::Envelope;
+use std::fmt;
+
+pub const PITCH_CLASSES: usize = 12;
+pub const NOTE_NAMES: [&str; PITCH_CLASSES] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+
+const A4_HZ: f32 = 440.0;
+const A4_MIDI: f32 = 69.0;
+const MIN_HZ: f32 = 50.0;
+const MAX_HZ: f32 = 5000.0;
+/// Smoothing (ms) of the chroma the chord is named from, and of the one the key is named from.
+const CHORD_SMOOTH_MS: f32 = 300.0;
+const KEY_SMOOTH_MS: f32 = 15_000.0;
+/// Strongest pitch class (dB relative to a full-scale sine) below which nothing is named.
+const MIN_LEVEL_DB: f32 = -60.0;
+/// Cosine similarity a triad needs with the chroma to be named.
+const MIN_CHORD_MATCH: f32 = 0.6;
+
+/// How well each scale degree fits a major and a minor key (Krumhansl & Kessler, 1982).
+const MAJOR_PROFILE: [f32; PITCH_CLASSES] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f32; PITCH_CLASSES] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+/// A major or minor chord or key, named after its root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tonality {
+    /// Pitch class of the root, 0 = C.
+    pub root: u8,
+    pub minor: bool,
+}
+
+impl Tonality {
+    /// Pitch classes of the triad on the root.
+    pub fn triad(&self) -> [usize; 3] {
+        let root = self.root as usize;
+        let third = if self.minor { 3 } else { 4 };
+        [root, (root + third) % PITCH_CLASSES, (root + 7) % PITCH_CLASSES]
+    }
+
+    /// `root + 12 × minor`, so 0–23; 255 stands for none over BLE.
+    pub fn to_u8(tonality: Option<Tonality>) -> u8 {
+        tonality.map_or(u8::MAX, |t| t.root + if t.minor { PITCH_CLASSES as u8 } else { 0 })
+    }
+}
+
+impl fmt::Display for Tonality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", NOTE_NAMES[self.root as usize], if self.minor { "m" } else { "" })
+    }
+}
+
+/// The harmony as the render loop sees it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Chroma {
+    /// Energy of each pitch class relative to the strongest one (0..1), C first.
+    pub classes: [f32; PITCH_CLASSES],
+    pub chord: Option<Tonality>,
+    pub key: Option<Tonality>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChromaTracker {
+    pub chroma: Chroma,
+    chord_energy: [Envelope; PITCH_CLASSES],
+    key_energy: [Envelope; PITCH_CLASSES],
+}
+
+impl ChromaTracker {
+    /// Feed one magnitude spectrum of bins `df` Hz apart, where a full-scale sine reads
+    /// `full_scale`, `elapsed` seconds after the previous one.
+    pub fn update(&mut self, magnitudes: &[f32], df: f32, full_scale: f32, elapsed: f32) {
+        let energy = pitch_class_energy(magnitudes, df, full_scale);
+        let mut chord_energy = [0.0; PITCH_CLASSES];
+        let mut key_energy = [0.0; PITCH_CLASSES];
+        for pitch_class in 0..PITCH_CLASSES {
+            let input = energy[pitch_class];
+            chord_energy[pitch_class] = self.chord_energy[pitch_class].update(input, elapsed, CHORD_SMOOTH_MS, CHORD_SMOOTH_MS);
+            key_energy[pitch_class] = self.key_energy[pitch_class].update(input, elapsed, KEY_SMOOTH_MS, KEY_SMOOTH_MS);
+        }
+
+        let strongest = chord_energy.iter().fold(0.0f32, |acc, &e| acc.max(e));
+        let audible = |energy: &[f32; PITCH_CLASSES]| 10.0 * energy.iter().fold(0.0f32, |acc, &e| acc.max(e)).log10() >= MIN_LEVEL_DB;
+        self.chroma.classes = if strongest > 0.0 { chord_energy.map(|e| e / strongest) } else { [0.0; PITCH_CLASSES] };
+        self.chroma.chord = if audible(&chord_energy) { best_chord(&chord_energy) } else { None };
+        self.chroma.key = if audible(&key_energy) { best_key(&key_energy) } else { None };
+    }
+}
+
+/// Pitch class of the note nearest to `hz`.
+pub fn pitch_class(hz: f32) -> usize {
+    let midi = (A4_MIDI + 12.0 * (hz / A4_HZ).log2()).round() as i32;
+    midi.rem_euclid(PITCH_CLASSES as i32) as usize
+}
+
+/// Energy of the bins of each pitch class, relative to a full-scale sine.
+fn pitch_class_energy(magnitudes: &[f32], df: f32, full_scale: f32) -> [f32; PITCH_CLASSES] {
+    let semitone = 2f32.powf(1.0 / 12.0) - 1.0;
+    let mut energy = [0.0; PITCH_CLASSES];
+    for (k, magnitude) in magnitudes.iter().enumerate() {
+        let hz = k as f32 * df;
+        if !(MIN_HZ..=MAX_HZ).contains(&hz) || df > hz * semitone {
+            continue;
+        }
+        let level = magnitude / full_scale;
+        energy[pitch_class(hz)] += level * level;
+    }
+    energy
+}
+
+/// Major or minor triad whose notes best match `energy`, if any matches well enough.
+fn best_chord(energy: &[f32; PITCH_CLASSES]) -> Option<Tonality> {
+    let norm = energy.iter().map(|e| e * e).sum::<f32>().sqrt();
+    if norm <= 0.0 {
+        return None;
+    }
+    let (chord, score) = tonalities()
+        .map(|chord| {
+            // Cosine against a template with 1 on each note of the triad
+            let score = chord.triad().iter().map(|&pitch_class| energy[pitch_class]).sum::<f32>() / (norm * 3f32.sqrt());
+            (chord, score)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    (score >= MIN_CHORD_MATCH).then_some(chord)
+}
+
+/// Key whose profile correlates best with `energy`.
+fn best_key(energy: &[f32; PITCH_CLASSES]) -> Option<Tonality> {
+    tonalities()
+        .map(|key| {
+            let profile = if key.minor { &MINOR_PROFILE } else { &MAJOR_PROFILE };
+            let rotated: Vec<f32> = (0..PITCH_CLASSES).map(|pc| profile[(pc + PITCH_CLASSES - key.root as usize) % PITCH_CLASSES]).collect();
+            (key, correlation(energy, &rotated))
+        })
+        .filter(|(_, score)| score.is_finite())
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(key, _)| key)
+}
+
+/// The 12 major then the 12 minor tonalities.
+fn tonalities() -> impl Iterator<Item = Tonality> {
+    [false, true].into_iter().flat_map(|minor| (0..PITCH_CLASSES as u8).map(move |root| Tonality { root, minor }))
+}
+
+/// Pearson correlation of two equally long series.
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / a.len() as f32;
+    let mean_b = b.iter().sum::<f32>() / b.len() as f32;
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a) * (x - mean_a);
+        variance_b += (y - mean_b) * (y - mean_b);
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+/// Default note-to-hue palette (degrees): notes a fifth apart are a twelfth of the colour wheel
+/// apart, so related chords and keys get neighbouring colours. C is red, G orange, D yellow…
+pub fn circle_of_fifths_hues() -> [f32; PITCH_CLASSES] {
+    std::array::from_fn(|pitch_class| ((pitch_class * 7) % PITCH_CLASSES) as f32 * 30.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DF: f32 = 44100.0 / 4096.0;
+
+    /// Spectrum with a full-scale peak on the bin nearest to each of `notes` (Hz).
+    fn spectrum(notes: &[f32]) -> Vec<f32> {
+        let mut magnitudes = vec![0.0; 2048];
+        for note in notes {
+            magnitudes[(note / DF).round() as usize] = 1.0;
+        }
+        magnitudes
+    }
+
+    fn track(notes: &[f32], seconds: f32) -> Chroma {
+        let mut tracker = ChromaTracker::default();
+        for _ in 0..(seconds * 100.0) as usize {
+            tracker.update(&spectrum(notes), DF, 1.0, 0.01);
+        }
+        tracker.chroma
+    }
+
+    #[test]
+    fn notes_fold_onto_their_pitch_class() {
+        assert_eq!(pitch_class(440.0), 9);
+        assert_eq!(pitch_class(55.0), 9);
+        assert_eq!(pitch_class(261.63), 0);
+        assert_eq!(pitch_class(1046.5), 0);
+        assert_eq!(pitch_class(392.0 * 1.02), 7);
+    }
+
+    #[test]
+    fn names_triads_and_keys() {
+        // A minor over two octaves
+        let chroma = track(&[220.0, 261.63, 329.63, 440.0, 523.25, 659.26], 1.0);
+        assert_eq!(chroma.chord, Some(Tonality { root: 9, minor: true }));
+        assert_eq!(chroma.classes[9], 1.0);
+        assert!(chroma.classes[1] < 0.01);
+        assert_eq!(chroma.chord.unwrap().to_string(), "Am");
+
+        // The C major scale, tonic and fifth leaning
+        let chroma = track(&[261.63, 261.63 * 2.0, 293.66, 329.63, 349.23, 392.0, 392.0 * 2.0, 440.0, 493.88], 20.0);
+        assert_eq!(chroma.key, Some(Tonality { root: 0, minor: false }));
+    }
+
+    #[test]
+    fn silence_names_nothing() {
+        let chroma = track(&[], 1.0);
+        assert_eq!(chroma, Chroma::default());
+    }
+}
@@ -51,6 +51,53 @@ impl Color {
         }
     }
 
+    /// Fully saturated colour of `hue` degrees on the colour wheel (0 red, 120 green, 240 blue).
+    pub fn from_hue(hue: f32) -> Color {
+        Color::from_hsv(hue, 1.0, 1.0)
+    }
+
+    /// Colour of `hue` degrees, `saturation` and `value` (0..1), where a value of 1 is 254.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+        let max = 254.0 * value.clamp(0.0, 1.0);
+        let min = max * (1.0 - saturation.clamp(0.0, 1.0));
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let rising = min + (max - min) * sector.fract();
+        let falling = max - (max - min) * sector.fract();
+        let (r, g, b) = match sector as u8 {
+            0 => (max, rising, min),
+            1 => (falling, max, min),
+            2 => (min, max, rising),
+            3 => (min, falling, max),
+            4 => (rising, min, max),
+            _ => (max, min, falling),
+        };
+        Color::new(r.round() as u8, g.round() as u8, b.round() as u8)
+    }
+
+    /// Hue (degrees), saturation and value (0..1) of this colour.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.r as f32, self.g as f32, self.b as f32);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        let saturation = if max > 0.0 { delta / max } else { 0.0 };
+        (hue, saturation, (max / 254.0).min(1.0))
+    }
+
+    /// The same colour turned `degrees` around the colour wheel.
+    pub fn rotate_hue(&self, degrees: f32) -> Color {
+        let (hue, saturation, value) = self.to_hsv();
+        Color::from_hsv(hue + degrees, saturation, value)
+    }
+
     pub fn to_rgb_888(&self) -> Vec<u8> {
         vec![self.r, self.g, self.b]
     }
@@ -125,4 +172,25 @@ pub fn color_from_string(color_str: &str) -> Color {
         "light_pink" => LIGHT_PINK,
         _ => WHITE, // Default to white if color not recognized
     }
+}
+
+/// Strict variant of `color_from_string` that also accepts `#rrggbb`, for user supplied config.
+pub fn parse_color(color_str: &str) -> Result<Color, String> {
+    if let Some(hex) = color_str.strip_prefix('#') {
+        return match u32::from_str_radix(hex, 16) {
+            Ok(rgb) if hex.len() == 6 => Ok(Color::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)),
+            _ => Err(format!("Invalid color '{}' (expected #rrggbb)", color_str)),
+        };
+    }
+    match color_str.to_lowercase().as_str() {
+        "white" => Ok(WHITE),
+        name => {
+            let color = color_from_string(name);
+            if color.to_rgb_888() == WHITE.to_rgb_888() {
+                Err(format!("Unknown color '{}' (expected a color name such as blue or light_green, or #rrggbb)", color_str))
+            } else {
+                Ok(color)
+            }
+        }
+    }
 }
\ No newline at end of file
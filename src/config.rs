@@ -0,0 +1,850 @@
+//! Startup configuration.
+//!
+//! Everything that used to be a constant or a command line flag can be set in a TOML file.
+//! Values are resolved once at boot, each layer overriding the previous one:
+//!
+//! ```text
+//! 1. built-in defaults (constants.rs)
+//! 2. config file: --config <path>, or /etc/audioleds/config.toml when it exists
+//! 3. command line flags
+//! ```
+//!
+//! Every key of the file is optional, see `config.example.toml` for the full list.
+//! Invalid values are reported with the offending key and flag and stop the program,
+//! instead of silently falling back to a default.
+
+use crate::audio::{AudioFormat, AudioInput, Pacing, PcmStream, RawSampleFormat};
+use crate::bands::BandLayout;
+use crate::calibration::CalibrationRequest;
+use crate::color::parse_color;
+use crate::constants::{
+    ADAPTER_PATH, BAUD, DEFAULT_ADVERTISED_NAME, DEFAULT_AGC_ATTACK_MS, DEFAULT_AGC_MAX_GAIN, DEFAULT_AGC_MIN_GAIN,
+    DEFAULT_AGC_RELEASE_MS, DEFAULT_AGC_TARGET, DEFAULT_ATTACK_MS, DEFAULT_CONFIG_PATH, DEFAULT_DB_CEILING, DEFAULT_DB_FLOOR,
+    DEFAULT_LEDS_PER_STRIP, DEFAULT_NOISE_GATE_DB, DEFAULT_NUM_STRIPS, DEFAULT_PEAK_DECAY, DEFAULT_PEAK_HOLD_MS,
+    DEFAULT_PRESET_PATH, DEFAULT_RELEASE_MS, DEFAULT_SKEW, DEFAULT_SMOOTH_SIZE, DEFAULT_STROBE_DECAY_MS, DEFAULT_STROBE_MAX_DELTA,
+    DEFAULT_STROBE_MAX_RATE, DEFAULT_WEIGHTING_PHON, FFT_SIZE, FPS, GAIN, HOP_SIZE,
+    MAX_CALIBRATION_SECONDS, MAX_FFT_SIZE, MAX_HOP_SIZE, MAX_SMOOTH_SIZE, MAX_WEIGHTING_PHON, MIN_FFT_SIZE, MIN_HOP_SIZE, MIN_WEIGHTING_PHON, PORT,
+};
+use crate::geometry::{LedGeometry, StartCorner};
+use crate::settings::{AnimationMode, BeatEffect, ColorModulation, DisplayMode, FftWindow, Settings, SpectralFeature, Transform, Weighting, is_valid_fft_size};
+use crate::sinks::SinkSpec;
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, message: String },
+    MissingValue(String),
+    UnknownOption(String),
+    Invalid { key: &'static str, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "Cannot read config file {}: {}", path, source),
+            ConfigError::Parse { path, message } => write!(f, "Invalid config file {}: {}", path, message),
+            ConfigError::MissingValue(flag) => write!(f, "Missing value after {}", flag),
+            ConfigError::UnknownOption(arg) => write!(f, "Unknown option '{}'", arg),
+            ConfigError::Invalid { key, message } => write!(f, "Invalid value for {}: {}", key, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(key: &'static str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid { key, message: message.into() }
+}
+
+fn ensure(condition: bool, key: &'static str, message: &str) -> Result<(), ConfigError> {
+    if condition { Ok(()) } else { Err(invalid(key, message)) }
+}
+
+/// Everything resolved from defaults, config file and command line.
+#[derive(Debug)]
+pub struct AppConfig {
+    /// Config file that was read, if any.
+    pub source: Option<String>,
+    pub settings: Settings,
+    pub sink: SinkSpec,
+    pub audio_input: AudioInput,
+    pub adapter_path: String,
+    pub advertised_name: String,
+    pub preset_dir: String,
+    /// `--bench_callback`: measure the audio callback instead of running the visualizer.
+    pub bench_callback: bool,
+    /// `--list_devices`: print the capture devices and exit.
+    pub list_devices: bool,
+}
+
+// ---------------------------------------------------------------------------
+// File layout
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub hardware: HardwareConfig,
+    pub audio: AudioConfig,
+    pub bluetooth: BluetoothConfig,
+    pub visual: VisualConfig,
+    pub agc: AgcConfig,
+    pub strobe: StrobeConfig,
+    pub presets: PresetsConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HardwareConfig {
+    /// `serial`, `file:<path>`, `stdout`, `udp:<host>:<port>` or `null`, see `sinks.rs`.
+    pub output: Option<String>,
+    pub port: Option<String>,
+    pub baud: Option<u32>,
+    pub strips: Option<usize>,
+    pub leds_per_strip: Option<usize>,
+    pub start_corner: Option<String>,
+    /// `serpentine` or `progressive`.
+    pub wiring: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    /// Name or index (see `--list_devices`) of the capture device.
+    pub device: Option<String>,
+    /// cpal host, e.g. `alsa` or `jack`.
+    pub host: Option<String>,
+    pub input: Option<String>,
+    pub raw_format: Option<String>,
+    pub raw_rate: Option<u32>,
+    pub raw_channels: Option<u16>,
+    pub fast: Option<bool>,
+    pub fft_size: Option<usize>,
+    pub window: Option<String>,
+    pub transform: Option<String>,
+    pub hop_size: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BluetoothConfig {
+    /// Adapter name (`hci0`) or object path (`/org/bluez/hci0`).
+    pub adapter: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VisualConfig {
+    pub smooth_size: Option<usize>,
+    pub attack_ms: Option<usize>,
+    pub release_ms: Option<usize>,
+    /// Per-band attack and release times (ms), resampled to the number of strips.
+    pub band_attack_ms: Option<Vec<f32>>,
+    pub band_release_ms: Option<Vec<f32>>,
+    pub gain: Option<f32>,
+    pub fps: Option<usize>,
+    pub color1: Option<String>,
+    pub color2: Option<String>,
+    pub color3: Option<String>,
+    pub skew: Option<f32>,
+    pub weighting: Option<String>,
+    pub weighting_phon: Option<f32>,
+    pub db_scale: Option<bool>,
+    pub db_floor: Option<f32>,
+    pub db_ceiling: Option<f32>,
+    pub noise_gate_db: Option<f32>,
+    /// Per-band noise gates (dBFS), resampled to the number of strips; replaces `noise_gate_db`.
+    pub band_noise_gate_db: Option<Vec<f32>>,
+    pub brightness: Option<f32>,
+    pub display_mode: Option<String>,
+    pub animation_mode: Option<String>,
+    pub peak_hold_ms: Option<usize>,
+    pub peak_decay: Option<f32>,
+    pub beat_effect: Option<String>,
+    pub color_modulation: Option<String>,
+    pub modulation_feature: Option<String>,
+    pub stereo_split: Option<bool>,
+    /// Generated band centres, `<scale>:<min Hz>-<max Hz>`; excludes `frequencies`.
+    pub bands: Option<String>,
+    pub frequencies: Option<Vec<f32>>,
+    pub gains: Option<Vec<f32>>,
+    pub chroma_hues: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgcConfig {
+    pub enabled: Option<bool>,
+    pub target: Option<f32>,
+    pub attack_ms: Option<usize>,
+    pub release_ms: Option<usize>,
+    pub min_gain: Option<f32>,
+    pub max_gain: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StrobeConfig {
+    pub decay_ms: Option<usize>,
+    pub limiter: Option<bool>,
+    pub max_rate: Option<f32>,
+    pub max_delta: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PresetsConfig {
+    pub directory: Option<String>,
+}
+
+/// Copy every field that is set in `$top` over `$base`.
+macro_rules! overlay {
+    ($base:expr, $top:expr, $($field:ident),+ $(,)?) => {
+        $( if $top.$field.is_some() { $base.$field = $top.$field; } )+
+    };
+}
+
+impl ConfigFile {
+    pub fn read(path: &str) -> Result<ConfigFile, ConfigError> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|source| ConfigError::Io { path: path.to_string(), source })?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse { path: path.to_string(), message: e.to_string() })
+    }
+
+    /// Layer `top` over `self`: every value set in `top` wins.
+    pub fn merge(&mut self, top: ConfigFile) {
+        overlay!(self.hardware, top.hardware, output, port, baud, strips, leds_per_strip, start_corner, wiring);
+        overlay!(self.audio, top.audio, device, host, input, raw_format, raw_rate, raw_channels, fast, fft_size, window, transform, hop_size);
+        overlay!(self.bluetooth, top.bluetooth, adapter, name);
+        overlay!(
+            self.visual, top.visual,
+            smooth_size, attack_ms, release_ms, band_attack_ms, band_release_ms, gain, fps, color1, color2, color3, skew, weighting, weighting_phon, db_scale, db_floor, db_ceiling, noise_gate_db, band_noise_gate_db,
+            brightness, display_mode, animation_mode,
+            peak_hold_ms, peak_decay, beat_effect, color_modulation, modulation_feature, stereo_split, bands, frequencies, gains, chroma_hues,
+        );
+        overlay!(self.agc, top.agc, enabled, target, attack_ms, release_ms, min_gain, max_gain);
+        overlay!(self.strobe, top.strobe, decay_ms, limiter, max_rate, max_delta);
+        overlay!(self.presets, top.presets, directory);
+    }
+
+    /// Validate every value and apply them over the built-in defaults.
+    pub fn resolve(self, source: Option<String>) -> Result<AppConfig, ConfigError> {
+        let ConfigFile { hardware, audio, bluetooth, visual, agc, strobe, presets } = self;
+        let mut settings = Settings::default();
+
+        // --- Hardware ---
+        let mut geometry = LedGeometry::default();
+        if let Some(strips) = hardware.strips {
+            ensure(strips >= 1, "hardware.strips (--strips)", "must be at least 1")?;
+            geometry.strips = strips;
+        }
+        if let Some(leds_per_strip) = hardware.leds_per_strip {
+            ensure(leds_per_strip >= 1, "hardware.leds_per_strip (--leds_per_strip)", "must be at least 1")?;
+            geometry.leds_per_strip = leds_per_strip;
+        }
+        ensure(
+            geometry.num_leds() <= u16::MAX as usize,
+            "hardware.strips × hardware.leds_per_strip",
+            "more than 65535 LEDs",
+        )?;
+        if let Some(corner) = hardware.start_corner {
+            geometry.start_corner = StartCorner::from_str(&corner).map_err(|e| invalid("hardware.start_corner (--start_corner)", e))?;
+        }
+        if let Some(wiring) = hardware.wiring {
+            geometry.serpentine = match wiring.as_str() {
+                "serpentine" => true,
+                "progressive" => false,
+                _ => return Err(invalid("hardware.wiring (--wiring)", format!("'{}' (expected serpentine or progressive)", wiring))),
+            };
+        }
+
+        let sink = match hardware.output.as_deref() {
+            None | Some("serial") => {
+                let port = hardware.port.unwrap_or_else(|| PORT.to_string());
+                let baud = hardware.baud.unwrap_or(BAUD);
+                ensure(!port.is_empty(), "hardware.port (--port)", "must not be empty")?;
+                ensure(baud > 0, "hardware.baud (--baud)", "must be greater than 0")?;
+                SinkSpec::Serial { port, baud }
+            }
+            Some(output) => SinkSpec::from_str(output).map_err(|e| invalid("hardware.output (--output)", e))?,
+        };
+
+        // --- Audio ---
+        if let Some(fft_size) = audio.fft_size {
+            ensure(
+                is_valid_fft_size(fft_size),
+                "audio.fft_size (--fft_size)",
+                &format!("{} (expected a power of two between {} and {})", fft_size, MIN_FFT_SIZE, MAX_FFT_SIZE),
+            )?;
+            settings.set_fft_size(fft_size);
+        }
+        if let Some(window) = audio.window {
+            settings.fft_window = FftWindow::from_str(&window).map_err(|e| invalid("audio.window (--window)", e))?;
+        }
+        if let Some(transform) = audio.transform {
+            settings.transform = Transform::from_str(&transform).map_err(|e| invalid("audio.transform (--transform)", e))?;
+        }
+        if let Some(hop_size) = audio.hop_size {
+            ensure(
+                (MIN_HOP_SIZE..=MAX_HOP_SIZE).contains(&hop_size),
+                "audio.hop_size (--hop_size)",
+                &format!("{} (expected {} to {})", hop_size, MIN_HOP_SIZE, MAX_HOP_SIZE),
+            )?;
+            settings.hop_size = hop_size;
+        }
+
+        let pacing = if audio.fast.unwrap_or(false) { Pacing::Fast } else { Pacing::Realtime };
+        // Raw files and live streams carry no header: raw_* describe them
+        let raw = || -> Result<(RawSampleFormat, AudioFormat), ConfigError> {
+            let sample_format = match &audio.raw_format {
+                Some(raw_format) => RawSampleFormat::from_str(raw_format).map_err(|e| invalid("audio.raw_format (--raw_format)", e))?,
+                None => RawSampleFormat::S16,
+            };
+            let sample_rate = audio.raw_rate.unwrap_or(44100);
+            let channels = audio.raw_channels.unwrap_or(2);
+            ensure(sample_rate > 0, "audio.raw_rate (--raw_rate)", "must be greater than 0")?;
+            ensure(channels > 0, "audio.raw_channels (--raw_channels)", "must be greater than 0")?;
+            Ok((sample_format, AudioFormat { sample_rate, channels, sample_format: sample_format.sample_format() }))
+        };
+        let audio_input = match audio.input.clone() {
+            None => AudioInput::Device { host: audio.host.clone(), device: audio.device.clone() },
+            Some(input) => match PcmStream::from_input(&input) {
+                // L16 is 16-bit big-endian whatever raw_format says
+                Some(stream @ PcmStream::Rtp(_)) => {
+                    let (_, format) = raw()?;
+                    let format = AudioFormat { sample_format: RawSampleFormat::S16Be.sample_format(), ..format };
+                    AudioInput::Pcm { stream, sample_format: RawSampleFormat::S16Be, format }
+                }
+                Some(stream) => {
+                    let (sample_format, format) = raw()?;
+                    AudioInput::Pcm { stream, sample_format, format }
+                }
+                None if input.to_lowercase().ends_with(".wav") => AudioInput::Wav { path: input, pacing },
+                None => {
+                    let (sample_format, format) = raw()?;
+                    AudioInput::Raw { path: input, sample_format, format, pacing }
+                }
+            },
+        };
+
+        // --- Bluetooth ---
+        let adapter_path = match bluetooth.adapter {
+            None => ADAPTER_PATH.to_string(),
+            Some(adapter) if adapter.starts_with('/') => adapter,
+            Some(adapter) => format!("/org/bluez/{}", adapter),
+        };
+        if let Err(e) = zbus::zvariant::ObjectPath::try_from(adapter_path.as_str()) {
+            return Err(invalid("bluetooth.adapter (--adapter)", format!("'{}' is not a valid object path: {}", adapter_path, e)));
+        }
+        let advertised_name = bluetooth.name.unwrap_or_else(|| DEFAULT_ADVERTISED_NAME.to_string());
+        ensure(!advertised_name.is_empty(), "bluetooth.name (--ble_name)", "must not be empty")?;
+
+        // --- Visual ---
+        if let Some(smooth_size) = visual.smooth_size {
+            ensure(
+                (1..=MAX_SMOOTH_SIZE).contains(&smooth_size),
+                "visual.smooth_size (--smooth)",
+                &format!("{} (expected 1 to {})", smooth_size, MAX_SMOOTH_SIZE),
+            )?;
+            settings.smooth_size = smooth_size;
+        }
+        if let Some(attack_ms) = visual.attack_ms {
+            ensure(attack_ms <= u16::MAX as usize, "visual.attack_ms (--attack)", "must be at most 65535 ms")?;
+            settings.attack_ms = attack_ms;
+        }
+        if let Some(release_ms) = visual.release_ms {
+            ensure(release_ms <= u16::MAX as usize, "visual.release_ms (--release)", "must be at most 65535 ms")?;
+            settings.release_ms = release_ms;
+        }
+        let band_attack_ms = visual.band_attack_ms.unwrap_or_default();
+        let band_release_ms = visual.band_release_ms.unwrap_or_default();
+        let valid_times = |times: &[f32]| times.iter().all(|t| (0.0..=u16::MAX as f32).contains(t));
+        ensure(valid_times(&band_attack_ms), "visual.band_attack_ms", "every time must be within 0 to 65535 ms")?;
+        ensure(valid_times(&band_release_ms), "visual.band_release_ms", "every time must be within 0 to 65535 ms")?;
+        if let Some(gain) = visual.gain {
+            ensure(gain.is_finite() && gain >= 0.0, "visual.gain (--gain)", "must be a positive number")?;
+            settings.gain = gain;
+        }
+        if let Some(fps) = visual.fps {
+            ensure((1..=1000).contains(&fps), "visual.fps (--fps)", &format!("{} (expected 1 to 1000)", fps))?;
+            settings.fps = fps;
+        }
+        if let Some(color) = visual.color1 {
+            settings.color1 = parse_color(&color).map_err(|e| invalid("visual.color1 (--color1)", e))?;
+        }
+        if let Some(color) = visual.color2 {
+            settings.color2 = parse_color(&color).map_err(|e| invalid("visual.color2 (--color2)", e))?;
+        }
+        if let Some(color) = visual.color3 {
+            settings.color3 = parse_color(&color).map_err(|e| invalid("visual.color3 (--color3)", e))?;
+        }
+        if let Some(skew) = visual.skew {
+            ensure(skew.is_finite(), "visual.skew (--skew)", "must be a number")?;
+            settings.skew = skew;
+        }
+        if let Some(weighting) = visual.weighting {
+            settings.weighting = Weighting::from_str(&weighting).map_err(|e| invalid("visual.weighting (--weighting)", e))?;
+        }
+        if let Some(phon) = visual.weighting_phon {
+            ensure(
+                (MIN_WEIGHTING_PHON..=MAX_WEIGHTING_PHON).contains(&phon),
+                "visual.weighting_phon (--phon)",
+                &format!("{} (expected {} to {})", phon, MIN_WEIGHTING_PHON, MAX_WEIGHTING_PHON),
+            )?;
+            settings.weighting_phon = phon;
+        }
+        if let Some(db_scale) = visual.db_scale {
+            settings.db_scale = db_scale;
+        }
+        if let Some(db_floor) = visual.db_floor {
+            ensure(db_floor.is_finite(), "visual.db_floor (--db_floor)", "must be a number")?;
+            settings.db_floor = db_floor;
+        }
+        if let Some(db_ceiling) = visual.db_ceiling {
+            ensure(db_ceiling.is_finite(), "visual.db_ceiling (--db_ceiling)", "must be a number")?;
+            settings.db_ceiling = db_ceiling;
+        }
+        ensure(
+            settings.db_floor < settings.db_ceiling,
+            "visual.db_floor (--db_floor)",
+            &format!("{} dB is not below visual.db_ceiling {} dB", settings.db_floor, settings.db_ceiling),
+        )?;
+        if let Some(noise_gate_db) = visual.noise_gate_db {
+            ensure(noise_gate_db.is_finite(), "visual.noise_gate_db (--noise_gate)", "must be a number")?;
+            settings.set_noise_gates(&[noise_gate_db]);
+        }
+        if let Some(brightness) = visual.brightness {
+            ensure((0.0..=1.0).contains(&brightness), "visual.brightness (--brightness)", &format!("{} (expected 0.0 to 1.0)", brightness))?;
+            settings.brightness = brightness;
+        }
+        if let Some(mode) = visual.display_mode {
+            settings.display_mode = DisplayMode::from_str(&mode).map_err(|e| invalid("visual.display_mode (--display_mode)", e))?;
+        }
+        if let Some(mode) = visual.animation_mode {
+            settings.animation_mode = AnimationMode::from_str(&mode).map_err(|e| invalid("visual.animation_mode (--animation_mode)", e))?;
+        }
+        if let Some(peak_hold_ms) = visual.peak_hold_ms {
+            ensure(peak_hold_ms <= u16::MAX as usize, "visual.peak_hold_ms (--peak_hold)", "must be at most 65535 ms")?;
+            settings.peak_hold_ms = peak_hold_ms;
+        }
+        if let Some(peak_decay) = visual.peak_decay {
+            ensure(peak_decay.is_finite() && peak_decay >= 0.0, "visual.peak_decay (--peak_decay)", "must be a positive number")?;
+            settings.peak_decay = peak_decay;
+        }
+        if let Some(effect) = visual.beat_effect {
+            settings.beat_effect = BeatEffect::from_str(&effect).map_err(|e| invalid("visual.beat_effect (--beat_effect)", e))?;
+        }
+        if let Some(modulation) = visual.color_modulation {
+            settings.color_modulation = ColorModulation::from_str(&modulation).map_err(|e| invalid("visual.color_modulation (--color_mod)", e))?;
+        }
+        if let Some(feature) = visual.modulation_feature {
+            settings.modulation_feature = SpectralFeature::from_str(&feature).map_err(|e| invalid("visual.modulation_feature (--color_mod_feature)", e))?;
+        }
+        if let Some(stereo_split) = visual.stereo_split {
+            settings.stereo_split = stereo_split;
+        }
+
+        let custom_frequencies = visual.frequencies.is_some();
+        let frequencies = visual.frequencies.unwrap_or_else(|| settings.frequencies.clone());
+        let gains = visual.gains.unwrap_or_else(|| settings.gains.clone());
+        ensure(!frequencies.is_empty(), "visual.frequencies", "must not be empty")?;
+        ensure(!gains.is_empty(), "visual.gains", "must not be empty")?;
+        ensure(frequencies.iter().all(|f| f.is_finite() && *f > 0.0), "visual.frequencies", "every frequency must be greater than 0 Hz")?;
+        ensure(gains.iter().all(|g| g.is_finite() && *g >= 0.0), "visual.gains", "every gain must be a positive number")?;
+
+        settings.set_geometry(geometry);
+        settings.set_bands(&frequencies, &gains);
+        if let Some(hues) = visual.chroma_hues {
+            ensure(hues.iter().all(|hue| (0.0..=360.0).contains(hue)), "visual.chroma_hues", "every hue must be within 0 to 360 degrees")?;
+            settings.chroma_hues = hues.as_slice().try_into()
+                .map_err(|_| invalid("visual.chroma_hues", format!("{} hues given, one per pitch class (12) needed", hues.len())))?;
+        }
+        settings.set_band_envelopes(&band_attack_ms, &band_release_ms);
+        if let Some(gates_db) = visual.band_noise_gate_db {
+            ensure(!gates_db.is_empty(), "visual.band_noise_gate_db", "must not be empty")?;
+            ensure(gates_db.iter().all(|gate| gate.is_finite()), "visual.band_noise_gate_db", "every gate must be a number")?;
+            settings.set_noise_gates(&gates_db);
+        }
+        if let Some(bands) = visual.bands {
+            ensure(!custom_frequencies, "visual.bands (--bands)", "cannot be combined with visual.frequencies")?;
+            let layout = BandLayout::from_str(&bands).map_err(|e| invalid("visual.bands (--bands)", e))?;
+            settings.set_band_layout(&layout).map_err(|e| invalid("visual.bands (--bands)", e))?;
+        }
+
+        // --- AGC ---
+        if let Some(enabled) = agc.enabled {
+            settings.agc.enabled = enabled;
+        }
+        if let Some(target) = agc.target {
+            ensure(target > 0.0 && target <= 1.0, "agc.target (--agc_target)", &format!("{} (expected more than 0.0, up to 1.0)", target))?;
+            settings.agc.target = target;
+        }
+        if let Some(attack_ms) = agc.attack_ms {
+            ensure(attack_ms <= 60_000, "agc.attack_ms (--agc_attack)", "must be at most 60000 ms")?;
+            settings.agc.attack_ms = attack_ms;
+        }
+        if let Some(release_ms) = agc.release_ms {
+            ensure(release_ms <= 60_000, "agc.release_ms (--agc_release)", "must be at most 60000 ms")?;
+            settings.agc.release_ms = release_ms;
+        }
+        if let Some(min_gain) = agc.min_gain {
+            ensure(min_gain.is_finite() && min_gain > 0.0, "agc.min_gain (--agc_min_gain)", "must be greater than 0")?;
+            settings.agc.min_gain = min_gain;
+        }
+        if let Some(max_gain) = agc.max_gain {
+            ensure(max_gain.is_finite(), "agc.max_gain (--agc_max_gain)", "must be a number")?;
+            settings.agc.max_gain = max_gain;
+        }
+        ensure(
+            settings.agc.max_gain >= settings.agc.min_gain,
+            "agc.max_gain (--agc_max_gain)",
+            &format!("{} is below agc.min_gain {}", settings.agc.max_gain, settings.agc.min_gain),
+        )?;
+
+        // --- Strobe ---
+        if let Some(decay_ms) = strobe.decay_ms {
+            ensure((10..=5_000).contains(&decay_ms), "strobe.decay_ms (--strobe_decay)", "must be between 10 and 5000 ms")?;
+            settings.strobe.decay_ms = decay_ms;
+        }
+        if let Some(limiter) = strobe.limiter {
+            settings.strobe.limiter = limiter;
+        }
+        if let Some(max_rate) = strobe.max_rate {
+            ensure(max_rate > 0.0 && max_rate <= 30.0, "strobe.max_rate (--strobe_max_rate)", &format!("{} (expected more than 0, up to 30 flashes per second)", max_rate))?;
+            settings.strobe.max_rate = max_rate;
+        }
+        if let Some(max_delta) = strobe.max_delta {
+            ensure(max_delta > 0.0 && max_delta <= 1.0, "strobe.max_delta (--strobe_max_delta)", &format!("{} (expected more than 0.0, up to 1.0)", max_delta))?;
+            settings.strobe.max_delta = max_delta;
+        }
+
+        // --- Presets ---
+        let preset_dir = presets.directory.unwrap_or_else(|| DEFAULT_PRESET_PATH.to_string());
+        ensure(!preset_dir.is_empty(), "presets.directory (--preset_dir)", "must not be empty")?;
+
+        Ok(AppConfig { source, settings, sink, audio_input, adapter_path, advertised_name, preset_dir, bench_callback: false, list_devices: false })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Command line
+// ---------------------------------------------------------------------------
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, ConfigError> {
+    args.next().ok_or_else(|| ConfigError::MissingValue(flag.to_string()))
+}
+
+fn parse_value<T>(args: &mut impl Iterator<Item = String>, flag: &str, key: &'static str) -> Result<T, ConfigError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let value = next_value(args, flag)?;
+    value.parse().map_err(|e| invalid(key, format!("'{}': {}", value, e)))
+}
+
+/// What the command line asks for besides settings.
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    pub config_path: Option<String>,
+    pub bench_callback: bool,
+    pub list_devices: bool,
+    /// `--calibrate`: length of each calibration phase, in seconds.
+    pub calibrate: Option<u8>,
+    /// Layer to put over the config file.
+    pub overlay: ConfigFile,
+}
+
+/// Parse the command line into the config file path, mode flags and a layer to put over the file.
+pub fn parse_command_line(args: impl Iterator<Item = String>) -> Result<CommandLine, ConfigError> {
+    let mut command_line = CommandLine::default();
+    let cli = &mut command_line.overlay;
+
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        let args = &mut args;
+        match arg.as_str() {
+            "--config" => command_line.config_path = Some(next_value(args, &arg)?),
+            "--bench_callback" => command_line.bench_callback = true,
+            "--list_devices" | "--list-devices" => command_line.list_devices = true,
+            "--calibrate" => command_line.calibrate = Some(parse_value(args, &arg, "calibrate (--calibrate)")?),
+            // Visual
+            "--smooth" | "-s" => cli.visual.smooth_size = Some(parse_value(args, &arg, "visual.smooth_size (--smooth)")?),
+            "--attack" => cli.visual.attack_ms = Some(parse_value(args, &arg, "visual.attack_ms (--attack)")?),
+            "--release" => cli.visual.release_ms = Some(parse_value(args, &arg, "visual.release_ms (--release)")?),
+            "--gain" | "-g" => cli.visual.gain = Some(parse_value(args, &arg, "visual.gain (--gain)")?),
+            "--fps" | "-f" => cli.visual.fps = Some(parse_value(args, &arg, "visual.fps (--fps)")?),
+            "--color1" | "-c1" => cli.visual.color1 = Some(next_value(args, &arg)?),
+            "--color2" | "-c2" => cli.visual.color2 = Some(next_value(args, &arg)?),
+            "--color3" | "-c3" => cli.visual.color3 = Some(next_value(args, &arg)?),
+            "--skew" | "-S" => cli.visual.skew = Some(parse_value(args, &arg, "visual.skew (--skew)")?),
+            "--weighting" => cli.visual.weighting = Some(next_value(args, &arg)?),
+            "--phon" => cli.visual.weighting_phon = Some(parse_value(args, &arg, "visual.weighting_phon (--phon)")?),
+            "--db_scale" => cli.visual.db_scale = Some(parse_value(args, &arg, "visual.db_scale (--db_scale)")?),
+            "--db_floor" => cli.visual.db_floor = Some(parse_value(args, &arg, "visual.db_floor (--db_floor)")?),
+            "--db_ceiling" => cli.visual.db_ceiling = Some(parse_value(args, &arg, "visual.db_ceiling (--db_ceiling)")?),
+            "--noise_gate" => cli.visual.noise_gate_db = Some(parse_value(args, &arg, "visual.noise_gate_db (--noise_gate)")?),
+            "--brightness" | "-b" => cli.visual.brightness = Some(parse_value(args, &arg, "visual.brightness (--brightness)")?),
+            "--display_mode" | "-d" => cli.visual.display_mode = Some(next_value(args, &arg)?),
+            "--animation_mode" | "-a" => cli.visual.animation_mode = Some(next_value(args, &arg)?),
+            "--peak_hold" => cli.visual.peak_hold_ms = Some(parse_value(args, &arg, "visual.peak_hold_ms (--peak_hold)")?),
+            "--peak_decay" => cli.visual.peak_decay = Some(parse_value(args, &arg, "visual.peak_decay (--peak_decay)")?),
+            "--beat_effect" => cli.visual.beat_effect = Some(next_value(args, &arg)?),
+            "--color_mod" => cli.visual.color_modulation = Some(next_value(args, &arg)?),
+            "--color_mod_feature" => cli.visual.modulation_feature = Some(next_value(args, &arg)?),
+            "--stereo_split" => cli.visual.stereo_split = Some(parse_value(args, &arg, "visual.stereo_split (--stereo_split)")?),
+            "--bands" => cli.visual.bands = Some(next_value(args, &arg)?),
+            // AGC
+            "--agc" => cli.agc.enabled = Some(parse_value(args, &arg, "agc.enabled (--agc)")?),
+            "--agc_target" => cli.agc.target = Some(parse_value(args, &arg, "agc.target (--agc_target)")?),
+            "--agc_attack" => cli.agc.attack_ms = Some(parse_value(args, &arg, "agc.attack_ms (--agc_attack)")?),
+            "--agc_release" => cli.agc.release_ms = Some(parse_value(args, &arg, "agc.release_ms (--agc_release)")?),
+            "--agc_min_gain" => cli.agc.min_gain = Some(parse_value(args, &arg, "agc.min_gain (--agc_min_gain)")?),
+            "--agc_max_gain" => cli.agc.max_gain = Some(parse_value(args, &arg, "agc.max_gain (--agc_max_gain)")?),
+            // Strobe
+            "--strobe_decay" => cli.strobe.decay_ms = Some(parse_value(args, &arg, "strobe.decay_ms (--strobe_decay)")?),
+            "--strobe_limiter" => cli.strobe.limiter = Some(parse_value(args, &arg, "strobe.limiter (--strobe_limiter)")?),
+            "--strobe_max_rate" => cli.strobe.max_rate = Some(parse_value(args, &arg, "strobe.max_rate (--strobe_max_rate)")?),
+            "--strobe_max_delta" => cli.strobe.max_delta = Some(parse_value(args, &arg, "strobe.max_delta (--strobe_max_delta)")?),
+            // Hardware
+            "--output" | "-o" => cli.hardware.output = Some(next_value(args, &arg)?),
+            "--port" => cli.hardware.port = Some(next_value(args, &arg)?),
+            "--baud" => cli.hardware.baud = Some(parse_value(args, &arg, "hardware.baud (--baud)")?),
+            "--strips" => cli.hardware.strips = Some(parse_value(args, &arg, "hardware.strips (--strips)")?),
+            "--leds_per_strip" => cli.hardware.leds_per_strip = Some(parse_value(args, &arg, "hardware.leds_per_strip (--leds_per_strip)")?),
+            "--start_corner" => cli.hardware.start_corner = Some(next_value(args, &arg)?),
+            "--wiring" => cli.hardware.wiring = Some(next_value(args, &arg)?),
+            // Audio
+            "--fft_size" | "-F" => cli.audio.fft_size = Some(parse_value(args, &arg, "audio.fft_size (--fft_size)")?),
+            "--window" => cli.audio.window = Some(next_value(args, &arg)?),
+            "--transform" => cli.audio.transform = Some(next_value(args, &arg)?),
+            "--hop_size" => cli.audio.hop_size = Some(parse_value(args, &arg, "audio.hop_size (--hop_size)")?),
+            "--device" => cli.audio.device = Some(next_value(args, &arg)?),
+            "--host" => cli.audio.host = Some(next_value(args, &arg)?),
+            "--input" | "-i" => cli.audio.input = Some(next_value(args, &arg)?),
+            "--raw_format" => cli.audio.raw_format = Some(next_value(args, &arg)?),
+            "--raw_rate" => cli.audio.raw_rate = Some(parse_value(args, &arg, "audio.raw_rate (--raw_rate)")?),
+            "--raw_channels" => cli.audio.raw_channels = Some(parse_value(args, &arg, "audio.raw_channels (--raw_channels)")?),
+            "--fast" => cli.audio.fast = Some(true),
+            // Bluetooth
+            "--adapter" => cli.bluetooth.adapter = Some(next_value(args, &arg)?),
+            "--ble_name" => cli.bluetooth.name = Some(next_value(args, &arg)?),
+            // Presets
+            "--preset_dir" => cli.presets.directory = Some(next_value(args, &arg)?),
+            _ => return Err(ConfigError::UnknownOption(arg)),
+        }
+    }
+
+    Ok(command_line)
+}
+
+/// Resolve the configuration from defaults, config file and command line.
+pub fn load_config() -> Result<AppConfig, ConfigError> {
+    resolve_config(std::env::args())
+}
+
+/// `load_config` for the command line `args`, program name first.
+fn resolve_config(args: impl Iterator<Item = String>) -> Result<AppConfig, ConfigError> {
+    let command_line = parse_command_line(args)?;
+
+    // An explicit --config must exist; the default location is optional.
+    let source = command_line.config_path.or_else(|| Path::new(DEFAULT_CONFIG_PATH).exists().then(|| DEFAULT_CONFIG_PATH.to_string()));
+    let mut config = match &source {
+        Some(path) => ConfigFile::read(path)?,
+        None => ConfigFile::default(),
+    };
+    config.merge(command_line.overlay);
+    let mut app_config = config.resolve(source)?;
+    app_config.bench_callback = command_line.bench_callback;
+    app_config.list_devices = command_line.list_devices;
+    if let Some(seconds) = command_line.calibrate {
+        ensure((1..=MAX_CALIBRATION_SECONDS).contains(&seconds), "calibrate (--calibrate)", "must be 1 to 120 seconds")?;
+        app_config.settings.calibration = CalibrationRequest { run: 1, seconds: seconds as f32 };
+    }
+    Ok(app_config)
+}
+
+pub fn display_usage() {
+    println!("Usage: audio_visualizer [OPTIONS]");
+    println!("Options:");
+    println!("      --config <path>          Read settings from a TOML file (default: {}, if present)", DEFAULT_CONFIG_PATH);
+    println!("  -s, --smooth <size>          Set the smooth size (default: {})", DEFAULT_SMOOTH_SIZE);
+    println!("      --attack <ms>            Set how fast the bands rise, 0 for instantly (default: {})", DEFAULT_ATTACK_MS);
+    println!("      --release <ms>           Set how fast the bands fall, 0 for instantly (default: {})", DEFAULT_RELEASE_MS);
+    println!("  -g, --gain <value>           Set the gain (default: {})", GAIN);
+    println!("  -f, --fps <value>            Set the frames per second (default: {})", FPS);
+    println!("  -c1, --color1 <color>        Set the first color, by name or #rrggbb (default: blue)");
+    println!("  -c2, --color2 <color>        Set the second color, by name or #rrggbb (default: red)");
+    println!("  -c3, --color3 <color>        Set the third color, by name or #rrggbb (default: magenta)");
+    println!("  -S, --skew <value>           Set the skew value (default: {})", DEFAULT_SKEW);
+    println!("      --weighting <curve>      Set the band weighting: skew, a, c or iso226 (default: skew)");
+    println!("      --phon <value>           Set the loudness of the iso226 curve, 20 to 90 (default: {})", DEFAULT_WEIGHTING_PHON);
+    println!("      --db_scale <true|false>  Show band levels in dB instead of linearly (default: false)");
+    println!("      --db_floor <dB>          Set the dB level shown as an empty strip (default: {})", DEFAULT_DB_FLOOR);
+    println!("      --db_ceiling <dB>        Set the dB level shown as a full strip (default: {})", DEFAULT_DB_CEILING);
+    println!("      --noise_gate <dBFS>      Silence bands quieter than this, before skew and gains (default: {})", DEFAULT_NOISE_GATE_DB);
+    println!("  -F, --fft_size <size>        Set the FFT size (default: {})", FFT_SIZE);
+    println!("      --window <window>        Set the FFT window (rectangular, hann, hamming, blackman_harris; default: hann)");
+    println!("      --transform <engine>     Measure the bands with one fft or a constant_q set of FFTs sized per band (default: fft)");
+    println!("      --hop_size <samples>     Set the number of new samples between two FFTs (default: {})", HOP_SIZE);
+    println!("  -b, --brightness <value>     Set the brightness (default: 1.0)");
+    println!("  -d, --display_mode <mode>    Set the display mode (spectrum, oscilloscope, color_gradient, chroma; default: spectrum)");
+    println!("  -a, --animation_mode <mode>  Set the animation mode (full, full_with_max, points, full_middle, full_middle_with_max, points_middle, strobe, strobe_strips; default: full)");
+    println!("      --peak_hold <ms>         Set how long the peak markers of the *_with_max modes hold (default: {})", DEFAULT_PEAK_HOLD_MS);
+    println!("      --peak_decay <value>     Set how fast the peak markers fall, in strip heights per second (default: {})", DEFAULT_PEAK_DECAY);
+    println!("      --beat_effect <effect>   Set what happens on each beat: off, flash, pulse or advance (default: off)");
+    println!("      --color_mod <mode>       Let the palette follow the sound: off, hue or mix (default: off)");
+    println!("      --color_mod_feature <f>  Set what the palette follows: centroid, rolloff, flatness or rms (default: centroid)");
+    println!("      --stereo_split <bool>    Show the left channel on the left half of the strips and the right on the other (default: false)");
+    println!("      --bands <layout>         Generate the band centres, <log|third_octave|mel|bark>:<min Hz>-<max Hz> (default: built-in list)");
+    println!("      --agc <true|false>       Let the gain follow the loudness instead of --gain (default: false)");
+    println!("      --agc_target <value>     Set the strip height the AGC brings the loudest band to (default: {})", DEFAULT_AGC_TARGET);
+    println!("      --agc_attack <ms>        Set how fast the AGC lowers the gain on loud passages (default: {})", DEFAULT_AGC_ATTACK_MS);
+    println!("      --agc_release <ms>       Set how fast the AGC raises the gain on quiet passages (default: {})", DEFAULT_AGC_RELEASE_MS);
+    println!("      --agc_min_gain <value>   Set the lowest gain the AGC may use (default: {})", DEFAULT_AGC_MIN_GAIN);
+    println!("      --agc_max_gain <value>   Set the highest gain the AGC may use (default: {})", DEFAULT_AGC_MAX_GAIN);
+    println!("      --strobe_decay <ms>      Set how long a strobe flash takes to fade out (default: {})", DEFAULT_STROBE_DECAY_MS);
+    println!("      --strobe_limiter <bool>  Limit flash rate and brightness steps for photosensitive viewers (default: true)");
+    println!("      --strobe_max_rate <n>    Set the most strobe flashes per second the limiter allows (default: {})", DEFAULT_STROBE_MAX_RATE);
+    println!("      --strobe_max_delta <n>   Set the largest luminance step (0-1) the limiter allows (default: {})", DEFAULT_STROBE_MAX_DELTA);
+    println!("      --strips <n>             Set the number of LED strips / bands (default: {})", DEFAULT_NUM_STRIPS);
+    println!("      --leds_per_strip <n>     Set the number of LEDs per strip (default: {})", DEFAULT_LEDS_PER_STRIP);
+    println!("      --start_corner <corner>  Set where the LED chain starts (bottom_left, bottom_right, top_left, top_right; default: bottom_left)");
+    println!("      --wiring <wiring>        Set the strip wiring (serpentine, progressive; default: serpentine)");
+    println!("      --device <name|index>    Capture from the device whose name contains <name>, or by index (default: system default)");
+    println!("      --host <name>            Capture through this audio host: alsa, or jack when built with --features jack (default: system default)");
+    println!("      --list_devices           List the capture devices of every audio host, then exit");
+    println!("  -i, --input <path>           Play a WAV or raw PCM file instead of the capture device");
+    println!("                               or read live PCM from - (stdin), fifo:<path>, udp:<addr>:<port> or rtp:<addr>:<port>");
+    println!("      --raw_format <format>    Sample format of raw PCM input (s16, s16be, f32; default: s16)");
+    println!("      --raw_rate <hz>          Sample rate of raw PCM input (default: 44100)");
+    println!("      --raw_channels <n>       Channel count of raw PCM input (default: 2)");
+    println!("      --fast                   Play input files as fast as possible instead of in real time");
+    println!("  -o, --output <output>        Set the LED output (serial[:port[@baud]], file:<path>, stdout, udp:<host>:<port>, null; default: serial)");
+    println!("      --port <port>            Set the serial port of the serial output (default: {})", PORT);
+    println!("      --baud <rate>            Set the baud rate of the serial output (default: {})", BAUD);
+    println!("      --adapter <adapter>      Set the Bluetooth adapter, e.g. hci0 (default: {})", ADAPTER_PATH);
+    println!("      --ble_name <name>        Set the advertised Bluetooth name (default: {})", DEFAULT_ADVERTISED_NAME);
+    println!("      --preset_dir <path>      Set the directory holding the presets (default: {})", DEFAULT_PRESET_PATH);
+    println!("      --calibrate <seconds>    Measure the room noise, then reference music, for <seconds> each and set the gains from them");
+    println!("      --bench_callback         Measure the audio callback latency of the old and new pipelines, then exit");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> {
+        std::iter::once("audioleds".to_string()).chain(line.split_whitespace().map(str::to_string).collect::<Vec<_>>())
+    }
+
+    /// `toml` as the config file with `line` on the command line, without touching the disk.
+    fn resolve(toml: &str, line: &str) -> Result<AppConfig, ConfigError> {
+        let mut config: ConfigFile = toml::from_str(toml).map_err(|e| ConfigError::Parse { path: "test".to_string(), message: e.to_string() })?;
+        config.merge(parse_command_line(args(line))?.overlay);
+        config.resolve(None)
+    }
+
+    fn invalid_key(result: Result<AppConfig, ConfigError>) -> &'static str {
+        match result {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn nothing_set_gives_the_built_in_defaults() {
+        let config = resolve("", "").unwrap();
+        assert_eq!(config.settings.fft_size, FFT_SIZE);
+        assert_eq!(config.settings.hop_size, HOP_SIZE);
+        assert_eq!(config.settings.fps, FPS);
+        assert_eq!(config.settings.geometry, LedGeometry::default());
+        assert_eq!(config.sink, SinkSpec::Serial { port: PORT.to_string(), baud: BAUD });
+        assert_eq!(config.audio_input, AudioInput::Device { host: None, device: None });
+        assert_eq!(config.adapter_path, ADAPTER_PATH);
+        assert_eq!(config.advertised_name, DEFAULT_ADVERTISED_NAME);
+        assert_eq!(config.preset_dir, DEFAULT_PRESET_PATH);
+    }
+
+    #[test]
+    fn the_command_line_overrides_the_file_which_overrides_the_defaults() {
+        let toml = "
+            [audio]
+            fft_size = 2048
+            fast = true
+            input = \"song.wav\"
+            [visual]
+            fps = 30
+            gain = 2.0
+            [bluetooth]
+            adapter = \"hci1\"
+        ";
+        let config = resolve(toml, "--fps 50 --strips 8 -o stdout").unwrap();
+        assert_eq!(config.settings.fps, 50);
+        assert_eq!(config.settings.gain, 2.0);
+        assert_eq!(config.settings.fft_size, 2048);
+        assert_eq!(config.settings.geometry.strips, 8);
+        assert_eq!(config.settings.frequencies.len(), 8);
+        assert_eq!(config.sink, SinkSpec::Stdout);
+        assert_eq!(config.audio_input, AudioInput::Wav { path: "song.wav".to_string(), pacing: Pacing::Fast });
+        assert_eq!(config.adapter_path, "/org/bluez/hci1");
+    }
+
+    #[test]
+    fn unknown_keys_and_options_are_rejected() {
+        for toml in ["[visual]\ncolour1 = \"red\"", "[video]\nfps = 30", "fps = 30"] {
+            let error = resolve(toml, "").unwrap_err();
+            assert!(matches!(error, ConfigError::Parse { .. }), "{:?} accepted: {:?}", toml, error);
+        }
+        assert!(resolve("[visual]\ncolour1 = \"red\"", "").unwrap_err().to_string().contains("unknown field `colour1`"));
+
+        assert!(matches!(resolve("", "--colour1 red"), Err(ConfigError::UnknownOption(arg)) if arg == "--colour1"));
+        assert!(matches!(resolve("", "--fps"), Err(ConfigError::MissingValue(flag)) if flag == "--fps"));
+        assert_eq!(invalid_key(resolve("", "--fps fast")), "visual.fps (--fps)");
+    }
+
+    #[test]
+    fn invalid_values_name_the_key_and_flag() {
+        let cases = [
+            ("", "--fft_size 1000", "audio.fft_size (--fft_size)"),
+            ("[audio]\nfft_size = 16384", "", "audio.fft_size (--fft_size)"),
+            ("", "--hop_size 16", "audio.hop_size (--hop_size)"),
+            ("", "--hop_size 10000", "audio.hop_size (--hop_size)"),
+            ("", "--db_floor -10 --db_ceiling -20", "visual.db_floor (--db_floor)"),
+            ("[visual]\ndb_floor = 0.0\ndb_ceiling = 0.0", "", "visual.db_floor (--db_floor)"),
+            ("[visual]\nfrequencies = [100.0, 1000.0]", "--bands log:20-20000", "visual.bands (--bands)"),
+            ("", "--bands log:20000-20", "visual.bands (--bands)"),
+            ("", "--strips 0", "hardware.strips (--strips)"),
+            ("", "--wiring diagonal", "hardware.wiring (--wiring)"),
+            ("", "--brightness 1.5", "visual.brightness (--brightness)"),
+            ("[visual]\nband_noise_gate_db = []", "", "visual.band_noise_gate_db"),
+            ("", "--agc_min_gain 4 --agc_max_gain 2", "agc.max_gain (--agc_max_gain)"),
+        ];
+        for (toml, line, key) in cases {
+            assert_eq!(invalid_key(resolve(toml, line)), key, "{:?} {:?}", toml, line);
+        }
+    }
+
+    #[test]
+    fn calibrate_takes_1_to_120_seconds() {
+        // An explicit, empty config file keeps /etc/audioleds out of the test.
+        let path = std::env::temp_dir().join(format!("audioleds-config-{}.toml", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let with_config = |line: &str| resolve_config(args(&format!("--config {} {}", path.display(), line)));
+
+        let config = with_config("--calibrate 10").unwrap();
+        assert_eq!(config.source.as_deref(), path.to_str());
+        assert_eq!(config.settings.calibration.seconds, 10.0);
+        assert_eq!(invalid_key(with_config("--calibrate 0")), "calibrate (--calibrate)");
+        assert_eq!(invalid_key(with_config("--calibrate 121")), "calibrate (--calibrate)");
+        assert_eq!(invalid_key(with_config("--calibrate 300")), "calibrate (--calibrate)");
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(with_config(""), Err(ConfigError::Io { .. })));
+    }
+}
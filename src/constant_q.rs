@@ -0,0 +1,109 @@
+//! Constant-Q band levels from a multi-resolution FFT.
+//!
+//! A single FFT has the same bin width everywhere: ~10.8 Hz at 4096 points and 44.1 kHz, so the
+//! lowest bands share a handful of bins while the treble bands average hundreds of them and react
+//! no faster than the bass. Here each band gets the shortest FFT whose bins are still
+//! `BINS_PER_BAND` times narrower than the band: long ones in the bass for frequency resolution,
+//! short ones in the treble for time resolution, which keeps the ratio of centre frequency to
+//! resolution (the Q) roughly constant. Bands that need the same size share one FFT.
+
+use crate::dsp::{band_level, magnitude_spectrum, window_coefficients};
+use crate::settings::{FftWindow, Settings};
+use crate::values::SamplesWindow;
+use spectrum_analyzer::error::SpectrumAnalyzerError;
+use std::collections::HashMap;
+
+/// Shortest FFT used, so the widest treble bands still see a few milliseconds of audio.
+pub const MIN_SIZE: usize = 256;
+/// How many bins of its FFT each band spans at least.
+const BINS_PER_BAND: f32 = 3.0;
+
+/// Tapering windows per FFT size, kept between frames.
+#[derive(Debug, Clone, Default)]
+pub struct ConstantQ {
+    windows: HashMap<usize, Vec<f32>>,
+    window_kind: Option<FftWindow>,
+}
+
+impl ConstantQ {
+    /// Level of every band between consecutive `edges`, from the newest samples of `samples`.
+    ///
+    /// Levels are scaled to what an FFT of `settings.fft_size` reads for a sine, so gains, the
+    /// noise gate and the dB range mean the same with either engine.
+    pub fn levels(&mut self, samples: &SamplesWindow, edges: &[f32], settings: &Settings) -> Result<Vec<f32>, SpectrumAnalyzerError> {
+        if self.window_kind != Some(settings.fft_window) {
+            self.windows.clear();
+            self.window_kind = Some(settings.fft_window);
+        }
+        let sample_rate = settings.sample_rate;
+        let max_size = largest_power_of_two(samples.len());
+        let sizes: Vec<usize> = edges.windows(2)
+            .map(|edge| band_fft_size(edge[0], edge[1], sample_rate, max_size))
+            .collect();
+
+        let mut distinct = sizes.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        let mut levels = vec![0.0; sizes.len()];
+        for size in distinct {
+            let window = self.windows.entry(size).or_insert_with(|| window_coefficients(settings.fft_window, size));
+            let magnitudes = magnitude_spectrum(&samples.newest_to_vec(size), window, sample_rate)?;
+            let df = sample_rate as f32 / size as f32;
+            // √N-normalised, a sine reads √N / 2 in its bin
+            let scale = (settings.fft_size as f32 / size as f32).sqrt();
+            for band in (0..sizes.len()).filter(|&band| sizes[band] == size) {
+                levels[band] = band_level(&magnitudes, edges[band], edges[band + 1], df) * scale;
+            }
+        }
+        Ok(levels)
+    }
+}
+
+/// FFT size for the band between `low_hz` and `high_hz`: a power of two from `MIN_SIZE`
+/// up to `max_size`, the most samples at hand.
+pub fn band_fft_size(low_hz: f32, high_hz: f32, sample_rate: u32, max_size: usize) -> usize {
+    let wanted = BINS_PER_BAND * sample_rate as f32 / (high_hz - low_hz).max(f32::MIN_POSITIVE);
+    (wanted.min(usize::MAX as f32 / 2.0).ceil() as usize)
+        .next_power_of_two()
+        .max(MIN_SIZE)
+        .min(max_size)
+}
+
+fn largest_power_of_two(n: usize) -> usize {
+    if n == 0 { 0 } else { 1 << (usize::BITS - 1 - n.leading_zeros()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bands::band_edges;
+    use crate::dsp::band_level;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn bass_bands_get_long_ffts_and_treble_bands_short_ones() {
+        assert_eq!(band_fft_size(35.4, 47.5, 44100, 8192), 8192);
+        assert_eq!(band_fft_size(1800.0, 2400.0, 44100, 8192), 256);
+        assert_eq!(band_fft_size(400.0, 450.0, 44100, 8192), 4096);
+        // Never more than the samples at hand
+        assert_eq!(band_fft_size(35.4, 47.5, 44100, 4096), 4096);
+        assert_eq!(largest_power_of_two(8191), 4096);
+    }
+
+    #[test]
+    fn neighbouring_bass_bands_are_told_apart() {
+        let mut settings = Settings::default(); // 44.1 kHz
+        settings.set_fft_size(4096);
+        let mut samples = SamplesWindow::new(8192);
+        let tone: Vec<f32> = (0..8192).map(|n| (TAU * 41.0 * n as f32 / 44100.0).sin()).collect();
+        samples.add_samples(&tone);
+        let edges = band_edges(&[41.0, 55.0, 65.0]);
+
+        let levels = ConstantQ::default().levels(&samples, &edges, &settings).unwrap();
+        let magnitudes = magnitude_spectrum(&samples.newest_to_vec(4096), &window_coefficients(settings.fft_window, 4096), 44100).unwrap();
+        let fft: Vec<f32> = edges.windows(2).map(|edge| band_level(&magnitudes, edge[0], edge[1], settings.cached_df)).collect();
+
+        // The 55 Hz band picks up less of the 41 Hz tone than with the plain FFT
+        assert!(levels[1] / levels[0] < 0.5 * fft[1] / fft[0], "constant-Q {:?}, FFT {:?}", levels, fft);
+    }
+}
@@ -1,15 +1,55 @@
-﻿pub const NUM_STRIPS: usize = 22;
-pub const LEDS_PER_STRIP: usize = 12;
-pub const NUM_LEDS: usize = NUM_STRIPS * LEDS_PER_STRIP;
+﻿pub const DEFAULT_NUM_STRIPS: usize = 22; // Default panel geometry, see `geometry::LedGeometry`
+pub const DEFAULT_LEDS_PER_STRIP: usize = 12;
 pub const END_MARKER: u8 = 0xFF;
+pub const LED_BUFFER_CHUNK: usize = 500; // Bytes of the LED buffer per BLE read, under the 512-byte value limit
 pub const BAUD: u32 = 500_000;
 pub const PORT: &str = "/dev/ttyUSB0";        // adapt to your system
 pub const FPS: usize = 60;
 pub const GAIN: f32 = 7.0; // Adjust this to change sensitivity to audio level
 pub const FFT_SIZE: usize = 4096; // Size of FFT buffer (4096 is more accurate, but slower, 2048 is faster)
+pub const MIN_FFT_SIZE: usize = 64;
+pub const MAX_FFT_SIZE: usize = 8192; // Largest FFT that fits in `StateValues::samples_window`
+pub const HOP_SIZE: usize = 512; // New samples between two FFTs (FFT_SIZE - HOP_SIZE samples overlap)
+pub const MIN_HOP_SIZE: usize = 32;
+pub const MAX_HOP_SIZE: usize = 8192;
 pub const SAMPLE_RATE: u32 = 44100;
 pub const DEFAULT_SMOOTH_SIZE: usize = 3; // Size of the rolling average buffer
+pub const MAX_SMOOTH_SIZE: usize = 100; // Number of band levels kept per band
 pub const DEFAULT_SKEW: f32 = 0.75; // Default skew value
+pub const DEFAULT_PEAK_HOLD_MS: usize = 500; // How long the *WithMax peak markers stay up before falling
+pub const DEFAULT_PEAK_DECAY: f32 = 1.5; // Peak marker fall speed, in strip heights per second
+pub const DEFAULT_ATTACK_MS: usize = 0; // Band envelope rise time constant; 0 follows each spectrum
+pub const DEFAULT_RELEASE_MS: usize = 0; // Band envelope fall time constant
+pub const DEFAULT_DB_FLOOR: f32 = -60.0; // dB scaling: level shown as an empty strip
+pub const DEFAULT_DB_CEILING: f32 = 0.0; // dB scaling: level shown as a full strip
+pub const DEFAULT_WEIGHTING_PHON: f32 = 60.0; // Loudness of the ISO 226 contour used for weighting
+pub const MIN_WEIGHTING_PHON: f32 = 20.0; // Range covered by ISO 226
+pub const MAX_WEIGHTING_PHON: f32 = 90.0;
+pub const DEFAULT_NOISE_GATE_DB: f32 = -120.0; // Band level (dBFS) below which a band is silenced; -120 is off
+pub const DEFAULT_AGC_TARGET: f32 = 0.8; // AGC: strip height the loudest band is brought to
+pub const DEFAULT_AGC_ATTACK_MS: usize = 50;
+pub const DEFAULT_AGC_RELEASE_MS: usize = 4000;
+pub const DEFAULT_AGC_MIN_GAIN: f32 = 0.5;
+pub const DEFAULT_AGC_MAX_GAIN: f32 = 60.0;
+pub const DEFAULT_STROBE_DECAY_MS: usize = 200; // Strobe: fade-out time of a flash
+pub const DEFAULT_STROBE_MAX_RATE: f32 = 3.0; // Strobe limiter: flashes per second
+pub const DEFAULT_STROBE_MAX_DELTA: f32 = 0.5; // Strobe limiter: largest luminance step (0..1)
+pub const DEFAULT_CALIBRATION_SECONDS: u8 = 10; // Calibration: length of the noise and of the music phase
+pub const MAX_CALIBRATION_SECONDS: u8 = 120;
+pub const CALIBRATION_MIN_SNR_DB: f32 = 3.0; // Calibration: bands with less music above their noise keep their gain
+pub const CALIBRATION_MIN_GAIN: f32 = 0.1;
+pub const CALIBRATION_MAX_GAIN: f32 = 10.0;
+pub const CALIBRATION_GATE_MARGIN_DB: f32 = 3.0; // Calibration: noise gates this far above the measured floors
+pub const OSCILLOSCOPE_WINDOW_MS: f32 = 20.0; // Time span shown across the panel in oscilloscope mode
+pub const SAMPLE_QUEUE_MS: usize = 500; // Audio the callback can queue ahead of the DSP thread, see `pipeline.rs`
+pub const DSP_POLL_MS: u64 = 2; // How long the DSP thread sleeps when the queue is empty
+pub const DSP_RESTART_MS: u64 = 1000; // Pause before the analysis starts over after a panic
+pub const OVERRUN_REPORT_MS: u64 = 10_000; // At most how often the render loop logs audio dropped by the queue
+pub const CAPTURE_POLL_MS: u64 = 250; // How often the capture supervisor checks the stream, see `audio.rs`
+pub const CAPTURE_STALL_MS: u64 = 2_000; // No buffer for this long and the capture device counts as lost
+pub const CAPTURE_RETRY_MS: u64 = 1_000; // How often a lost capture device is looked for
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/audioleds/config.toml"; // Optional, see `config.rs`
+pub const DEFAULT_PRESET_PATH: &str = "presets"; // Relative to the working directory
 
 // --- Bluez Bluetooth Related ---
 pub const AGENT_PATH: &str = "/com/kevinisabelle/ledvisualizer/agent";
@@ -20,7 +60,8 @@ pub const BASE_UUID: &str = "3E0E{:04X}-7C7A-47B0-9FD5-1FC3044C3E63";
 pub const GATT_SERVICE_VISUALIZER_UUID: &str = "3E0E0000-7C7A-47B0-9FD5-1FC3044C3E63";
 pub const BLUEZ_SERVICE: &str = "org.bluez";
 pub const BLUEZ_SERVICE_PATH: &str = "/org/bluez";
-pub const ADAPTER_PATH: &str = "/org/bluez/hci0"; // adjust if needed
+pub const ADAPTER_PATH: &str = "/org/bluez/hci0"; // default, see [bluetooth] adapter in the config file
+pub const DEFAULT_ADVERTISED_NAME: &str = "LedVisualizer";
 pub const AGENT_MANAGER_IFACE: &str = "org.bluez.AgentManager1";
 pub const DBUS_PROPERTIES_IFACE: &str = "org.freedesktop.DBus.Properties";
 pub const ADAPTER_IFACE: &str = "org.bluez.Adapter1";
@@ -62,28 +103,41 @@ pub const GATT_PRESET_ACTIVATE_UUID: &str = "3E0E0015-7C7A-47B0-9FD5-1FC3044C3E6
 pub const GATT_PRESET_DELETE_UUID: &str = "3E0E0016-7C7A-47B0-9FD5-1FC3044C3E63";
 pub const GATT_PRESET_READ_ACTIVATED_INDEX_UUID: &str = "3E0E0017-7C7A-47B0-9FD5-1FC3044C3E63";
 pub const GATT_READ_SETTINGS_AS_PRESET_UUID: &str = "3E0E0018-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_PEAK_HOLD_UUID: &str = "3E0E0019-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_PEAK_DECAY_UUID: &str = "3E0E001A-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_FFT_WINDOW_UUID: &str = "3E0E001B-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_BAND_LAYOUT_UUID: &str = "3E0E001C-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_AGC_UUID: &str = "3E0E001D-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_WEIGHTING_UUID: &str = "3E0E001E-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_BEAT_UUID: &str = "3E0E001F-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_STROBE_UUID: &str = "3E0E0020-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_CHROMA_UUID: &str = "3E0E0021-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_COLOR_MODULATION_UUID: &str = "3E0E0022-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_STEREO_UUID: &str = "3E0E0023-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_TRANSFORM_UUID: &str = "3E0E0024-7C7A-47B0-9FD5-1FC3044C3E63";
+pub const GATT_CALIBRATION_UUID: &str = "3E0E0025-7C7A-47B0-9FD5-1FC3044C3E63";
 
 /*
 
 | #                               | UUID (128-bit)†                          | Properties       | Value type / size              | Encoding & notes                                                                                                             |
 |---------------------------------|------------------------------------------|------------------|--------------------------------|------------------------------------------------------------------------------------------------------------------------------|
 | **Service**                     | **3E0E0000-7C7A-47B0-9FD5-1FC3044C3E63** | —                | —                              | Primary service holding all LED-visualizer settings                                                                          |
-| 1 Smooth Size                   | 3E0E0001-…C3E63                          | Read · Write WoR | `u16` · 2 B                    | Rolling-average window length                                                                                                |
+| 1 Smooth Size                   | 3E0E0001-…C3E63                          | Read · Write WoR | 3 × `u16` · 6 B                | Rolling-average window length, then band attack and release times in ms (LE). A 2 B write sets the window length only        |
 | 2 Gain                          | 3E0E0002-…C3E63                          | Read · Write WoR | `f32` · 4 B                    | Global audio gain                                                                                                            |
 | 3 FPS                           | 3E0E0003-…C3E63                          | Read · Write WoR | `u16` · 2 B                    | Target frames per second                                                                                                     |
 | 4 Color 1                       | 3E0E0004-…C3E63                          | Read · Write WoR | `RGB888` · 3 B                 | First palette colour                                                                                                         |
 | 5 Color 2                       | 3E0E0005-…C3E63                          | Read · Write WoR | `RGB888` · 3 B                 | Second palette colour                                                                                                        |
 | 6 Color 3                       | 3E0E0006-…C3E63                          | Read · Write WoR | `RGB888` · 3 B                 | Third palette colour                                                                                                         |
 | 7 FFT Size                      | 3E0E0007-…C3E63                          | Read · Write WoR | `u16` · 2 B                    | FFT length (e.g. 512, 1024)                                                                                                  |
-| 8 Frequencies                   | 3E0E0008-…C3E63                          | Read · Write WoR | 22×`f32` · 88 B                | **Fixed-length array** of 22 little-endian floats (Hz)                                                                       |
-| 9 Gains                         | 3E0E0009-…C3E63                          | Read · Write WoR | 22×`f32` · 88 B                | One-to-one per-band gains (linear)                                                                                           |
+| 8 Frequencies                   | 3E0E0008-…C3E63                          | Read · Write WoR | N×`f32` · 4N B                 | One little-endian float (Hz) per strip, N = strip count (22 by default)                                                      |
+| 9 Gains                         | 3E0E0009-…C3E63                          | Read · Write WoR | N×`f32` · 4N B                 | One-to-one per-band gains (linear)                                                                                           |
 | 10 Skew                         | 3E0E000A-…C3E63                          | Read · Write WoR | `f32` · 4 B                    | Frequency-to-LED skew factor                                                                                                 |
 | 11 Brightness                   | 3E0E000B-…C3E63                          | Read · Write WoR | `f32` · 4 B                    | 0.0 – 1.0 mapped to LED PWM                                                                                                  |
-| 12 Display Mode                 | 3E0E000C-…C3E63                          | Read · Write WoR | `u8` · 1 B                     | 0 Spectrum, 1 Oscilloscope, 2 ColorGradient                                                                                  |
-| 13 Animation Mode               | 3E0E000D-…C3E63                          | Read · Write WoR | `u8` · 1 B                     | 0 Full, 1 FullWithMax, 2 Points, 3 FullMiddle, 4 FullMiddleWithMax, 5 PointsMiddle                                           |
-| 14 LED Count                    | 3E0E000E-…C3E63                          | Read             | `u16 · 2 B`                    | Fixed to **264** (22 × 12) for the current build, but still exposed so the phone can adapt if you change strip length later. |
-| 15 LED Buffer                   | 3E0E000F-…C3E63                          | Read             | `500 B` (`264 × RGB888`)       | Snapshot of all pixels in physical order. **Read-only** (no Notify).                                                         |
-| 16 LED Buffer (2)               | 3E0E0010-…C3E63                          | Read             | `792 - 500 B` (`264 × RGB888`) | Same as above, but for the second rest of the buffer.                                                                        |
+| 12 Display Mode                 | 3E0E000C-…C3E63                          | Read · Write WoR | `u8` · 1 B                     | 0 Spectrum, 1 Oscilloscope, 2 ColorGradient, 3 Chroma                                                                        |
+| 13 Animation Mode               | 3E0E000D-…C3E63                          | Read · Write WoR | `u8` · 1 B                     | 0 Full, 1 FullWithMax, 2 Points, 3 FullMiddle, 4 FullMiddleWithMax, 5 PointsMiddle, 6 Strobe, 7 StrobeStrips                 |
+| 14 LED Count                    | 3E0E000E-…C3E63                          | Read             | `u16 · 2 B`                    | Number of LEDs of the configured panel (strips × LEDs per strip, **264** for the default 22 × 12).                          |
+| 15 LED Buffer                   | 3E0E000F-…C3E63                          | Read             | `500 B` (`N × GRB888`)         | First 500 bytes of the last frame, pixels in physical order. **Read-only** (no Notify).                                      |
+| 16 LED Buffer (2)               | 3E0E0010-…C3E63                          | Read · Write     | ≤ `500 B` · write `u16` · 2 B  | Up to 500 bytes of the frame from an offset (default 500), including the end marker. Write the offset (u16 LE) before reading; the frame is `N × 3 + 1` bytes |
 | 14 Preset List                  | 3E0E0011-…-C3E63                         | Read             | `u8 + (up to 24 × 17)`         | Returns up to 24 entries: `{id: u8, name[16]: UTF-8}`; first byte is count                                                   |
 | 15 Preset Select Index          | 3E0E0012-…-C3E63                         | Read · Write WoR | `u8`                           | Sets or gets the selected preset index                                                                                       |
 | 16 Preset Read                  | 3E0E0013-…-C3E63                         | Read             | `222 B`                        | Returns the selected preset's binary data                                                                                    |
@@ -91,4 +145,17 @@ pub const GATT_READ_SETTINGS_AS_PRESET_UUID: &str = "3E0E0018-7C7A-47B0-9FD5-1FC
 | 18 Preset Activate              | 3E0E0015-…-C3E63                         | Write WoR        | `u8`                           | Activates a preset by `id` (0–23); system applies it immediately                                                             |
 | 19 Preset Delete                | 3E0E0016-…-C3E63                         | Write WoR        | `u8`                           | Deletes a preset by `id` (0–23)                                                                                              |
 | 20 Preset Read Activated Index  | 3E0E0017-…-C3E63                         | Read             | `u8`                           | Returns the currently activated preset index (0–23) - 255 if none                                                            |
+| 22 Peak Hold                    | 3E0E0019-…-C3E63                         | Read · Write WoR | `u16` · 2 B                    | Time (ms) the *WithMax peak markers stay at their highest point before falling                                               |
+| 23 Peak Decay                   | 3E0E001A-…-C3E63                         | Read · Write WoR | `f32` · 4 B                    | Fall speed of the peak markers once the hold time is over, in strip heights per second                                        |
+| 24 FFT Window                   | 3E0E001B-…-C3E63                         | Read · Write WoR | `u8` + `u16` · 3 B             | FFT window (0 rectangular, 1 Hann, 2 Hamming, 3 Blackman-Harris), then the hop size in samples (32–8192, LE)                 |
+| 25 Band Layout                  | 3E0E001C-…-C3E63                         | Write WoR        | `u8` + 2 × `f32` · 9 B         | Regenerates the band centres: scale (0 log, 1 third-octave, 2 mel, 3 Bark), then min and max Hz (LE). Gains are kept         |
+| 26 AGC                          | 3E0E001D-…-C3E63                         | Read · Write WoR | `u8` + 2 × `f32` · 9 B         | AGC on/off, target strip height (0–1), then the gain applied to the last frame (LE). Writes take the first 5 B only          |
+| 27 Weighting                    | 3E0E001E-…-C3E63                         | Read · Write WoR | `u8` + `f32` · 5 B             | Band weighting (0 skew, 1 A, 2 C, 3 ISO 226 equal loudness), then the ISO 226 loudness in phon (20–90, LE)                   |
+| 28 Beat                         | 3E0E001F-…-C3E63                         | Read · Write WoR | `u8` + `f32` + `u32` · 9 B     | Beat effect (0 off, 1 flash, 2 pulse, 3 advance), tempo in BPM (0 if unknown), beats counted (LE). Writes take the effect only |
+| 29 Strobe                       | 3E0E0020-…-C3E63                         | Read · Write WoR | `u8` + `u16` + 2 × `f32` · 11 B | Photosensitivity limiter on/off, flash fade-out in ms (10–5000), then the most flashes per second and largest luminance step (LE) |
+| 30 Chroma                       | 3E0E0021-…-C3E63                         | Read · Write WoR | 12 × `u16` + 2 × `u8` · 26 B   | Hue (0–360°) of each pitch class from C to B (LE), then chord and key last named: root (0 = C), +12 if minor, 255 if none. Writes take the hues only |
+| 31 Color Modulation             | 3E0E0022-…-C3E63                         | Read · Write WoR | 2 × `u8` + 4 × `f32` · 18 B    | Modulation (0 off, 1 hue, 2 mix), feature (0 centroid, 1 rolloff, 2 flatness, 3 RMS), then centroid Hz, rolloff Hz, flatness, RMS of the last frame (LE). Writes take the first 2 B only |
+| 32 Stereo                       | 3E0E0023-…-C3E63                         | Read · Write WoR | `u8` + 2 × `f32` · 9 B         | Split display on/off (left channel on the left half of the strips, right on the other), then balance (-1 left to 1 right) and width (0 mono to 1 out of phase) of the last frame (LE). Writes take the first byte only |
+| 33 Transform                    | 3E0E0024-…-C3E63                         | Read · Write WoR | `u8` · 1 B                     | Band measurement: 0 one FFT of the FFT size, 1 constant-Q (an FFT sized per band, long in the bass, short in the treble) |
+| 34 Calibration                  | 3E0E0025-…-C3E63                         | Read · Write WoR | 3 × `u8` + n × `i8` · 3+n B    | Write `[command u8: 0 cancel, 1 start][seconds u8: per phase, 1–120, 0 default]`. Read phase (0 idle, 1 noise, 2 music, 3 done, 4 cancelled, 5 failed), progress of the phase in %, band count, then the noise floor of each band in dBFS from the last run |
 */
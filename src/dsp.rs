@@ -1,80 +1,338 @@
-﻿use std::sync::{Arc, Mutex};
-use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
+﻿use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
+use spectrum_analyzer::error::SpectrumAnalyzerError;
 use spectrum_analyzer::scaling::divide_by_N_sqrt;
-use crate::constants::SAMPLE_RATE;
-use crate::settings::{Settings};
-use crate::values::{FrequenciesValues, SamplesWindow, StateValues};
+use crate::bands::band_edges;
+use crate::features::SpectralFeatures;
+use crate::settings::{resample_bands, FftWindow, Settings, Transform, Weighting};
+use crate::stereo::deinterleave;
+use crate::values::StateValues;
+use crate::weighting::{a_weighting_db, c_weighting_db, db_to_gain, iso226_weighting_db};
+use std::f32::consts::TAU;
 
+/// Runs on the DSP thread, which owns `state_values`; the audio callback never gets here.
+/// Returns how many spectra were computed: one every `settings.hop_size` new samples.
 pub fn process_audio_data(
     data: &[f32],
-    state_values: &Arc<Mutex<StateValues>>,
+    state_values: &mut StateValues,
     settings: &Settings
-) {
-    let df = settings.cached_df; // frequency bin width
+) -> usize {
+    state_values.update_settings(settings);
+
+    // 1.  Downmix interleaved frames to mono and move them into the rolling window,
+    //     stopping at every hop boundary so each FFT ends exactly there.
+    //     The split stereo display also keeps left and right apart.
+    let channels = state_values.format.channels.max(1) as usize;
+    let mono = downmix(data, channels);
+    state_values.stereo.update(data, channels, mono.len() as f32 / settings.sample_rate as f32);
+    let split: Vec<Vec<f32>> = (0..state_values.channels.len()).map(|channel| deinterleave(data, channels, channel)).collect();
+    let hop_size = settings.hop_size.max(1);
+    let mut spectra = 0;
+    let mut rest = mono.as_slice();
+    while !rest.is_empty() {
+        let take = hop_size.saturating_sub(state_values.hop_pending).clamp(1, rest.len());
+        let start = mono.len() - rest.len();
+        state_values.samples_window.add_samples(&rest[..take]);
+        for (channel, samples) in state_values.channels.iter_mut().zip(&split) {
+            channel.samples_window.add_samples(&samples[start..start + take]);
+        }
+        state_values.hop_pending += take;
+        rest = &rest[take..];
 
-    // 1.  Move samples into the rolling window
-    {
-        state_values.lock().unwrap().samples_window.add_samples(data);
-        if state_values.lock().unwrap().samples_window.samples.lock().unwrap().len() < settings.fft_size as usize {
-            println!("Not enough samples for FFT: {} < {}", state_values.lock().unwrap().samples_window.samples.lock().unwrap().len(), settings.fft_size);
-            return;                       // not enough for one FFT yet
+        if state_values.hop_pending >= hop_size {
+            state_values.hop_pending = 0;
+            match analyse_frame(state_values, settings) {
+                Ok(true) => spectra += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("FFT of {} samples failed: {}", settings.fft_size, e),
+            }
         }
     }
+    spectra
+}
 
-    // println!("Processing {} samples for FFT", state_values.lock().unwrap().samples_window.samples.lock().unwrap().len());
+/// FFT of the newest `fft_size` samples, added to the per-band smoothing windows.
+fn analyse_frame(state_values: &mut StateValues, settings: &Settings) -> Result<bool, SpectrumAnalyzerError> {
+    let df = settings.cached_df; // frequency bin width
+    let sample_rate = settings.sample_rate;
 
-    // Take fft_size samples from the end of the rolling window
-    let samples_window = state_values.lock().unwrap().samples_window.samples.lock().unwrap().
-        iter()
-        .rev()
-        .take(settings.fft_size)
-        .cloned()
-        .rev()
-        .collect::<Vec<f32>>();
+    if state_values.samples_window.len() < settings.fft_size {
+        println!("Not enough samples for FFT: {} < {}", state_values.samples_window.len(), settings.fft_size);
+        return Ok(false);                   // not enough for one FFT yet
+    }
 
-    // 2.  FFT → linear magnitude spectrum (already √N-normalised)
-    let spec = samples_fft_to_spectrum(
-        &samples_window,
-        SAMPLE_RATE,
-        FrequencyLimit::All,
-        Some(&divide_by_N_sqrt),
-    ).expect("FFT failed – check sample count");
+    // 2.  FFT of the newest fft_size samples of the rolling window
+    let samples = state_values.samples_window.newest_to_vec(settings.fft_size);
+    let magnitudes = magnitude_spectrum(&samples, &state_values.window, sample_rate)?;
 
+    // 3.  Integrate every band between its edges and gate out the ones lost in the noise
+    let edges = band_edges(&settings.frequencies);
+    let full_scale = full_scale_level(settings.fft_size);
+    let elapsed = settings.hop_size as f32 / sample_rate as f32; // audio time since the previous spectrum
+    let mut loudness: f32 = 0.0;
+    let levels = match settings.transform {
+        Transform::Fft => fft_band_levels(&magnitudes, &edges, df),
+        Transform::ConstantQ => state_values.constant_q.levels(&state_values.samples_window, &edges, settings)?,
+    };
     for (i, &f_cfg) in settings.frequencies.iter().enumerate() {
-        let idx_c = (f_cfg / df).round() as isize;          // centre bin
-        let r     = half_window_bins(f_cfg, df);            // window radius
-
-        let mut acc = 0.0;
-        let mut n   = 0;
-        for off in -(r as isize)..=(r as isize) {
-            let idx = idx_c + off;
-            if idx >= 0 && (idx as usize) < spec.data().len() {
-                acc += spec.data()[idx as usize].1.val();
-                n   += 1;
+        let v = gate_and_weight(levels[i], f_cfg, settings.band_noise_gate_db(i), full_scale, settings);
+        loudness = loudness.max(v * settings.gains.get(i).copied().unwrap_or(1.0));
+        let (attack_ms, release_ms) = settings.band_envelope_ms(i);
+        let v = state_values.envelopes[i].update(v, elapsed, attack_ms, release_ms);
+        state_values.frequencies[i].add_sample(v);  // smooth between frames
+    }
+    state_values.calibrator.update(&levels, elapsed, full_scale, settings);
+
+    // 4.  Follow the loudness, even while the AGC is off, so switching it on starts from a sensible gain
+    state_values.agc_gain = state_values.auto_gain.update(loudness, elapsed, &settings.agc);
+
+    // 5.  Onsets and tempo, from the whole spectrum
+    state_values.beat.update(&magnitudes, df, elapsed);
+
+    // 6.  Pitch classes, chord and key
+    state_values.chroma.update(&magnitudes, df, full_scale, elapsed);
+
+    // 7.  Timbre, for the colour modulation
+    let features = SpectralFeatures::measure(&samples, &magnitudes, df);
+    state_values.features.smooth(&features, elapsed);
+
+    // 8.  Left and right on their own, a band per strip of their half of the split display
+    if !state_values.channels.is_empty() {
+        let half = settings.geometry.strips / 2;
+        let centres = resample_bands(&settings.frequencies, half, true);
+        let edges = band_edges(&centres);
+        for channel in state_values.channels.iter_mut() {
+            if channel.samples_window.len() < settings.fft_size {
+                continue;
+            }
+            let levels = match settings.transform {
+                Transform::Fft => {
+                    let samples = channel.samples_window.newest_to_vec(settings.fft_size);
+                    fft_band_levels(&magnitude_spectrum(&samples, &state_values.window, sample_rate)?, &edges, df)
+                }
+                Transform::ConstantQ => state_values.constant_q.levels(&channel.samples_window, &edges, settings)?,
+            };
+            for (b, &centre) in centres.iter().enumerate() {
+                // Gate and times of the band at the same position in the full list
+                let band = b * settings.frequencies.len() / half;
+                let v = gate_and_weight(levels[b], centre, settings.band_noise_gate_db(band), full_scale, settings);
+                let (attack_ms, release_ms) = settings.band_envelope_ms(band);
+                let v = channel.envelopes[b].update(v, elapsed, attack_ms, release_ms);
+                channel.frequencies[b].add_sample(v);
             }
         }
-        let mut v = if n > 0 { acc / n as f32 } else { 0.0 };
-        v *= weight(f_cfg, settings.skew);                           // high-freq boost
-        state_values.lock().unwrap().frequencies[i].add_sample(v);  // smooth between frames
+    }
+    Ok(true)
+}
+
+/// Linear magnitude spectrum (√N-normalised) of `samples` tapered by `window`.
+/// Fails unless the sample count is a power of two the FFT supports.
+pub fn magnitude_spectrum(samples: &[f32], window: &[f32], sample_rate: u32) -> Result<Vec<f32>, SpectrumAnalyzerError> {
+    let tapered: Vec<f32> = samples.iter().zip(window).map(|(sample, coefficient)| sample * coefficient).collect();
+    let spec = samples_fft_to_spectrum(
+        &tapered,
+        sample_rate,
+        FrequencyLimit::All,
+        Some(&divide_by_N_sqrt),
+    )?;
+    Ok(spec.data().iter().map(|(_, value)| value.val()).collect())
+}
+
+/// Level of every band between consecutive `edges` in one spectrum.
+fn fft_band_levels(magnitudes: &[f32], edges: &[f32], df: f32) -> Vec<f32> {
+    edges.windows(2).map(|edge| band_level(magnitudes, edge[0], edge[1], df)).collect()
+}
+
+/// `level` of the band centred on `centre_hz`, silenced below its noise gate `gate_db` and
+/// weighted (skew or perceptual curve).
+fn gate_and_weight(level: f32, centre_hz: f32, gate_db: f32, full_scale: f32, settings: &Settings) -> f32 {
+    if to_db(level / full_scale) < gate_db {
+        return 0.0;
+    }
+    level * band_weight(centre_hz, settings)
+}
+
+/// Coefficients of `window` over `size` samples (periodic form, as used for spectral analysis),
+/// scaled to a mean of 1 so a steady tone reads the same level whichever window is selected.
+pub fn window_coefficients(window: FftWindow, size: usize) -> Vec<f32> {
+    let cosine_terms: &[f32] = match window {
+        FftWindow::Rectangular => return vec![1.0; size],
+        FftWindow::Hann => &[0.5, 0.5],
+        FftWindow::Hamming => &[0.54, 0.46],
+        FftWindow::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+    };
+
+    // w(n) = a0 - a1·cos(2πn/N) + a2·cos(4πn/N) - …, whose mean is a0
+    (0..size)
+        .map(|n| {
+            let phase = TAU * n as f32 / size as f32;
+            let value: f32 = cosine_terms
+                .iter()
+                .enumerate()
+                .map(|(k, a)| if k % 2 == 0 { 1.0 } else { -1.0 } * a * (k as f32 * phase).cos())
+                .sum();
+            value / cosine_terms[0]
+        })
+        .collect()
+}
+
+/// Average interleaved frames into one mono sample per frame.
+pub fn downmix(data: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return data.to_vec();
+    }
+    data.chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Weight of the band centred on `freq_hz` under `settings.weighting`.
+pub fn band_weight(freq_hz: f32, settings: &Settings) -> f32 {
+    match settings.weighting {
+        Weighting::Skew => weight(freq_hz, settings.skew, settings.sample_rate),
+        Weighting::A => db_to_gain(a_weighting_db(freq_hz)),
+        Weighting::C => db_to_gain(c_weighting_db(freq_hz)),
+        Weighting::Iso226 => db_to_gain(iso226_weighting_db(freq_hz, settings.weighting_phon)),
     }
 }
 
 /// Exponential-law weighting.
 /// alpha=0.0  → flat,   alpha>0 → boost highs,   alpha<0 → boost lows.
 /// Typical values: alpha = 0.35 … 0.55 gives a gentle but audible lift of everything above ~1 kHz.
-pub fn weight(freq_hz: f32, alpha: f32) -> f32 {
+/// In dB this is a straight tilt of 20·alpha dB per decade, so it combines with `db_scale` as is.
+pub fn weight(freq_hz: f32, alpha: f32, sample_rate: u32) -> f32 {
     let f_min: f32 = 0.0; // min frequency
-    let f_max: f32 = SAMPLE_RATE as f32 / 2.0; // Nyquist frequency
+    let f_max: f32 = sample_rate as f32 / 2.0; // Nyquist frequency
 
     // normalised [0,1] then exponential
     let x = ((freq_hz - f_min) / (f_max - f_min)).clamp(0.0, 1.0);
     x.powf(alpha)
 }
 
-/// How many neighbouring bins to average on *each* side of the centre bin.
-/// 1 ≈ 3-bin window, 2 ≈ 5-bin window, etc.
-pub fn half_window_bins(freq_hz: f32, df: f32) -> usize {
-    // keep ~15 % fractional bandwidth (tweak to taste)
-    let bw_hz = 0.15 * freq_hz;
-    ((bw_hz / df).round() as isize).max(1) as usize
-}
\ No newline at end of file
+/// Level of a full-scale sine in the bin it falls on (√N-normalised, unit-mean window): the 0 dBFS
+/// reference of the noise gate. `band_level` is an RMS, so noise reads the same whatever the band width.
+fn full_scale_level(fft_size: usize) -> f32 {
+    (fft_size as f32).sqrt() / 2.0
+}
+
+/// `value` in dB relative to 1.0; silence is -∞.
+pub fn to_db(value: f32) -> f32 {
+    20.0 * value.log10()
+}
+
+/// Map a linear level (1.0 = full strip) onto 0..1 in dB, from `floor_db` (empty) to `ceiling_db` (full).
+pub fn db_scale(value: f32, floor_db: f32, ceiling_db: f32) -> f32 {
+    if value <= 0.0 {
+        return 0.0;
+    }
+    ((to_db(value) - floor_db) / (ceiling_db - floor_db)).clamp(0.0, 1.0)
+}
+
+/// RMS magnitude of the bins between `low_hz` and `high_hz`.
+/// Bin `k` covers `(k ± ½)·df`; bins cut by an edge count for the part inside the band,
+/// so a band narrower than one bin still reads the bin it falls in.
+/// Mean rather than summed power keeps wide treble bands comparable to narrow bass ones.
+pub fn band_level(magnitudes: &[f32], low_hz: f32, high_hz: f32, df: f32) -> f32 {
+    if magnitudes.is_empty() || high_hz <= low_hz {
+        return 0.0;
+    }
+    let first = (low_hz / df + 0.5).floor().max(0.0) as usize;
+    let last = ((high_hz / df + 0.5).floor() as usize).min(magnitudes.len() - 1);
+
+    let mut power = 0.0;
+    let mut width = 0.0;
+    for (k, magnitude) in magnitudes.iter().enumerate().take(last + 1).skip(first) {
+        let bin_low = (k as f32 - 0.5) * df;
+        let overlap = ((bin_low + df).min(high_hz) - bin_low.max(low_hz)).max(0.0) / df;
+        power += overlap * magnitude * magnitude;
+        width += overlap;
+    }
+    if width > 0.0 { (power / width).sqrt() } else { 0.0 }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpal::SampleFormat;
+    use crate::audio::AudioFormat;
+    use crate::settings::is_valid_fft_size;
+
+    #[test]
+    fn windows_have_unit_mean_and_taper_to_the_edges() {
+        for window in [FftWindow::Rectangular, FftWindow::Hann, FftWindow::Hamming, FftWindow::BlackmanHarris] {
+            let coefficients = window_coefficients(window, 1024);
+            let mean = coefficients.iter().sum::<f32>() / 1024.0;
+            assert!((mean - 1.0).abs() < 1e-3, "{:?} mean {}", window, mean);
+            if window != FftWindow::Rectangular {
+                assert!(coefficients[0] < coefficients[512], "{:?} does not taper", window);
+            }
+        }
+    }
+
+    #[test]
+    fn one_spectrum_per_hop_whatever_the_buffer_size() {
+        let mut settings = Settings::default();
+        settings.set_fft_size(256);
+        settings.hop_size = 100;
+        let format = AudioFormat { sample_rate: 44100, channels: 1, sample_format: SampleFormat::F32 };
+        let mut state = StateValues::new(&settings, format);
+
+        // 256 samples to fill the first FFT, then one spectrum per 100 new samples.
+        assert_eq!(process_audio_data(&[0.1; 250], &mut state, &settings), 0);
+        assert_eq!(process_audio_data(&[0.1; 50], &mut state, &settings), 1);
+        assert_eq!(process_audio_data(&[0.1; 330], &mut state, &settings), 3);
+        assert_eq!(state.hop_pending, 30);
+    }
+
+    #[test]
+    fn an_unsupported_fft_size_skips_the_spectrum_instead_of_panicking() {
+        let mut settings = Settings::default();
+        settings.set_fft_size(1000);
+        let format = AudioFormat { sample_rate: 44100, channels: 1, sample_format: SampleFormat::F32 };
+        let mut state = StateValues::new(&settings, format);
+        assert_eq!(process_audio_data(&[0.1; 2048], &mut state, &settings), 0);
+        assert!(!is_valid_fft_size(1000) && is_valid_fft_size(1024) && !is_valid_fft_size(16384));
+    }
+
+    #[test]
+    fn band_level_weights_bins_cut_by_the_edges() {
+        let magnitudes = [0.0, 1.0, 3.0, 0.0];
+        // Bin 1 covers 5..15 Hz and bin 2 15..25 Hz with df = 10.
+        assert_eq!(band_level(&magnitudes, 5.0, 15.0, 10.0), 1.0);
+        assert_eq!(band_level(&magnitudes, 16.0, 18.0, 10.0), 3.0);
+        let half_and_half = band_level(&magnitudes, 10.0, 20.0, 10.0);
+        assert!((half_and_half - 5f32.sqrt()).abs() < 1e-6);
+        assert_eq!(band_level(&magnitudes, 100.0, 200.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn noise_gate_is_referenced_to_a_full_scale_sine() {
+        let mut settings = Settings { skew: 0.0, ..Settings::default() };
+        // A tone right on bin 93, alone in a band narrower than one bin.
+        let tone = 93.0 * settings.cached_df;
+        settings.frequencies = vec![tone / 1.01, tone, tone * 1.01];
+        let format = AudioFormat { sample_rate: 44100, channels: 1, sample_format: SampleFormat::F32 };
+        let sine = |amplitude: f32| -> Vec<f32> {
+            (0..settings.fft_size).map(|n| amplitude * (TAU * tone * n as f32 / 44100.0).sin()).collect()
+        };
+        let band_db = |settings: &Settings, amplitude: f32| {
+            let mut state = StateValues::new(settings, format);
+            process_audio_data(&sine(amplitude), &mut state, settings);
+            to_db(state.frequencies[1].average(1) / full_scale_level(settings.fft_size))
+        };
+
+        assert!(band_db(&settings, 1.0).abs() < 1.0, "{}", band_db(&settings, 1.0));
+        settings.noise_gate_db = vec![-50.0; 3];
+        assert!((band_db(&settings, 0.01) + 40.0).abs() < 1.0);
+        assert_eq!(band_db(&settings, 0.001), f32::NEG_INFINITY);
+        // Each band has its own gate
+        settings.noise_gate_db = vec![-120.0, -30.0, -120.0];
+        assert_eq!(band_db(&settings, 0.01), f32::NEG_INFINITY);
+    }
+
+    #[test]
+    fn db_scale_spans_floor_to_ceiling() {
+        assert_eq!(db_scale(0.0, -60.0, 0.0), 0.0);
+        assert_eq!(db_scale(0.001, -60.0, 0.0), 0.0);
+        assert!((db_scale(0.1, -60.0, 0.0) - 2.0 / 3.0).abs() < 1e-6);
+        assert_eq!(db_scale(2.0, -60.0, 0.0), 1.0);
+    }
+}
@@ -0,0 +1,117 @@
+//! Spectral features: what a frame sounds like rather than how loud each band is.
+//!
+//! Measured on every spectrum and smoothed a little, they let the palette follow the timbre
+//! (see `ColorModulation`): the centroid and rolloff rise with brighter sounds, the flatness
+//! tells noise (close to 1) from tones (close to 0), and the RMS follows the overall level.
+
+use crate::dsp::db_scale;
+use crate::settings::SpectralFeature;
+
+/// Share of the spectral energy below the rolloff frequency.
+const ROLLOFF_SHARE: f32 = 0.85;
+/// Range the centroid and rolloff are mapped onto 0..1 over, on a log scale.
+const MIN_FEATURE_HZ: f32 = 100.0;
+const MAX_FEATURE_HZ: f32 = 10_000.0;
+/// RMS (dBFS) mapped onto 0 and 1.
+const MIN_FEATURE_DB: f32 = -60.0;
+const MAX_FEATURE_DB: f32 = 0.0;
+/// Time constant (ms) of the smoothing, so colours drift instead of flickering.
+const SMOOTH_MS: f32 = 150.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SpectralFeatures {
+    /// Energy-weighted mean frequency.
+    pub centroid_hz: f32,
+    /// Frequency below which `ROLLOFF_SHARE` of the energy lies.
+    pub rolloff_hz: f32,
+    /// Geometric over arithmetic mean of the power spectrum, 0..1.
+    pub flatness: f32,
+    /// RMS of the frame, 1.0 for a full-scale sine.
+    pub rms: f32,
+}
+
+impl SpectralFeatures {
+    /// Features of one frame: its unwindowed `samples` and the magnitudes of their spectrum, bins `df` Hz apart.
+    pub fn measure(samples: &[f32], magnitudes: &[f32], df: f32) -> Self {
+        let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32;
+        let rms = (2.0 * mean_square).sqrt();
+
+        // DC says nothing about timbre
+        let powers: Vec<f32> = magnitudes.iter().skip(1).map(|m| m * m).collect();
+        let total: f32 = powers.iter().sum();
+        if total <= 0.0 {
+            return SpectralFeatures { rms, ..SpectralFeatures::default() };
+        }
+        let frequency = |i: usize| (i + 1) as f32 * df;
+
+        let centroid_hz = powers.iter().enumerate().map(|(i, p)| frequency(i) * p).sum::<f32>() / total;
+
+        let mut cumulative = 0.0;
+        let rolloff = powers.iter().position(|p| {
+            cumulative += p;
+            cumulative >= ROLLOFF_SHARE * total
+        });
+        let rolloff_hz = frequency(rolloff.unwrap_or(powers.len() - 1));
+
+        // Bins at exactly zero would make the geometric mean zero whatever the rest
+        let floor = total / powers.len() as f32 * 1e-10;
+        let log_mean = powers.iter().map(|p| (p + floor).ln()).sum::<f32>() / powers.len() as f32;
+        let flatness = (log_mean.exp() / (total / powers.len() as f32 + floor)).clamp(0.0, 1.0);
+
+        SpectralFeatures { centroid_hz, rolloff_hz, flatness, rms }
+    }
+
+    /// Move towards `measured`, `elapsed` seconds after the previous frame.
+    pub fn smooth(&mut self, measured: &SpectralFeatures, elapsed: f32) {
+        let k = 1.0 - (-elapsed * 1000.0 / SMOOTH_MS).exp();
+        self.centroid_hz += (measured.centroid_hz - self.centroid_hz) * k;
+        self.rolloff_hz += (measured.rolloff_hz - self.rolloff_hz) * k;
+        self.flatness += (measured.flatness - self.flatness) * k;
+        self.rms += (measured.rms - self.rms) * k;
+    }
+
+    /// `feature` mapped onto 0..1, for modulating colours.
+    pub fn value(&self, feature: SpectralFeature) -> f32 {
+        let log_position = |hz: f32| {
+            if hz <= 0.0 {
+                return 0.0;
+            }
+            ((hz / MIN_FEATURE_HZ).ln() / (MAX_FEATURE_HZ / MIN_FEATURE_HZ).ln()).clamp(0.0, 1.0)
+        };
+        match feature {
+            SpectralFeature::Centroid => log_position(self.centroid_hz),
+            SpectralFeature::Rolloff => log_position(self.rolloff_hz),
+            SpectralFeature::Flatness => self.flatness.clamp(0.0, 1.0),
+            SpectralFeature::Rms => db_scale(self.rms, MIN_FEATURE_DB, MAX_FEATURE_DB),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tone_is_bright_where_it_sits_and_not_flat() {
+        let mut magnitudes = vec![0.0; 512];
+        magnitudes[100] = 1.0;
+        let samples: Vec<f32> = (0..1024).map(|n| (n as f32 * 0.3).sin()).collect();
+        let features = SpectralFeatures::measure(&samples, &magnitudes, 10.0);
+        assert!((features.centroid_hz - 1000.0).abs() < 1e-3, "{:?}", features);
+        assert_eq!(features.rolloff_hz, 1000.0);
+        assert!(features.flatness < 1e-3, "{:?}", features);
+        assert!((features.rms - 1.0).abs() < 0.01, "{:?}", features);
+        assert!((features.value(SpectralFeature::Centroid) - 0.5).abs() < 1e-3);
+        assert!(features.value(SpectralFeature::Rms) > 0.99);
+    }
+
+    #[test]
+    fn white_noise_is_flat_and_centred() {
+        let magnitudes = vec![1.0; 513];
+        let features = SpectralFeatures::measure(&[0.0; 1024], &magnitudes, 10.0);
+        assert!((features.flatness - 1.0).abs() < 1e-3, "{:?}", features);
+        assert!((features.centroid_hz - 2565.0).abs() < 1.0, "{:?}", features);
+        assert!((features.rolloff_hz - 4360.0).abs() < 11.0, "{:?}", features);
+        assert_eq!(features.value(SpectralFeature::Rms), 0.0);
+    }
+}
@@ -0,0 +1,140 @@
+//! Physical layout of the LED matrix.
+//!
+//! The renderer works in logical coordinates: `strip` 0 is the left-most column (lowest band)
+//! and `led` 0 is the bottom row. `LedGeometry` maps those to the index of the pixel in the
+//! chain as it is wired, so panels of any size and wiring can be driven.
+
+use crate::constants::{DEFAULT_LEDS_PER_STRIP, DEFAULT_NUM_STRIPS};
+use std::fmt;
+use std::str::FromStr;
+
+/// Corner of the panel where the first LED of the chain sits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StartCorner {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight,
+}
+
+impl FromStr for StartCorner {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bottom_left" => Ok(StartCorner::BottomLeft),
+            "bottom_right" => Ok(StartCorner::BottomRight),
+            "top_left" => Ok(StartCorner::TopLeft),
+            "top_right" => Ok(StartCorner::TopRight),
+            _ => Err(format!("Invalid start corner '{}' (expected bottom_left, bottom_right, top_left or top_right)", s)),
+        }
+    }
+}
+
+impl fmt::Display for StartCorner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StartCorner::BottomLeft => "bottom_left",
+            StartCorner::BottomRight => "bottom_right",
+            StartCorner::TopLeft => "top_left",
+            StartCorner::TopRight => "top_right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedGeometry {
+    pub strips: usize,
+    pub leds_per_strip: usize,
+    pub start_corner: StartCorner,
+    /// Every other strip runs in the opposite direction (zig-zag wiring).
+    pub serpentine: bool,
+}
+
+impl Default for LedGeometry {
+    /// 22 × 12 panel, chained from the bottom-left corner in zig-zag.
+    fn default() -> Self {
+        LedGeometry {
+            strips: DEFAULT_NUM_STRIPS,
+            leds_per_strip: DEFAULT_LEDS_PER_STRIP,
+            start_corner: StartCorner::BottomLeft,
+            serpentine: true,
+        }
+    }
+}
+
+impl LedGeometry {
+    pub fn num_leds(&self) -> usize {
+        self.strips * self.leds_per_strip
+    }
+
+    /// Size of one output frame: 3 bytes per LED plus the end marker.
+    pub fn frame_len(&self) -> usize {
+        self.num_leds() * 3 + 1
+    }
+
+    /// Position in the chain of the LED at column `strip` (0 = left) and row `led` (0 = bottom).
+    pub fn physical_index(&self, strip: usize, led: usize) -> usize {
+        let from_right = matches!(self.start_corner, StartCorner::BottomRight | StartCorner::TopRight);
+        let from_top = matches!(self.start_corner, StartCorner::TopLeft | StartCorner::TopRight);
+
+        let chain_strip = if from_right { self.strips - 1 - strip } else { strip };
+        let reversed = from_top ^ (self.serpentine && chain_strip % 2 == 1);
+        let chain_led = if reversed { self.leds_per_strip - 1 - led } else { led };
+
+        chain_strip * self.leds_per_strip + chain_led
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(strips: usize, leds_per_strip: usize, start_corner: StartCorner, serpentine: bool) -> LedGeometry {
+        LedGeometry { strips, leds_per_strip, start_corner, serpentine }
+    }
+
+    #[test]
+    fn every_corner_and_wiring_chains_the_leds_in_order() {
+        // Chain index of each LED of a 3 × 4 panel, one row per strip from the left, bottom LED first.
+        let cases = [
+            (StartCorner::BottomLeft, true, [[0, 1, 2, 3], [7, 6, 5, 4], [8, 9, 10, 11]]),
+            (StartCorner::BottomLeft, false, [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]]),
+            (StartCorner::BottomRight, true, [[8, 9, 10, 11], [7, 6, 5, 4], [0, 1, 2, 3]]),
+            (StartCorner::BottomRight, false, [[8, 9, 10, 11], [4, 5, 6, 7], [0, 1, 2, 3]]),
+            (StartCorner::TopLeft, true, [[3, 2, 1, 0], [4, 5, 6, 7], [11, 10, 9, 8]]),
+            (StartCorner::TopLeft, false, [[3, 2, 1, 0], [7, 6, 5, 4], [11, 10, 9, 8]]),
+            (StartCorner::TopRight, true, [[11, 10, 9, 8], [4, 5, 6, 7], [3, 2, 1, 0]]),
+            (StartCorner::TopRight, false, [[11, 10, 9, 8], [7, 6, 5, 4], [3, 2, 1, 0]]),
+        ];
+        for (start_corner, serpentine, expected) in cases {
+            let geometry = geometry(3, 4, start_corner, serpentine);
+            for (strip, leds) in expected.iter().enumerate() {
+                for (led, &index) in leds.iter().enumerate() {
+                    assert_eq!(geometry.physical_index(strip, led), index, "{} serpentine={} strip {} led {}", start_corner, serpentine, strip, led);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_led_has_its_own_index_within_the_chain() {
+        let corners = [StartCorner::BottomLeft, StartCorner::BottomRight, StartCorner::TopLeft, StartCorner::TopRight];
+        for (strips, leds_per_strip) in [(3, 4), (4, 3), (1, 5), (5, 1), (DEFAULT_NUM_STRIPS, DEFAULT_LEDS_PER_STRIP)] {
+            for start_corner in corners {
+                for serpentine in [true, false] {
+                    let geometry = geometry(strips, leds_per_strip, start_corner, serpentine);
+                    let mut seen = vec![false; geometry.num_leds()];
+                    for strip in 0..strips {
+                        for led in 0..leds_per_strip {
+                            let index = geometry.physical_index(strip, led);
+                            assert!(index < seen.len() && !seen[index], "{:?}: strip {} led {} -> {}", geometry, strip, led, index);
+                            seen[index] = true;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -7,23 +7,38 @@ mod bluetooth;
 mod bluez;
 mod values;
 mod presets;
+mod geometry;
+mod sinks;
+mod audio;
+mod agc;
+mod beat;
+mod calibration;
+mod chroma;
+mod constant_q;
+mod features;
+mod stereo;
+mod strobe;
+mod bands;
+mod weighting;
+mod config;
+mod pipeline;
+mod bench;
 
-use crate::animations::animate_leds;
+use crate::animations::{animate_leds, RenderState};
+use crate::audio::{list_devices, open_audio_source};
+use crate::bench::bench_callback;
 use crate::bluetooth::registration::create_advertisement;
 use crate::bluetooth::visualizer_app::create_and_register_application;
 use crate::bluez::advertisment::register_advertisement;
 use crate::bluez::agent::{register_agent, Agent};
 use crate::bluez::utils::register_object;
+use crate::config::{display_usage, load_config};
 use crate::constants::*;
-use crate::dsp::process_audio_data;
-use crate::settings::{display_usage, get_config};
-use crate::values::StateValues;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::StreamConfig;
+use crate::pipeline::{audio_callback, sample_queue, spawn_dsp, Pipeline};
+use crate::presets::set_preset_dir;
+use crate::sinks::open_sink;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::thread;
-use std::time::Duration;
 use zbus::Connection;
 
 #[tokio::main]
@@ -32,62 +47,75 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     display_usage();
     println!("Starting LED Strip Visualizer...");
 
+    // --- Configuration ---
+    let config = match load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    match &config.source {
+        Some(path) => println!("Configuration loaded from {}", path),
+        None => println!("No configuration file, using defaults and command line"),
+    }
+    set_preset_dir(&config.preset_dir);
+
+    if config.bench_callback {
+        bench_callback(config.settings);
+        return Ok(());
+    }
+    if config.list_devices {
+        return list_devices();
+    }
+
     // --- D-Bus Connection ---
     let connection = Connection::system().await?;
     println!("Connection to dbus established!");
 
-    // --- Configuration ---
-    let settings = get_config();
+    // --- Audio Source ---
+    println!("Using audio input: {}", config.audio_input);
+    let mut audio_source = open_audio_source(&config.audio_input)?;
+    let audio_format = audio_source.format();
+    println!("Audio format: {:?}", audio_format);
+
+    // --- Settings ---
+    let mut settings = config.settings;
+    settings.set_sample_rate(audio_format.sample_rate);
+    let pipeline = Pipeline::new(settings.clone());
     let settings_mutex = Arc::new(Mutex::new(settings));
-    let state_values = StateValues::new(settings_mutex.clone());
-    let state_values_arc_mutex = Arc::new(Mutex::new(state_values));
 
     println!("Current Settings: {:?}", settings_mutex.lock().unwrap());
 
     // --- Audio Setup ---
-    let host = cpal::default_host();
-    let device = host.default_input_device().expect("no capture device found");
-    println!("Using device: {}", device.name()?);
-    let config: StreamConfig = device.default_input_config()?.into();
-    println!("Default input config: {:?}", config);
-    let settings_mutex_for_audio = settings_mutex.clone();
-    let state_values_for_audio = state_values_arc_mutex.clone();
-
-    let input_stream = device.build_input_stream(
-        &config,
-        move |data: &[f32], _: &_| {
-            process_audio_data(data, &state_values_for_audio.clone(), &settings_mutex_for_audio.lock().unwrap().clone());
-        },
-        |err| eprintln!("an error occurred on stream: {}", err),
-        None
-    )?;
-    input_stream.play()?;
+    let (producer, consumer) = sample_queue(&audio_format);
+    spawn_dsp(consumer, pipeline.clone(), audio_format)?;
+    audio_source.start(audio_callback(producer, pipeline.clone(), config.audio_input.pacing()))?;
 
     // --- Bluetooth Agent Setup ---
     let agent = Arc::new(Agent::new(AGENT_PATH.to_string()));
     register_object(&connection, agent).await?;
-    register_agent(&connection, AGENT_PATH, "KeyboardDisplay").await?;
+    register_agent(&connection, AGENT_PATH, "KeyboardDisplay", &config.adapter_path).await?;
 
     // --- Register GATT Application ---
-    _ = create_and_register_application(&connection, settings_mutex.clone()).await?;
+    _ = create_and_register_application(&connection, settings_mutex.clone(), &config.adapter_path).await?;
     println!("GATT Application registered!");
 
     println!("Creating advertisement...");
-    let advert = Arc::new(create_advertisement(ADVERT_PATH.to_string()));
+    let advert = Arc::new(create_advertisement(ADVERT_PATH.to_string(), &config.advertised_name));
     register_object(&connection, advert).await?;
-    register_advertisement(&connection, ADVERT_PATH.to_string()).await?;
+    register_advertisement(&connection, ADVERT_PATH.to_string(), &config.adapter_path).await?;
     println!("Advertisement registered!");
 
-    // --- Serial Setup ---
-    let mut port = serialport::new(PORT, BAUD)
-        .timeout(Duration::from_millis(10))
-        .open()?;
+    // --- LED Output Setup ---
+    println!("Using LED output: {}", config.sink);
+    let mut sink = open_sink(&config.sink)?;
 
-    let settings_for_serial = settings_mutex.clone();
-    let states_values_for_serial = state_values_arc_mutex.clone();
+    let settings_for_sink = settings_mutex.clone();
+    let mut render_state = RenderState::default();
 
     // --- Render Loop ---
     loop {
-        animate_leds(&states_values_for_serial, &settings_for_serial, port.as_mut());
+        animate_leds(&pipeline, &settings_for_sink, &mut render_state, sink.as_mut());
     }
 }
\ No newline at end of file
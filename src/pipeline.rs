@@ -0,0 +1,232 @@
+//! Hand-off between the audio callback, the DSP thread and the render loop.
+//!
+//! The audio callback runs on the driver's real-time thread and must never block, so all it
+//! does is copy its buffer into a single-producer ring. A DSP thread drains the ring, runs the
+//! FFT and publishes an `Analysis`; the render loop publishes the settings it read from the BLE
+//! side. Both snapshots are swapped atomically, so the three threads never wait on each other
+//! and only the render loop touches the `Arc<Mutex<Settings>>` shared with the BLE handlers.
+//!
+//! ```text
+//! audio callback ──ring──▶ DSP thread ──Analysis──▶ render loop ◀──Mutex── BLE
+//!                              ▲                         │
+//!                              └──────── Settings ───────┘
+//! ```
+
+use crate::animations::oscilloscope_history;
+use crate::audio::{AudioCallback, AudioFormat, Pacing};
+use crate::constants::{DSP_POLL_MS, DSP_RESTART_MS, SAMPLE_QUEUE_MS};
+use crate::dsp::process_audio_data;
+use crate::settings::{DisplayMode, Settings};
+use crate::values::{Analysis, StateValues};
+use arc_swap::ArcSwap;
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub struct Pipeline {
+    /// Settings the DSP thread works with, as last published by the render loop.
+    pub settings: ArcSwap<Settings>,
+    /// Latest output of the DSP thread.
+    pub analysis: ArcSwap<Analysis>,
+    /// Audio buffers dropped because the DSP thread fell behind.
+    pub overruns: AtomicUsize,
+}
+
+impl Pipeline {
+    pub fn new(settings: Settings) -> Arc<Self> {
+        Arc::new(Pipeline {
+            settings: ArcSwap::from_pointee(settings),
+            analysis: ArcSwap::from_pointee(Analysis::default()),
+            overruns: AtomicUsize::new(0),
+        })
+    }
+
+    /// Make `settings` the ones the DSP thread uses from its next buffer on.
+    pub fn publish_settings(&self, settings: Arc<Settings>) {
+        self.settings.store(settings);
+    }
+}
+
+/// Ring between the audio callback and the DSP thread, sized for `SAMPLE_QUEUE_MS` of audio.
+pub fn sample_queue(format: &AudioFormat) -> (Producer<f32>, Consumer<f32>) {
+    let capacity = format.sample_rate as usize * format.channels.max(1) as usize * SAMPLE_QUEUE_MS / 1000;
+    RingBuffer::new(capacity)
+}
+
+/// The audio callback for a source delivering at `pacing`.
+///
+/// A live source cannot be held back, so its callback queues the buffer as a whole or drops it,
+/// never blocking or allocating. A `Pacing::Fast` file has no clock to keep up with, so its
+/// callback waits for room instead: every sample is analysed and replays are deterministic.
+pub fn audio_callback(mut producer: Producer<f32>, pipeline: Arc<Pipeline>, pacing: Pacing) -> AudioCallback {
+    Box::new(move |data: &[f32]| {
+        if pacing == Pacing::Fast {
+            // Until the buffer fits, unless it never can or the DSP thread is gone.
+            while producer.slots() < data.len() && data.len() <= producer.buffer().capacity() && !producer.is_abandoned() {
+                thread::sleep(Duration::from_millis(DSP_POLL_MS));
+            }
+        }
+        // Whole buffers only, so the queue always holds complete interleaved frames.
+        match producer.write_chunk_uninit(data.len()) {
+            Ok(chunk) => {
+                chunk.fill_from_iter(data.iter().copied());
+            }
+            Err(_) => {
+                pipeline.overruns.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    })
+}
+
+/// Start the thread that turns queued audio into `Analysis` snapshots.
+/// It exits once the audio callback is dropped and the queue is drained.
+///
+/// A panic in the analysis would otherwise leave the render loop repeating the last `Analysis`
+/// forever; instead it is logged and the analysis starts over with fresh state on the same queue.
+/// The release profile builds with `panic = "abort"`, where the whole process stops instead and
+/// is left to the service manager to restart.
+pub fn spawn_dsp(mut consumer: Consumer<f32>, pipeline: Arc<Pipeline>, format: AudioFormat) -> io::Result<JoinHandle<()>> {
+    thread::Builder::new()
+        .name("dsp".to_string())
+        .spawn(move || restart_on_panic(|| run_dsp(&mut consumer, &pipeline, format)))
+}
+
+/// Run `body` until it returns without panicking.
+fn restart_on_panic(mut body: impl FnMut()) {
+    while panic::catch_unwind(AssertUnwindSafe(&mut body)).is_err() {
+        eprintln!("DSP thread panicked, restarting the analysis in {} ms", DSP_RESTART_MS);
+        thread::sleep(Duration::from_millis(DSP_RESTART_MS));
+    }
+}
+
+/// Body of the DSP thread; returns once the queue is abandoned and drained.
+fn run_dsp(consumer: &mut Consumer<f32>, pipeline: &Pipeline, format: AudioFormat) {
+    let mut state = StateValues::new(&pipeline.settings.load(), format);
+    let mut buffer = Vec::with_capacity(consumer.buffer().capacity());
+
+    loop {
+        let available = consumer.slots();
+        if available == 0 {
+            if consumer.is_abandoned() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(DSP_POLL_MS));
+            continue;
+        }
+
+        let chunk = consumer.read_chunk(available).expect("slots() reported this many samples");
+        let (first, second) = chunk.as_slices();
+        buffer.clear();
+        buffer.extend_from_slice(first);
+        buffer.extend_from_slice(second);
+        chunk.commit_all();
+
+        let settings = pipeline.settings.load();
+        let spectra = process_audio_data(&buffer, &mut state, &settings);
+
+        // The oscilloscope wants every new buffer; the spectrum only changes once per hop.
+        let samples = match settings.display_mode {
+            DisplayMode::Oscilloscope => oscilloscope_history(settings.sample_rate, settings.geometry.strips),
+            _ => 0,
+        };
+        if spectra == 0 && samples == 0 {
+            continue;
+        }
+        pipeline.analysis.store(Arc::new(state.analysis(&settings, samples)));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioSource, FileSource, RawSampleFormat};
+    use cpal::SampleFormat;
+    use std::f32::consts::TAU;
+
+    const FORMAT: AudioFormat = AudioFormat { sample_rate: 44100, channels: 1, sample_format: SampleFormat::F32 };
+
+    fn sine(len: usize) -> Vec<f32> {
+        (0..len).map(|n| (TAU * 1000.0 * n as f32 / 44100.0).sin()).collect()
+    }
+
+    #[test]
+    fn queued_audio_reaches_the_analysis() {
+        let pipeline = Pipeline::new(Settings::default());
+        let (producer, consumer) = sample_queue(&FORMAT);
+        let dsp = spawn_dsp(consumer, pipeline.clone(), FORMAT).unwrap();
+        let mut callback = audio_callback(producer, pipeline.clone(), Pacing::Realtime);
+        for buffer in sine(16384).chunks(512) {
+            callback(buffer);
+        }
+        drop(callback);
+        dsp.join().unwrap();
+
+        let analysis = pipeline.analysis.load();
+        assert_eq!(analysis.levels.len(), Settings::default().geometry.strips);
+        assert!(analysis.levels.iter().any(|&level| level > 0.0));
+        assert_eq!(pipeline.overruns.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn a_full_queue_drops_the_buffer_instead_of_blocking() {
+        let pipeline = Pipeline::new(Settings::default());
+        let (producer, consumer) = RingBuffer::new(8);
+        let mut callback = audio_callback(producer, pipeline.clone(), Pacing::Realtime);
+        callback(&[0.5; 6]);
+        callback(&[0.5; 6]);
+        assert_eq!(pipeline.overruns.load(Ordering::Relaxed), 1);
+        assert_eq!(consumer.slots(), 6);
+    }
+
+    #[test]
+    fn the_dsp_thread_exits_once_the_callback_is_gone_and_the_queue_drained() {
+        let pipeline = Pipeline::new(Settings::default());
+        let (producer, consumer) = sample_queue(&FORMAT);
+        let dsp = spawn_dsp(consumer, pipeline.clone(), FORMAT).unwrap();
+        let mut callback = audio_callback(producer, pipeline, Pacing::Realtime);
+        callback(&sine(4096));
+        drop(callback);
+        dsp.join().unwrap();
+    }
+
+    #[test]
+    fn a_panic_in_the_analysis_starts_it_over() {
+        let mut runs = 0;
+        restart_on_panic(|| {
+            runs += 1;
+            if runs == 1 {
+                panic!("first run");
+            }
+        });
+        assert_eq!(runs, 2);
+    }
+
+    #[test]
+    fn a_fast_file_run_delivers_every_sample() {
+        let samples: Vec<f32> = (0..20_000).map(|n| n as f32).collect();
+        let path = std::env::temp_dir().join(format!("audioleds-fast-{}.raw", std::process::id()));
+        std::fs::write(&path, samples.iter().flat_map(|sample| sample.to_le_bytes()).collect::<Vec<u8>>()).unwrap();
+
+        // A queue much smaller than the file, drained slower than the file is read.
+        let pipeline = Pipeline::new(Settings::default());
+        let (producer, mut consumer) = RingBuffer::new(1024);
+        let mut source = FileSource::open_raw(path.to_str().unwrap(), RawSampleFormat::F32, FORMAT, Pacing::Fast).unwrap();
+        source.start(audio_callback(producer, pipeline.clone(), Pacing::Fast)).unwrap();
+
+        let mut received = Vec::new();
+        while !(consumer.is_abandoned() && consumer.is_empty()) {
+            let chunk = consumer.read_chunk(consumer.slots()).unwrap();
+            received.extend(chunk);
+            thread::sleep(Duration::from_millis(1));
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(received, samples);
+        assert_eq!(pipeline.overruns.load(Ordering::Relaxed), 0);
+    }
+}
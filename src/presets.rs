@@ -1,8 +1,15 @@
 ﻿use std::io::{Read, Write};
-use std::sync::MutexGuard;
+use std::sync::{MutexGuard, OnceLock};
+use crate::agc::AgcSettings;
+use crate::calibration::{CalibrationRequest, CalibrationStatus};
 use crate::color::Color;
-use crate::constants::NUM_LEDS;
-use crate::settings::{AnimationMode, DisplayMode, Settings};
+use crate::constants::{DEFAULT_ATTACK_MS, DEFAULT_DB_CEILING, DEFAULT_RELEASE_MS, DEFAULT_DB_FLOOR, DEFAULT_STROBE_DECAY_MS, DEFAULT_NOISE_GATE_DB, DEFAULT_PEAK_DECAY, DEFAULT_PEAK_HOLD_MS, DEFAULT_AGC_TARGET, DEFAULT_PRESET_PATH, DEFAULT_WEIGHTING_PHON, GAIN, HOP_SIZE, MAX_FFT_SIZE, MAX_HOP_SIZE, MAX_WEIGHTING_PHON, MIN_FFT_SIZE, MIN_HOP_SIZE, MIN_WEIGHTING_PHON, SAMPLE_RATE};
+use crate::geometry::LedGeometry;
+use crate::features::SpectralFeatures;
+use crate::stereo::StereoImage;
+use crate::settings::{AnimationMode, BeatEffect, ColorModulation, DisplayMode, FftWindow, Settings, SpectralFeature, Transform, Weighting, is_valid_fft_size};
+use crate::strobe::StrobeSettings;
+use crate::chroma::{circle_of_fifths_hues, PITCH_CLASSES};
 
 #[derive(Debug)]
 pub struct Preset {
@@ -15,12 +22,35 @@ pub struct Preset {
     pub color2: [u8; 3], // Index 28-30
     pub color3: [u8; 3], // Index 31-33
     pub fft_size: u16, // Index 34-35
-    pub frequencies: [f32; 22], // Number of bytes each frequency has 4 bytes, total 88 bytes for 22 frequencies, Index 36-99
-    pub gains: [f32; 22], // Number of bytes each gain has 4 bytes, total 88 bytes for 22 gains, Index 100-163
+    pub frequencies: Vec<f32>, // One centre frequency per band (22 for the default panel)
+    pub gains: Vec<f32>, // One gain per band, same length as `frequencies`
     pub skew: f32, // Index 164-167
     pub brightness: f32, // Index 168-171
     pub display_mode: DisplayMode, // enum encoded as u8, // Index 172
     pub animation_mode: AnimationMode, // enum encoded as u8, // Index 173
+    pub peak_hold_ms: u16, // Extension field `peak_hold_ms=<u16>`
+    pub peak_decay: f32, // Extension field `peak_decay=<f32>`
+    pub fft_window: FftWindow, // Extension field `window=<u8>`
+    pub hop_size: u16, // Extension field `hop_size=<u16>`
+    pub agc_enabled: bool, // Extension field `agc=<0|1>`
+    pub agc_target: f32, // Extension field `agc_target=<f32>`
+    pub db_scale: bool, // Extension field `db_scale=<0|1>`
+    pub db_floor: f32, // Extension field `db_floor=<f32>`
+    pub db_ceiling: f32, // Extension field `db_ceiling=<f32>`
+    pub noise_gate_db: Vec<f32>, // Extension field `noise_gate=[<f32>|...]` (a single `<f32>` gates every band alike)
+    pub weighting: Weighting, // Extension field `weighting=<u8>`
+    pub weighting_phon: f32, // Extension field `phon=<f32>`
+    pub attack_ms: u16, // Extension field `attack_ms=<u16>`
+    pub release_ms: u16, // Extension field `release_ms=<u16>`
+    pub band_attack_ms: Vec<f32>, // Extension field `band_attack_ms=[<f32>|...]`, empty for the global time
+    pub band_release_ms: Vec<f32>, // Extension field `band_release_ms=[<f32>|...]`
+    pub beat_effect: BeatEffect, // Extension field `beat_effect=<u8>`
+    pub strobe_decay_ms: u16, // Extension field `strobe_decay_ms=<u16>`; the limiter is not part of a preset
+    pub chroma_hues: [f32; PITCH_CLASSES], // Extension field `chroma_hues=[<f32>|...]`, 12 hues in degrees
+    pub color_modulation: ColorModulation, // Extension field `color_mod=<u8>`
+    pub modulation_feature: SpectralFeature, // Extension field `color_mod_feature=<u8>`
+    pub stereo_split: bool, // Extension field `stereo_split=<0|1>`
+    pub transform: Transform, // Extension field `transform=<u8>`
 }
 
 impl Preset {
@@ -35,12 +65,35 @@ impl Preset {
             color2: settings.color2.to_rgb_slice(),
             color3: settings.color3.to_rgb_slice(),
             fft_size: settings.fft_size as u16,
-            frequencies: settings.frequencies.clone().try_into().unwrap_or([0.0; 22]),
-            gains: settings.gains.clone().try_into().unwrap_or([0.0; 22]),
+            frequencies: settings.frequencies.clone(),
+            gains: settings.gains.clone(),
             skew: settings.skew,
             brightness: settings.brightness,
             display_mode: settings.display_mode.clone(),
             animation_mode: settings.animation_mode.clone(),
+            peak_hold_ms: settings.peak_hold_ms.min(u16::MAX as usize) as u16,
+            peak_decay: settings.peak_decay,
+            fft_window: settings.fft_window,
+            hop_size: settings.hop_size.min(u16::MAX as usize) as u16,
+            agc_enabled: settings.agc.enabled,
+            agc_target: settings.agc.target,
+            db_scale: settings.db_scale,
+            db_floor: settings.db_floor,
+            db_ceiling: settings.db_ceiling,
+            noise_gate_db: settings.noise_gate_db.clone(),
+            weighting: settings.weighting,
+            weighting_phon: settings.weighting_phon,
+            attack_ms: settings.attack_ms.min(u16::MAX as usize) as u16,
+            release_ms: settings.release_ms.min(u16::MAX as usize) as u16,
+            band_attack_ms: settings.band_attack_ms.clone(),
+            band_release_ms: settings.band_release_ms.clone(),
+            beat_effect: settings.beat_effect,
+            strobe_decay_ms: settings.strobe.decay_ms.min(u16::MAX as usize) as u16,
+            chroma_hues: settings.chroma_hues,
+            color_modulation: settings.color_modulation,
+            modulation_feature: settings.modulation_feature,
+            stereo_split: settings.stereo_split,
+            transform: settings.transform,
         }
     }
 
@@ -53,18 +106,54 @@ impl Preset {
             color2: Color::from_slice(&self.color2),
             color3: Color::from_slice(&self.color3),
             fft_size: self.fft_size as usize,
+            fft_window: self.fft_window,
+            transform: self.transform,
+            hop_size: self.hop_size as usize,
             frequencies: self.frequencies.to_vec(),
             gains: self.gains.to_vec(),
             skew: self.skew,
+            db_scale: self.db_scale,
+            db_floor: self.db_floor,
+            db_ceiling: self.db_ceiling,
+            noise_gate_db: Vec::new(), // Set by `set_noise_gates`
+            weighting: self.weighting,
+            weighting_phon: self.weighting_phon,
+            attack_ms: self.attack_ms as usize,
+            release_ms: self.release_ms as usize,
+            band_attack_ms: Vec::new(), // Set by `set_band_envelopes`
+            band_release_ms: Vec::new(),
             brightness: self.brightness,
             display_mode: self.display_mode.clone(),
             animation_mode: self.animation_mode.clone(),
-            led_buffer: vec![0; NUM_LEDS * 3 + 1], // Assuming 22 frequencies, each with RGB values
+            peak_hold_ms: self.peak_hold_ms as usize,
+            peak_decay: self.peak_decay,
+            strobe: StrobeSettings { decay_ms: self.strobe_decay_ms as usize, ..StrobeSettings::default() },
+            agc: AgcSettings { enabled: self.agc_enabled, target: self.agc_target, ..AgcSettings::default() },
+            agc_gain: GAIN,
+            beat_effect: self.beat_effect,
+            bpm: 0.0,
+            beat_count: 0,
+            chroma_hues: self.chroma_hues,
+            chord: None,
+            key: None,
+            color_modulation: self.color_modulation,
+            modulation_feature: self.modulation_feature,
+            features: SpectralFeatures::default(),
+            stereo_split: self.stereo_split,
+            stereo: StereoImage::default(),
+            calibration: CalibrationRequest::default(),
+            calibration_status: CalibrationStatus::default(),
+            led_buffer: vec![0; LedGeometry::default().frame_len()],
+            geometry: LedGeometry::default(),
             cached_df: 0.0, // Set by `set_fft_size`
+            sample_rate: SAMPLE_RATE, // Replaced by the capture rate at startup
             selected_preset: 0, // Default value, can be set later
             active_preset: self.index as usize,
         };
         settings.set_fft_size(self.fft_size as usize);
+        settings.set_bands(&self.frequencies, &self.gains);
+        settings.set_band_envelopes(&self.band_attack_ms, &self.band_release_ms);
+        settings.set_noise_gates(&self.noise_gate_db);
         settings
     }
 
@@ -75,22 +164,52 @@ impl Preset {
         settings.color1 = Color::from_slice(&self.color1);
         settings.color2 = Color::from_slice(&self.color2);
         settings.color3 = Color::from_slice(&self.color3);
-        settings.fft_size = self.fft_size as usize;
-        settings.frequencies = self.frequencies.to_vec();
-        settings.gains = self.gains.to_vec();
+        settings.set_fft_size(self.fft_size as usize);
+        settings.fft_window = self.fft_window;
+        settings.transform = self.transform;
+        settings.hop_size = self.hop_size as usize;
+        settings.set_bands(&self.frequencies, &self.gains);
         settings.skew = self.skew;
         settings.brightness = self.brightness;
         settings.display_mode = self.display_mode.clone();
         settings.animation_mode = self.animation_mode.clone();
+        settings.peak_hold_ms = self.peak_hold_ms as usize;
+        settings.peak_decay = self.peak_decay;
+        settings.agc.enabled = self.agc_enabled;
+        settings.agc.target = self.agc_target;
+        settings.db_scale = self.db_scale;
+        settings.db_floor = self.db_floor;
+        settings.db_ceiling = self.db_ceiling;
+        settings.set_noise_gates(&self.noise_gate_db);
+        settings.weighting = self.weighting;
+        settings.weighting_phon = self.weighting_phon;
+        settings.attack_ms = self.attack_ms as usize;
+        settings.release_ms = self.release_ms as usize;
+        settings.set_band_envelopes(&self.band_attack_ms, &self.band_release_ms);
+        settings.beat_effect = self.beat_effect;
+        settings.strobe.decay_ms = self.strobe_decay_ms as usize;
+        settings.chroma_hues = self.chroma_hues;
+        settings.color_modulation = self.color_modulation;
+        settings.modulation_feature = self.modulation_feature;
+        settings.stereo_split = self.stereo_split;
         settings.active_preset = self.index as usize;
     }
 }
 
-const PRESET_PATH: &str = "presets";
+static PRESET_PATH: OnceLock<String> = OnceLock::new();
+
+/// Directory holding the `preset_<index>.bin` files. Set once at startup from the configuration.
+pub fn set_preset_dir(dir: &str) {
+    let _ = PRESET_PATH.set(dir.to_string());
+}
+
+fn preset_dir() -> &'static str {
+    PRESET_PATH.get().map(String::as_str).unwrap_or(DEFAULT_PRESET_PATH)
+}
 
 pub fn save_preset(preset: &Preset) -> std::io::Result<()> {
     let preset_index = preset.index;
-    let preset_filename = format!("{}/preset_{}.bin", PRESET_PATH, preset_index);
+    let preset_filename = format!("{}/preset_{}.bin", preset_dir(), preset_index);
     println!("Preset filename: {}", preset_filename);
     let encoded = encode_preset_csv(preset);
 
@@ -102,7 +221,7 @@ pub fn save_preset(preset: &Preset) -> std::io::Result<()> {
 }
 
 pub fn load_preset(index: u8) -> std::io::Result<Preset> {
-    let preset_filename = format!("{}/preset_{}.bin", PRESET_PATH, index);
+    let preset_filename = format!("{}/preset_{}.bin", preset_dir(), index);
     let data = std::fs::read(preset_filename)?;
     let preset: Preset = decode_preset_csv(std::str::from_utf8(&data)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("UTF-8 decoding error: {}", e)))?
@@ -112,7 +231,7 @@ pub fn load_preset(index: u8) -> std::io::Result<Preset> {
 
 pub fn list_presets() -> std::io::Result<Vec<Preset>> {
     let mut presets = Vec::new();
-    for entry in std::fs::read_dir(PRESET_PATH)? {
+    for entry in std::fs::read_dir(preset_dir())? {
         let entry = entry?;
         if entry.path().extension().and_then(|s| s.to_str()) == Some("bin") {
             let preset_index = entry.file_name().to_string_lossy()
@@ -129,7 +248,7 @@ pub fn list_presets() -> std::io::Result<Vec<Preset>> {
 }
 
 pub fn delete_preset(index: u8) -> std::io::Result<()> {
-    let preset_filename = format!("{}/preset_{}.bin", PRESET_PATH, index);
+    let preset_filename = format!("{}/preset_{}.bin", preset_dir(), index);
     std::fs::remove_file(preset_filename)?;
     Ok(())
 }
@@ -176,9 +295,15 @@ fn encode_preset_csv(preset: &Preset) -> String {
     let name_str = name_bytes_to_string(&preset.name).replace(',', " ");
     let frequencies_str = preset.frequencies.iter().map(|f| f.to_string()).collect::<Vec<String>>().join("|");
     let gains_str = preset.gains.iter().map(|g| g.to_string()).collect::<Vec<String>>().join("|");
+    let chroma_hues_str = preset.chroma_hues.iter().map(|h| h.to_string()).collect::<Vec<String>>().join("|");
+    let band_attack_str = preset.band_attack_ms.iter().map(|t| t.to_string()).collect::<Vec<String>>().join("|");
+    let noise_gate_str = preset.noise_gate_db.iter().map(|g| g.to_string()).collect::<Vec<String>>().join("|");
+    let band_release_str = preset.band_release_ms.iter().map(|t| t.to_string()).collect::<Vec<String>>().join("|");
 
+    // The first 15 fields are the original format; newer fields follow as `key=value`
+    // so readers that only know the original fields can skip them.
     format!(
-        "{},{},{},{},{},{},{},{},{},[{}],[{}],{},{},{},{}",
+        "{},{},{},{},{},{},{},{},{},[{}],[{}],{},{},{},{},peak_hold_ms={},peak_decay={},window={},hop_size={},agc={},agc_target={},db_scale={},db_floor={},db_ceiling={},noise_gate=[{}],weighting={},phon={},attack_ms={},release_ms={},band_attack_ms=[{}],band_release_ms=[{}],beat_effect={},strobe_decay_ms={},chroma_hues=[{}],color_mod={},color_mod_feature={},stereo_split={},transform={}",
         preset.index,
         name_str,
         preset.smooth_size,
@@ -193,16 +318,39 @@ fn encode_preset_csv(preset: &Preset) -> String {
         preset.skew,
         preset.brightness,
         preset.display_mode.clone() as u8, // Assuming DisplayMode can be cast to u8
-        preset.animation_mode.clone() as u8 // Assuming AnimationMode can be cast to u8
+        preset.animation_mode.clone() as u8, // Assuming AnimationMode can be cast to u8
+        preset.peak_hold_ms,
+        preset.peak_decay,
+        preset.fft_window as u8,
+        preset.hop_size,
+        preset.agc_enabled as u8,
+        preset.agc_target,
+        preset.db_scale as u8,
+        preset.db_floor,
+        preset.db_ceiling,
+        noise_gate_str,
+        preset.weighting as u8,
+        preset.weighting_phon,
+        preset.attack_ms,
+        preset.release_ms,
+        band_attack_str,
+        band_release_str,
+        preset.beat_effect as u8,
+        preset.strobe_decay_ms,
+        chroma_hues_str,
+        preset.color_modulation as u8,
+        preset.modulation_feature as u8,
+        preset.stereo_split as u8,
+        preset.transform as u8
     )
 }
 
 fn decode_preset_csv(csv: &str) -> Result<Preset, PresetCsvError> {
     let parts: Vec<&str> = csv.split(',').collect();
 
-    if parts.len() != 15 {
+    if parts.len() < 15 {
         println!("CSV parts: {:?}", parts);
-        return Err(PresetCsvError::InvalidFormat(format!("Expected 15 CSV parts, got {}", parts.len())));
+        return Err(PresetCsvError::InvalidFormat(format!("Expected at least 15 CSV parts, got {}", parts.len())));
     }
 
     let index = parts[0].parse::<u8>().map_err(|e| PresetCsvError::ParseError(format!("Index: {}", e)))?;
@@ -221,18 +369,23 @@ fn decode_preset_csv(csv: &str) -> Result<Preset, PresetCsvError> {
     // println!("Decoded colors: {:?}, {:?}, {:?}", color1, color2, color3);
 
     let fft_size = parts[8].parse::<u16>().map_err(|e| PresetCsvError::ParseError(format!("FFT Size: {}", e)))?;
+    if !is_valid_fft_size(fft_size as usize) {
+        return Err(PresetCsvError::ParseError(format!("FFT Size: {} is not a power of two between {} and {}", fft_size, MIN_FFT_SIZE, MAX_FFT_SIZE)));
+    }
     // println!("Decoded FFT size: {}", fft_size);
 
-    let parse_f32_array = |s: &str, context: &str| -> Result<[f32; 22], PresetCsvError> {
+    let parse_f32_array = |s: &str, context: &str| -> Result<Vec<f32>, PresetCsvError> {
         let content = s.strip_prefix('[').unwrap_or(s).strip_suffix(']').unwrap_or(s);
-        let vec_f32: Vec<f32> = content.split('|')
+        content.split('|')
             .map(|val_str| val_str.parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("Failed to parse f32 for {} value '{}': {}", context, val_str, e))))
-            .collect::<Result<Vec<f32>, _>>()?;
-        vec_f32.try_into().map_err(|_| PresetCsvError::ParseError(format!("{} array must contain exactly 22 values, got {}", context, parts.len())))
+            .collect::<Result<Vec<f32>, _>>()
     };
 
     let frequencies = parse_f32_array(parts[9], "Frequencies")?;
     let gains = parse_f32_array(parts[10], "Gains")?;
+    if frequencies.len() != gains.len() {
+        return Err(PresetCsvError::ParseError(format!("Frequencies and Gains must have the same length, got {} and {}", frequencies.len(), gains.len())));
+    }
 
     let skew = parts[11].parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("Skew: {}", e)))?;
     let brightness = parts[12].parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("Brightness: {}", e)))?;
@@ -246,6 +399,137 @@ fn decode_preset_csv(csv: &str) -> Result<Preset, PresetCsvError> {
     let animation_mode = AnimationMode::from_u8(animation_mode_val) // Assuming AnimationMode::from_u8(u8) -> Option<AnimationMode>
         .ok_or_else(|| PresetCsvError::ParseError(format!("Invalid animation mode code: {}", animation_mode_val)))?;
 
+    // Extension fields, absent from presets saved by older versions
+    let mut peak_hold_ms = DEFAULT_PEAK_HOLD_MS as u16;
+    let mut peak_decay = DEFAULT_PEAK_DECAY;
+    let mut fft_window = FftWindow::Rectangular; // What older versions did
+    let mut hop_size = HOP_SIZE as u16;
+    let mut agc_enabled = false;
+    let mut agc_target = DEFAULT_AGC_TARGET;
+    let mut db_scale = false;
+    let mut db_floor = DEFAULT_DB_FLOOR;
+    let mut db_ceiling = DEFAULT_DB_CEILING;
+    let mut noise_gate_db = vec![DEFAULT_NOISE_GATE_DB];
+    let mut weighting = Weighting::Skew;
+    let mut weighting_phon = DEFAULT_WEIGHTING_PHON;
+    let mut attack_ms = DEFAULT_ATTACK_MS as u16;
+    let mut release_ms = DEFAULT_RELEASE_MS as u16;
+    let mut band_attack_ms = Vec::new();
+    let mut band_release_ms = Vec::new();
+    let mut beat_effect = BeatEffect::Off;
+    let mut strobe_decay_ms = DEFAULT_STROBE_DECAY_MS as u16;
+    let mut chroma_hues = circle_of_fifths_hues();
+    let mut color_modulation = ColorModulation::Off;
+    let mut modulation_feature = SpectralFeature::Centroid;
+    let mut stereo_split = false;
+    let mut transform = Transform::Fft;
+    let parse_times = |s: &str, context: &str| -> Result<Vec<f32>, PresetCsvError> {
+        if s == "[]" {
+            return Ok(Vec::new());
+        }
+        let times = parse_f32_array(s, context)?;
+        if times.iter().any(|t| !(0.0..=u16::MAX as f32).contains(t)) {
+            return Err(PresetCsvError::ParseError(format!("{}: every time must be within 0..={} ms", context, u16::MAX)));
+        }
+        Ok(times)
+    };
+    for extension in &parts[15..] {
+        let (key, value) = extension.split_once('=')
+            .ok_or_else(|| PresetCsvError::InvalidFormat(format!("Expected key=value, got '{}'", extension)))?;
+        match key {
+            "peak_hold_ms" => peak_hold_ms = value.parse::<u16>().map_err(|e| PresetCsvError::ParseError(format!("Peak Hold: {}", e)))?,
+            "peak_decay" => peak_decay = value.parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("Peak Decay: {}", e)))?,
+            "window" => {
+                let code = value.parse::<u8>().map_err(|e| PresetCsvError::ParseError(format!("FFT Window: {}", e)))?;
+                fft_window = FftWindow::from_u8(code)
+                    .ok_or_else(|| PresetCsvError::ParseError(format!("Invalid FFT window code: {}", code)))?;
+            }
+            "hop_size" => {
+                hop_size = value.parse::<u16>().map_err(|e| PresetCsvError::ParseError(format!("Hop Size: {}", e)))?;
+                if !(MIN_HOP_SIZE..=MAX_HOP_SIZE).contains(&(hop_size as usize)) {
+                    return Err(PresetCsvError::ParseError(format!("Hop Size: {} is outside {}..={}", hop_size, MIN_HOP_SIZE, MAX_HOP_SIZE)));
+                }
+            }
+            "agc" => agc_enabled = match value {
+                "0" => false,
+                "1" => true,
+                _ => return Err(PresetCsvError::ParseError(format!("AGC: expected 0 or 1, got '{}'", value))),
+            },
+            "agc_target" => {
+                agc_target = value.parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("AGC Target: {}", e)))?;
+                if !(agc_target > 0.0 && agc_target <= 1.0) {
+                    return Err(PresetCsvError::ParseError(format!("AGC Target: {} is outside 0..=1", agc_target)));
+                }
+            }
+            "db_scale" => db_scale = match value {
+                "0" => false,
+                "1" => true,
+                _ => return Err(PresetCsvError::ParseError(format!("dB Scale: expected 0 or 1, got '{}'", value))),
+            },
+            "db_floor" => db_floor = value.parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("dB Floor: {}", e)))?,
+            "db_ceiling" => db_ceiling = value.parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("dB Ceiling: {}", e)))?,
+            "noise_gate" if value != "[]" => noise_gate_db = parse_f32_array(value, "Noise Gate")?,
+            "weighting" => {
+                let code = value.parse::<u8>().map_err(|e| PresetCsvError::ParseError(format!("Weighting: {}", e)))?;
+                weighting = Weighting::from_u8(code)
+                    .ok_or_else(|| PresetCsvError::ParseError(format!("Invalid weighting code: {}", code)))?;
+            }
+            "phon" => {
+                weighting_phon = value.parse::<f32>().map_err(|e| PresetCsvError::ParseError(format!("Phon: {}", e)))?;
+                if !(MIN_WEIGHTING_PHON..=MAX_WEIGHTING_PHON).contains(&weighting_phon) {
+                    return Err(PresetCsvError::ParseError(format!("Phon: {} is outside {}..={}", weighting_phon, MIN_WEIGHTING_PHON, MAX_WEIGHTING_PHON)));
+                }
+            }
+            "attack_ms" => attack_ms = value.parse::<u16>().map_err(|e| PresetCsvError::ParseError(format!("Attack: {}", e)))?,
+            "release_ms" => release_ms = value.parse::<u16>().map_err(|e| PresetCsvError::ParseError(format!("Release: {}", e)))?,
+            "band_attack_ms" => band_attack_ms = parse_times(value, "Band Attack")?,
+            "band_release_ms" => band_release_ms = parse_times(value, "Band Release")?,
+            "beat_effect" => {
+                let code = value.parse::<u8>().map_err(|e| PresetCsvError::ParseError(format!("Beat Effect: {}", e)))?;
+                beat_effect = BeatEffect::from_u8(code)
+                    .ok_or_else(|| PresetCsvError::ParseError(format!("Invalid beat effect code: {}", code)))?;
+            }
+            "strobe_decay_ms" => {
+                strobe_decay_ms = value.parse::<u16>().map_err(|e| PresetCsvError::ParseError(format!("Strobe Decay: {}", e)))?;
+                if !(10..=5_000).contains(&strobe_decay_ms) {
+                    return Err(PresetCsvError::ParseError(format!("Strobe Decay: {} ms is outside 10..=5000", strobe_decay_ms)));
+                }
+            }
+            "chroma_hues" => {
+                let hues = parse_f32_array(value, "Chroma Hues")?;
+                chroma_hues = hues.as_slice().try_into()
+                    .map_err(|_| PresetCsvError::ParseError(format!("Chroma Hues: expected {} hues, got {}", PITCH_CLASSES, hues.len())))?;
+                if chroma_hues.iter().any(|hue| !(0.0..=360.0).contains(hue)) {
+                    return Err(PresetCsvError::ParseError("Chroma Hues: every hue must be within 0..=360 degrees".to_string()));
+                }
+            }
+            "color_mod" => {
+                let code = value.parse::<u8>().map_err(|e| PresetCsvError::ParseError(format!("Color Modulation: {}", e)))?;
+                color_modulation = ColorModulation::from_u8(code)
+                    .ok_or_else(|| PresetCsvError::ParseError(format!("Invalid color modulation code: {}", code)))?;
+            }
+            "color_mod_feature" => {
+                let code = value.parse::<u8>().map_err(|e| PresetCsvError::ParseError(format!("Color Modulation Feature: {}", e)))?;
+                modulation_feature = SpectralFeature::from_u8(code)
+                    .ok_or_else(|| PresetCsvError::ParseError(format!("Invalid spectral feature code: {}", code)))?;
+            }
+            "stereo_split" => stereo_split = match value {
+                "0" => false,
+                "1" => true,
+                _ => return Err(PresetCsvError::ParseError(format!("Stereo Split: expected 0 or 1, got '{}'", value))),
+            },
+            "transform" => {
+                let code = value.parse::<u8>().map_err(|e| PresetCsvError::ParseError(format!("Transform: {}", e)))?;
+                transform = Transform::from_u8(code)
+                    .ok_or_else(|| PresetCsvError::ParseError(format!("Invalid transform code: {}", code)))?;
+            }
+            _ => {} // Written by a newer version
+        }
+    }
+    if !db_floor.is_finite() || !db_ceiling.is_finite() || db_floor >= db_ceiling {
+        return Err(PresetCsvError::ParseError(format!("dB Range: floor {} is not below ceiling {}", db_floor, db_ceiling)));
+    }
+
     Ok(Preset {
         index,
         name,
@@ -262,6 +546,29 @@ fn decode_preset_csv(csv: &str) -> Result<Preset, PresetCsvError> {
         brightness,
         display_mode,
         animation_mode,
+        peak_hold_ms,
+        peak_decay,
+        fft_window,
+        hop_size,
+        agc_enabled,
+        agc_target,
+        db_scale,
+        db_floor,
+        db_ceiling,
+        noise_gate_db,
+        weighting,
+        weighting_phon,
+        attack_ms,
+        release_ms,
+        band_attack_ms,
+        band_release_ms,
+        beat_effect,
+        strobe_decay_ms,
+        chroma_hues,
+        color_modulation,
+        modulation_feature,
+        stereo_split,
+        transform,
     })
 }
 
@@ -286,3 +593,33 @@ pub fn decode_preset(data: &[u8]) -> std::io::Result<Preset> {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noise_gates_round_trip_per_band() {
+        let mut settings = Settings::default();
+        settings.set_noise_gates(&[-60.0, -90.0]);
+        let preset = Preset::from_settings(&settings, 3, string_to_name_bytes("Gates"));
+        let decoded = decode_preset_csv(&encode_preset_csv(&preset)).unwrap();
+        assert_eq!(decoded.noise_gate_db, settings.noise_gate_db);
+
+        // Older presets have one gate for every band
+        let csv = encode_preset_csv(&preset).split(',')
+            .map(|field| if field.starts_with("noise_gate=") { "noise_gate=-70" } else { field })
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(decode_preset_csv(&csv).unwrap().noise_gate_db, vec![-70.0]);
+    }
+
+    #[test]
+    fn presets_with_an_unsupported_fft_size_are_rejected() {
+        let preset = Preset::from_settings(&Settings::default(), 0, string_to_name_bytes("FFT"));
+        let csv = encode_preset_csv(&preset);
+        let fields: Vec<&str> = csv.split(',').collect();
+        let bad = [&fields[..8], &["1000"], &fields[9..]].concat().join(",");
+        assert!(matches!(decode_preset_csv(&bad), Err(PresetCsvError::ParseError(_))));
+    }
+}
@@ -1,12 +1,23 @@
-﻿use crate::color::{color_from_string, Color};
+﻿use crate::agc::AgcSettings;
+use crate::bands::BandLayout;
+use crate::calibration::{CalibrationRequest, CalibrationStatus};
+use crate::chroma::{circle_of_fifths_hues, Tonality, PITCH_CLASSES};
+use crate::color::{color_from_string, Color};
+use crate::features::SpectralFeatures;
+use crate::stereo::StereoImage;
+use crate::geometry::LedGeometry;
+use crate::strobe::StrobeSettings;
 use crate::DEFAULT_SMOOTH_SIZE;
-use crate::constants::{DEFAULT_SKEW, FFT_SIZE, FPS, GAIN, SAMPLE_RATE};
+use crate::constants::{DEFAULT_ATTACK_MS, DEFAULT_DB_CEILING, DEFAULT_RELEASE_MS, DEFAULT_DB_FLOOR, DEFAULT_NOISE_GATE_DB, DEFAULT_PEAK_DECAY, DEFAULT_PEAK_HOLD_MS, DEFAULT_SKEW, DEFAULT_WEIGHTING_PHON, FFT_SIZE, FPS, GAIN, HOP_SIZE, MAX_FFT_SIZE, MIN_FFT_SIZE, SAMPLE_RATE};
+use std::str::FromStr;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum DisplayMode {
     Spectrum = 0,
     Oscilloscope = 1,
     ColorGradient = 2,
+    /// One bar per pitch class in its palette colour, the notes of the current chord lit up, see `chroma.rs`.
+    Chroma = 3,
 }
 
 impl DisplayMode {
@@ -15,11 +26,26 @@ impl DisplayMode {
             0 => Some(DisplayMode::Spectrum),
             1 => Some(DisplayMode::Oscilloscope),
             2 => Some(DisplayMode::ColorGradient),
+            3 => Some(DisplayMode::Chroma),
             _ => None,
         }
     }
 }
 
+impl FromStr for DisplayMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spectrum" => Ok(DisplayMode::Spectrum),
+            "oscilloscope" => Ok(DisplayMode::Oscilloscope),
+            "color_gradient" => Ok(DisplayMode::ColorGradient),
+            "chroma" => Ok(DisplayMode::Chroma),
+            _ => Err(format!("Invalid display mode '{}' (expected spectrum, oscilloscope, color_gradient or chroma)", s)),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum AnimationMode {
     Full = 0,
@@ -28,6 +54,10 @@ pub enum AnimationMode {
     FullMiddle = 3,
     FullMiddleWithMax = 4,
     PointsMiddle = 5,
+    /// `Full` with the whole matrix flashing `color3` on each kick, see `strobe.rs`.
+    Strobe = 6,
+    /// `Full` with each strip flashing `color3` on each kick, as far as it is lit.
+    StrobeStrips = 7,
 }
 
 impl AnimationMode {
@@ -39,159 +69,476 @@ impl AnimationMode {
             3 => Some(AnimationMode::FullMiddle),
             4 => Some(AnimationMode::FullMiddleWithMax),
             5 => Some(AnimationMode::PointsMiddle),
+            6 => Some(AnimationMode::Strobe),
+            7 => Some(AnimationMode::StrobeStrips),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for AnimationMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(AnimationMode::Full),
+            "full_with_max" => Ok(AnimationMode::FullWithMax),
+            "points" => Ok(AnimationMode::Points),
+            "full_middle" => Ok(AnimationMode::FullMiddle),
+            "full_middle_with_max" => Ok(AnimationMode::FullMiddleWithMax),
+            "points_middle" => Ok(AnimationMode::PointsMiddle),
+            "strobe" => Ok(AnimationMode::Strobe),
+            "strobe_strips" => Ok(AnimationMode::StrobeStrips),
+            _ => Err(format!(
+                "Invalid animation mode '{}' (expected full, full_with_max, points, full_middle, full_middle_with_max, points_middle, strobe or strobe_strips)",
+                s
+            )),
+        }
+    }
+}
+
+/// How the band levels are measured.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Transform {
+    /// One FFT of `fft_size` samples for every band.
+    Fft = 0,
+    /// One FFT size per band, long in the bass and short in the treble (see `constant_q`).
+    ConstantQ = 1,
+}
+
+impl Transform {
+    pub fn from_u8(value: u8) -> Option<Transform> {
+        match value {
+            0 => Some(Transform::Fft),
+            1 => Some(Transform::ConstantQ),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fft" => Ok(Transform::Fft),
+            "constant_q" => Ok(Transform::ConstantQ),
+            _ => Err(format!("Invalid transform '{}' (expected fft or constant_q)", s)),
+        }
+    }
+}
+
+/// Window applied to each FFT frame: less leakage between bands costs a wider main lobe.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FftWindow {
+    Rectangular = 0,
+    Hann = 1,
+    Hamming = 2,
+    BlackmanHarris = 3,
+}
+
+impl FftWindow {
+    pub fn from_u8(value: u8) -> Option<FftWindow> {
+        match value {
+            0 => Some(FftWindow::Rectangular),
+            1 => Some(FftWindow::Hann),
+            2 => Some(FftWindow::Hamming),
+            3 => Some(FftWindow::BlackmanHarris),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for FftWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rectangular" => Ok(FftWindow::Rectangular),
+            "hann" => Ok(FftWindow::Hann),
+            "hamming" => Ok(FftWindow::Hamming),
+            "blackman_harris" => Ok(FftWindow::BlackmanHarris),
+            _ => Err(format!("Invalid FFT window '{}' (expected rectangular, hann, hamming or blackman_harris)", s)),
+        }
+    }
+}
+
+/// What the animations do on each beat found by `beat::BeatTracker`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BeatEffect {
+    Off = 0,
+    /// Wash the strips towards `color3` at the start of each beat.
+    Flash = 1,
+    /// Levels are full on the beat and sink until the next one.
+    Pulse = 2,
+    /// Rotate `color1`, `color2` and `color3` by one on every beat.
+    Advance = 3,
+}
+
+impl BeatEffect {
+    pub fn from_u8(value: u8) -> Option<BeatEffect> {
+        match value {
+            0 => Some(BeatEffect::Off),
+            1 => Some(BeatEffect::Flash),
+            2 => Some(BeatEffect::Pulse),
+            3 => Some(BeatEffect::Advance),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for BeatEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(BeatEffect::Off),
+            "flash" => Ok(BeatEffect::Flash),
+            "pulse" => Ok(BeatEffect::Pulse),
+            "advance" => Ok(BeatEffect::Advance),
+            _ => Err(format!("Invalid beat effect '{}' (expected off, flash, pulse or advance)", s)),
+        }
+    }
+}
+
+/// How the palette follows the sound, see `features.rs`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorModulation {
+    Off = 0,
+    /// Turn `color1`, `color2` and `color3` around the colour wheel as the feature rises.
+    Hue = 1,
+    /// Mix `color1` into `color2` by the feature instead of by LED position.
+    Mix = 2,
+}
+
+impl ColorModulation {
+    pub fn from_u8(value: u8) -> Option<ColorModulation> {
+        match value {
+            0 => Some(ColorModulation::Off),
+            1 => Some(ColorModulation::Hue),
+            2 => Some(ColorModulation::Mix),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ColorModulation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(ColorModulation::Off),
+            "hue" => Ok(ColorModulation::Hue),
+            "mix" => Ok(ColorModulation::Mix),
+            _ => Err(format!("Invalid color modulation '{}' (expected off, hue or mix)", s)),
+        }
+    }
+}
+
+/// The spectral feature `ColorModulation` follows.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpectralFeature {
+    Centroid = 0,
+    Rolloff = 1,
+    Flatness = 2,
+    Rms = 3,
+}
+
+impl SpectralFeature {
+    pub fn from_u8(value: u8) -> Option<SpectralFeature> {
+        match value {
+            0 => Some(SpectralFeature::Centroid),
+            1 => Some(SpectralFeature::Rolloff),
+            2 => Some(SpectralFeature::Flatness),
+            3 => Some(SpectralFeature::Rms),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for SpectralFeature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "centroid" => Ok(SpectralFeature::Centroid),
+            "rolloff" => Ok(SpectralFeature::Rolloff),
+            "flatness" => Ok(SpectralFeature::Flatness),
+            "rms" => Ok(SpectralFeature::Rms),
+            _ => Err(format!("Invalid spectral feature '{}' (expected centroid, rolloff, flatness or rms)", s)),
+        }
+    }
+}
+
+/// How band levels are weighted across frequency before gains are applied.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Weighting {
+    /// `dsp::weight`, the skew power law.
+    Skew = 0,
+    /// IEC 61672 A-weighting.
+    A = 1,
+    /// IEC 61672 C-weighting.
+    C = 2,
+    /// ISO 226 equal-loudness contour at `Settings::weighting_phon`.
+    Iso226 = 3,
+}
+
+impl Weighting {
+    pub fn from_u8(value: u8) -> Option<Weighting> {
+        match value {
+            0 => Some(Weighting::Skew),
+            1 => Some(Weighting::A),
+            2 => Some(Weighting::C),
+            3 => Some(Weighting::Iso226),
             _ => None,
         }
     }
 }
 
+impl FromStr for Weighting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skew" => Ok(Weighting::Skew),
+            "a" => Ok(Weighting::A),
+            "c" => Ok(Weighting::C),
+            "iso226" => Ok(Weighting::Iso226),
+            _ => Err(format!("Invalid weighting '{}' (expected skew, a, c or iso226)", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Settings  {
+    /// Spectra averaged per band, after the envelope follower.
     pub smooth_size: usize,
+    /// Time constants (ms) of the band envelopes: how fast a band rises and falls, whatever the FFT rate.
+    pub attack_ms: usize,
+    pub release_ms: usize,
+    /// Per-band overrides of `attack_ms` and `release_ms`, one per strip; empty to use the global ones.
+    pub band_attack_ms: Vec<f32>,
+    pub band_release_ms: Vec<f32>,
     pub gain: f32,
     pub fps: usize,
     pub color1: Color,
     pub color2: Color,
     pub color3: Color,
     pub fft_size: usize,
+    pub fft_window: FftWindow,
+    pub transform: Transform,
+    /// New mono samples between two FFTs; `fft_size - hop_size` samples are shared with the previous one.
+    pub hop_size: usize,
     pub frequencies:  Vec<f32>,
     pub gains: Vec<f32>,
+    /// Tilt of the band levels towards the treble (> 0) or the bass (< 0), see `dsp::weight`.
     pub skew: f32,
+    /// Curve applied across the bands; `skew` only applies to `Weighting::Skew`.
+    pub weighting: Weighting,
+    /// Loudness (phon) of the ISO 226 contour, 20–90.
+    pub weighting_phon: f32,
+    /// Show band levels in dB: `db_floor` is an empty strip, `db_ceiling` a full one.
+    pub db_scale: bool,
+    pub db_floor: f32,
+    pub db_ceiling: f32,
+    /// Per band, one per strip: bands whose own level is below this (dBFS, before tilt and gains) show nothing.
+    pub noise_gate_db: Vec<f32>,
     pub brightness: f32,
     pub display_mode: DisplayMode,
     pub animation_mode: AnimationMode,
+    /// How long (ms) the peak markers of the *WithMax modes hold before falling.
+    pub peak_hold_ms: usize,
+    /// Fall speed of the peak markers, in strip heights per second.
+    pub peak_decay: f32,
+    /// Flash and photosensitivity limiter of the strobe modes.
+    pub strobe: StrobeSettings,
+    /// Automatic gain control; when enabled it replaces `gain`.
+    pub agc: AgcSettings,
+    /// Gain the AGC applied to the last frame, reported over BLE.
+    pub agc_gain: f32,
+    pub beat_effect: BeatEffect,
+    /// Tempo and beats counted so far, reported over BLE.
+    pub bpm: f32,
+    pub beat_count: u32,
+    /// Hue (degrees) of each pitch class in chroma mode, C first.
+    pub chroma_hues: [f32; PITCH_CLASSES],
+    /// Chord and key last named, reported over BLE.
+    pub chord: Option<Tonality>,
+    pub key: Option<Tonality>,
+    pub color_modulation: ColorModulation,
+    pub modulation_feature: SpectralFeature,
+    /// Features of the last frame, reported over BLE.
+    pub features: SpectralFeatures,
+    /// Left channel on the left half of the strips and right on the other, bass at the centre.
+    pub stereo_split: bool,
+    /// Balance and width of the last frame, reported over BLE.
+    pub stereo: StereoImage,
+    /// Calibration run asked for from the command line or over BLE.
+    pub calibration: CalibrationRequest,
+    /// Phase and result of the last calibration run, reported over BLE.
+    pub calibration_status: CalibrationStatus,
     pub led_buffer: Vec<u8>,
+    pub geometry: LedGeometry,
     pub cached_df: f32,
+    pub sample_rate: u32,
     pub selected_preset: usize,
     pub active_preset: usize,
 }
 
+impl Default for Settings {
+    /// Built-in defaults, before the config file and command line are applied.
+    fn default() -> Self {
+        let mut settings = Settings {
+            smooth_size: DEFAULT_SMOOTH_SIZE,
+            attack_ms: DEFAULT_ATTACK_MS,
+            release_ms: DEFAULT_RELEASE_MS,
+            band_attack_ms: Vec::new(),
+            band_release_ms: Vec::new(),
+            gain: GAIN,
+            fps: FPS,
+            color1: color_from_string("blue"),
+            color2: color_from_string("red"),
+            color3: color_from_string("magenta"),
+            fft_size: FFT_SIZE,
+            fft_window: FftWindow::Hann,
+            transform: Transform::Fft,
+            hop_size: HOP_SIZE,
+            skew: DEFAULT_SKEW,
+            weighting: Weighting::Skew,
+            weighting_phon: DEFAULT_WEIGHTING_PHON,
+            db_scale: false,
+            db_floor: DEFAULT_DB_FLOOR,
+            db_ceiling: DEFAULT_DB_CEILING,
+            noise_gate_db: vec![DEFAULT_NOISE_GATE_DB; LedGeometry::default().strips],
+            brightness: 1.0,
+            display_mode: DisplayMode::Spectrum,
+            frequencies: vec![41.0, 55.0, 65.0, 82.0, 110.0, 146.0, 220.0, 261.0, 329.0, 392.0,
+                              440.0, 523.0, 880.0, 987.0, 2000.0, 3000.0, 4000.0, 5000.0, 6000.0, 7500.0,
+                              9000.0, 13000.0],
+            gains: vec![1.3, 1.2, 1.1, 1.0, 1.0, 1.0, 1.0, 0.85, 0.75, 0.75,
+                        0.75, 0.75, 0.75, 0.75, 1.0, 1.0, 1.0, 1.0, 1.2, 3.0,
+                        4.0, 4.0],
+            animation_mode: AnimationMode::Full,
+            peak_hold_ms: DEFAULT_PEAK_HOLD_MS,
+            peak_decay: DEFAULT_PEAK_DECAY,
+            strobe: StrobeSettings::default(),
+            agc: AgcSettings::default(),
+            agc_gain: GAIN,
+            beat_effect: BeatEffect::Off,
+            bpm: 0.0,
+            beat_count: 0,
+            chroma_hues: circle_of_fifths_hues(),
+            chord: None,
+            key: None,
+            color_modulation: ColorModulation::Off,
+            modulation_feature: SpectralFeature::Centroid,
+            features: SpectralFeatures::default(),
+            stereo_split: false,
+            stereo: StereoImage::default(),
+            calibration: CalibrationRequest::default(),
+            calibration_status: CalibrationStatus::default(),
+            led_buffer: vec![0; LedGeometry::default().frame_len()],
+            geometry: LedGeometry::default(),
+            cached_df: 0.0,
+            sample_rate: SAMPLE_RATE,
+            selected_preset: 0,
+            active_preset: 255,
+        };
+
+        settings.set_fft_size(FFT_SIZE);
+
+        settings
+    }
+}
+
 impl Settings
 {
+    /// `fft_size` must pass `is_valid_fft_size`; the entry points check it.
     pub fn set_fft_size(&mut self, fft_size: usize) {
         self.fft_size = fft_size;
-        self.cached_df = SAMPLE_RATE as f32 / self.fft_size as f32
-    }
-}
-
-pub fn get_config() -> Settings {
-
-    let mut settings = Settings {
-        smooth_size: DEFAULT_SMOOTH_SIZE,
-        gain: GAIN,
-        fps: FPS,
-        color1: color_from_string("blue"),
-        color2: color_from_string("red"),
-        color3: color_from_string("magenta"),
-        fft_size: FFT_SIZE,
-        skew: DEFAULT_SKEW,
-        brightness: 1.0,
-        display_mode: DisplayMode::Spectrum,
-        frequencies: vec![41.0, 55.0, 65.0, 82.0, 110.0, 146.0, 220.0, 261.0, 329.0, 392.0,
-                          440.0, 523.0, 880.0, 987.0, 2000.0, 3000.0, 4000.0, 5000.0, 6000.0, 7500.0,
-                          9000.0, 13000.0],
-        gains: vec![1.3, 1.2, 1.1, 1.0, 1.0, 1.0, 1.0, 0.85, 0.75, 0.75,
-                    0.75, 0.75, 0.75, 0.75, 1.0, 1.0, 1.0, 1.0, 1.2, 3.0,
-                    4.0, 4.0],
-        animation_mode: AnimationMode::Full,
-        led_buffer: vec![0; 3 * 22 * 12 + 1], // Assuming 22 LEDs, 3 bytes per LED + 1 end marker,
-        cached_df: 0.0,
-        selected_preset: 0,
-        active_preset: 255,
-    };
-
-    settings.set_fft_size(FFT_SIZE);
-
-    let mut args = std::env::args();
-    while let Some(arg) = args.next() {
-        match arg.as_str() {
-            "--smooth" | "-s" => {
-                if let Some(val) = args.next() {
-                    settings.smooth_size = val.parse().unwrap_or(DEFAULT_SMOOTH_SIZE);
-                }
-            }
-            "--gain" | "-g" => {
-                if let Some(val) = args.next() {
-                    settings.gain = val.parse().unwrap_or(GAIN);
-                }
-            }
-            "--fps" | "-f" => {
-                if let Some(val) = args.next() {
-                    settings.fps = val.parse().unwrap_or(FPS);
-                }
-            }
-            "--color1" | "-c1" => {
-                if let Some(val) = args.next() {
-                    settings.color1 = color_from_string(&val);
-                }
-            }
-            "--color2" | "-c2" => {
-                if let Some(val) = args.next() {
-                    settings.color2 = color_from_string(&val);
-                }
-            }
-            "--color3" | "-c3" => {
-                if let Some(val) = args.next() {
-                    settings.color3 = color_from_string(&val);
-                }
-            }
-            "--skew" | "-S" => {
-                if let Some(val) = args.next() {
-                    settings.skew = val.parse().unwrap_or(DEFAULT_SKEW);
-                }
-            }
-            "--fft_size" | "-F" => {
-                if let Some(val) = args.next() {
-                    settings.fft_size = val.parse().unwrap_or(FFT_SIZE);
-                    settings.set_fft_size(settings.fft_size);
-                }
-            }
-            "--brightness" | "-b" => {
-                if let Some(val) = args.next() {
-                    settings.brightness = val.parse().unwrap_or(0.0);
-                }
-            }
-            "--display_mode" | "-d" => {
-                if let Some(val) = args.next() {
-                    settings.display_mode = match val.as_str() {
-                        "spectrum" => DisplayMode::Spectrum,
-                        "oscilloscope" => DisplayMode::Oscilloscope,
-                        "color_gradient" => DisplayMode::ColorGradient,
-                        _ => DisplayMode::Spectrum,
-                    };
-                }
-            }
-            "--animation_mode" | "-a" => {
-                if let Some(val) = args.next() {
-                    settings.animation_mode = match val.as_str() {
-                        "full" => AnimationMode::Full,
-                        "full_with_max" => AnimationMode::FullWithMax,
-                        "points" => AnimationMode::Points,
-                        "full_middle" => AnimationMode::FullMiddle,
-                        "full_middle_with_max" => AnimationMode::FullMiddleWithMax,
-                        "points_middle" => AnimationMode::PointsMiddle,
-                        _ => AnimationMode::Full,
-                    };
-                }
-            }
-            _ => {}
-        }
-    }
-
-    settings
-}
-
-pub fn display_usage() {
-    println!("Usage: audio_visualizer [OPTIONS]");
-    println!("Options:");
-    println!("  -s, --smooth <size>          Set the smooth size (default: {})", DEFAULT_SMOOTH_SIZE);
-    println!("  -g, --gain <value>           Set the gain (default: {})", GAIN);
-    println!("  -f, --fps <value>            Set the frames per second (default: {})", FPS);
-    println!("  -c1, --color1 <color>        Set the first color (default: blue)");
-    println!("  -c2, --color2 <color>        Set the second color (default: red)");
-    println!("  -c3, --color3 <color>        Set the third color (default: magenta)");
-    println!("  -S, --skew <value>           Set the skew value (default: {})", DEFAULT_SKEW);
-    println!("  -F, --fft_size <size>        Set the FFT size (default: {})", FFT_SIZE);
-    println!("  -b, --brightness <value>     Set the brightness (default: 1.0)");
-    println!("  -d, --display_mode <mode>    Set the display mode (spectrum, oscilloscope, color_gradient; default: spectrum)");
-    println!("  -a, --animation_mode <mode>  Set the animation mode (full, full_with_max, points, full_middle, full_middle_with_max, points_middle; default: full)");
+        self.cached_df = self.sample_rate as f32 / self.fft_size as f32
+    }
+
+    /// Rate of the audio actually captured; FFT bin widths are derived from it.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.set_fft_size(self.fft_size);
+    }
+
+    /// Set the panel layout; the LED buffer and the band list follow the new size.
+    pub fn set_geometry(&mut self, geometry: LedGeometry) {
+        self.led_buffer = vec![0; geometry.frame_len()];
+        self.geometry = geometry;
+        self.set_bands(&self.frequencies.clone(), &self.gains.clone());
+        self.set_band_envelopes(&self.band_attack_ms.clone(), &self.band_release_ms.clone());
+    }
+
+    /// Replace the band centres with one per strip generated from `layout`; gains are kept.
+    pub fn set_band_layout(&mut self, layout: &BandLayout) -> Result<(), String> {
+        self.frequencies = layout.centres(self.geometry.strips)?;
+        Ok(())
+    }
+
+    /// Set band frequencies and gains, resampled so there is exactly one band per strip.
+    pub fn set_bands(&mut self, frequencies: &[f32], gains: &[f32]) {
+        let strips = self.geometry.strips;
+        self.frequencies = resample_bands(frequencies, strips, true);
+        self.gains = resample_bands(gains, strips, false);
+        self.noise_gate_db = resample_bands(&self.noise_gate_db, strips, false);
+    }
+
+    /// Set the noise gate of each band (dBFS), resampled to one per strip; a single value gates every band alike.
+    pub fn set_noise_gates(&mut self, gates_db: &[f32]) {
+        self.noise_gate_db = resample_bands(gates_db, self.geometry.strips, false);
+    }
+
+    /// Noise gate (dBFS) of band `band`.
+    pub fn band_noise_gate_db(&self, band: usize) -> f32 {
+        self.noise_gate_db.get(band).copied().unwrap_or(DEFAULT_NOISE_GATE_DB)
+    }
+
+    /// Set per-band attack and release times (ms), resampled to one per strip; an empty list keeps the global time.
+    pub fn set_band_envelopes(&mut self, attack_ms: &[f32], release_ms: &[f32]) {
+        let strips = self.geometry.strips;
+        self.band_attack_ms = resample_bands(attack_ms, strips, false);
+        self.band_release_ms = resample_bands(release_ms, strips, false);
+    }
+
+    /// Attack and release times (ms) of band `band`.
+    pub fn band_envelope_ms(&self, band: usize) -> (f32, f32) {
+        (
+            self.band_attack_ms.get(band).copied().unwrap_or(self.attack_ms as f32),
+            self.band_release_ms.get(band).copied().unwrap_or(self.release_ms as f32),
+        )
+    }
+}
+
+/// FFT sizes the analysis supports: powers of two from `MIN_FFT_SIZE` up to the
+/// `MAX_FFT_SIZE` samples the rolling window holds.
+pub fn is_valid_fft_size(fft_size: usize) -> bool {
+    fft_size.is_power_of_two() && (MIN_FFT_SIZE..=MAX_FFT_SIZE).contains(&fft_size)
+}
+
+/// Stretch or shrink a per-band list to `len` entries by linear interpolation
+/// (in the log domain for frequencies, so bands keep their musical spacing).
+pub fn resample_bands(values: &[f32], len: usize, logarithmic: bool) -> Vec<f32> {
+    if values.len() == len || values.is_empty() {
+        return values.to_vec();
+    }
+    if values.len() == 1 || len == 1 {
+        return vec![values[0]; len];
+    }
+
+    let to = |v: f32| if logarithmic { v.max(f32::MIN_POSITIVE).ln() } else { v };
+    let from = |v: f32| if logarithmic { v.exp() } else { v };
+
+    (0..len)
+        .map(|i| {
+            let pos = i as f32 * (values.len() - 1) as f32 / (len - 1) as f32;
+            let lo = pos.floor() as usize;
+            let hi = (lo + 1).min(values.len() - 1);
+            let t = pos - lo as f32;
+            from(to(values[lo]) * (1.0 - t) + to(values[hi]) * t)
+        })
+        .collect()
 }
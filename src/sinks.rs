@@ -0,0 +1,217 @@
+//! LED output sinks.
+//!
+//! The render loop produces one frame per tick (GRB pixels in physical order followed by
+//! `END_MARKER`) and hands it to an `LedSink`, which decides how to ship it.
+//! The sink is selected once at startup from an `--output` spec:
+//!
+//! ```text
+//! serial[:<port>[@<baud>]]   Arduino over USB serial (default: /dev/ttyUSB0 @ 500000)
+//! file:<path>                Raw frames appended to a file
+//! stdout                     One hex line per frame on stdout
+//! udp:<host>:<port>          One datagram per frame
+//! null                       Discard frames
+//! ```
+
+use crate::constants::{BAUD, PORT};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use std::time::Duration;
+
+pub trait LedSink: Send {
+    /// Ship one complete frame to the output.
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SinkSpec {
+    Serial { port: String, baud: u32 },
+    File(String),
+    Stdout,
+    Udp(String),
+    Null,
+}
+
+impl Default for SinkSpec {
+    fn default() -> Self {
+        SinkSpec::Serial { port: PORT.to_string(), baud: BAUD }
+    }
+}
+
+impl FromStr for SinkSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match s.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (s, None),
+        };
+
+        match (kind, arg) {
+            ("serial", None) => Ok(SinkSpec::default()),
+            ("serial", Some(arg)) => match arg.split_once('@') {
+                Some((port, baud)) => {
+                    let baud = baud.parse::<u32>().map_err(|e| format!("Invalid baud rate '{}': {}", baud, e))?;
+                    Ok(SinkSpec::Serial { port: port.to_string(), baud })
+                }
+                None => Ok(SinkSpec::Serial { port: arg.to_string(), baud: BAUD }),
+            },
+            ("file", Some(path)) if !path.is_empty() => Ok(SinkSpec::File(path.to_string())),
+            ("stdout", None) => Ok(SinkSpec::Stdout),
+            ("udp", Some(addr)) if !addr.is_empty() => Ok(SinkSpec::Udp(addr.to_string())),
+            ("null", None) => Ok(SinkSpec::Null),
+            _ => Err(format!("Invalid output '{}' (expected serial[:port[@baud]], file:<path>, stdout, udp:<host>:<port> or null)", s)),
+        }
+    }
+}
+
+impl fmt::Display for SinkSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkSpec::Serial { port, baud } => write!(f, "serial:{}@{}", port, baud),
+            SinkSpec::File(path) => write!(f, "file:{}", path),
+            SinkSpec::Stdout => write!(f, "stdout"),
+            SinkSpec::Udp(addr) => write!(f, "udp:{}", addr),
+            SinkSpec::Null => write!(f, "null"),
+        }
+    }
+}
+
+/// Open the sink described by `spec`.
+pub fn open_sink(spec: &SinkSpec) -> io::Result<Box<dyn LedSink>> {
+    let sink: Box<dyn LedSink> = match spec {
+        SinkSpec::Serial { port, baud } => Box::new(SerialSink::open(port, *baud)?),
+        SinkSpec::File(path) => Box::new(FileSink::create(path)?),
+        SinkSpec::Stdout => Box::new(StdoutSink),
+        SinkSpec::Udp(addr) => Box::new(UdpSink::connect(addr)?),
+        SinkSpec::Null => Box::new(NullSink),
+    };
+    Ok(sink)
+}
+
+// ---------------------------------------------------------------------------
+// Sink implementations
+// ---------------------------------------------------------------------------
+
+/// Arduino running `arduino/device/device.ino` on a serial port.
+pub struct SerialSink {
+    port: Box<dyn serialport::SerialPort>,
+}
+
+impl SerialSink {
+    pub fn open(port: &str, baud: u32) -> io::Result<Self> {
+        let port = serialport::new(port, baud)
+            .timeout(Duration::from_millis(10))
+            .open()?;
+        Ok(SerialSink { port })
+    }
+}
+
+impl LedSink for SerialSink {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.port.write_all(frame)?;
+        self.port.flush()
+    }
+}
+
+/// Raw frames appended back to back, e.g. for replaying or diffing a session.
+pub struct FileSink {
+    file: File,
+}
+
+impl FileSink {
+    pub fn create(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(FileSink { file })
+    }
+}
+
+impl LedSink for FileSink {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.file.write_all(frame)
+    }
+}
+
+/// Human-readable dump: one line of hex per frame.
+pub struct StdoutSink;
+
+impl LedSink for StdoutSink {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        let line = frame.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", line)
+    }
+}
+
+/// One datagram per frame to a fixed peer.
+pub struct UdpSink {
+    socket: UdpSocket,
+}
+
+impl UdpSink {
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        let peer = addr.to_socket_addrs()?.next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("No address for '{}'", addr)))?;
+        // Bind in the peer's family, an IPv4 socket cannot send to [::1]
+        let local: SocketAddr = match peer {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(peer)?;
+        Ok(UdpSink { socket })
+    }
+}
+
+impl LedSink for UdpSink {
+    fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.socket.send(frame).map(|_| ())
+    }
+}
+
+/// Discards every frame.
+pub struct NullSink;
+
+impl LedSink for NullSink {
+    fn write_frame(&mut self, _frame: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_specs_parse() {
+        let cases = [
+            ("serial", Ok(SinkSpec::default())),
+            ("serial:/dev/ttyACM0", Ok(SinkSpec::Serial { port: "/dev/ttyACM0".into(), baud: BAUD })),
+            ("serial:/dev/ttyACM0@115200", Ok(SinkSpec::Serial { port: "/dev/ttyACM0".into(), baud: 115200 })),
+            ("file:/tmp/frames.bin", Ok(SinkSpec::File("/tmp/frames.bin".into()))),
+            ("stdout", Ok(SinkSpec::Stdout)),
+            ("udp:192.168.1.20:7777", Ok(SinkSpec::Udp("192.168.1.20:7777".into()))),
+            ("udp:[::1]:7777", Ok(SinkSpec::Udp("[::1]:7777".into()))),
+            ("null", Ok(SinkSpec::Null)),
+        ];
+        for (spec, expected) in cases {
+            assert_eq!(spec.parse::<SinkSpec>(), expected, "{}", spec);
+        }
+        for spec in ["serial:/dev/ttyACM0@fast", "file:", "udp:", "stdout:x", "null:x", "hdmi", ""] {
+            assert!(spec.parse::<SinkSpec>().is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn udp_sink_sends_to_ipv6_peers() {
+        let Ok(receiver) = UdpSocket::bind("[::1]:0") else {
+            return; // no IPv6 loopback here
+        };
+        let mut sink = UdpSink::connect(&receiver.local_addr().unwrap().to_string()).unwrap();
+        sink.write_frame(&[1, 2, 3]).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(receiver.recv(&mut buf).unwrap(), 3);
+    }
+}
@@ -0,0 +1,90 @@
+//! Stereo image: where the sound sits between the speakers and how wide it is.
+//!
+//! The left, right, mid (L+R)/2 and side (L-R)/2 energies of every buffer are followed with a
+//! short envelope. The balance compares left and right, the width the side with the mid: a mono
+//! mix has no side at all, two unrelated channels have as much side as mid, and a signal out of
+//! phase between the speakers is all side.
+
+use crate::values::Envelope;
+
+/// Time constant (ms) of the energy envelopes.
+const SMOOTH_MS: f32 = 300.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StereoImage {
+    /// -1 all left, 0 centred, 1 all right.
+    pub balance: f32,
+    /// 0 mono, 0.5 unrelated channels, 1 out of phase.
+    pub width: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StereoMeter {
+    pub image: StereoImage,
+    left: Envelope,
+    right: Envelope,
+    mid: Envelope,
+    side: Envelope,
+}
+
+impl StereoMeter {
+    /// Feed interleaved `data` of `channels` channels (the first two are left and right),
+    /// `elapsed` seconds long. Mono input stays centred and narrow.
+    pub fn update(&mut self, data: &[f32], channels: usize, elapsed: f32) {
+        if channels < 2 || data.len() < channels {
+            self.image = StereoImage::default();
+            return;
+        }
+        let frames = (data.len() / channels) as f32;
+        let (mut left, mut right, mut mid, mut side) = (0.0, 0.0, 0.0, 0.0);
+        for frame in data.chunks_exact(channels) {
+            let (l, r) = (frame[0], frame[1]);
+            left += l * l;
+            right += r * r;
+            mid += (l + r) * (l + r) / 4.0;
+            side += (l - r) * (l - r) / 4.0;
+        }
+        let left = self.left.update(left / frames, elapsed, SMOOTH_MS, SMOOTH_MS);
+        let right = self.right.update(right / frames, elapsed, SMOOTH_MS, SMOOTH_MS);
+        let mid = self.mid.update(mid / frames, elapsed, SMOOTH_MS, SMOOTH_MS);
+        let side = self.side.update(side / frames, elapsed, SMOOTH_MS, SMOOTH_MS);
+
+        let (left, right) = (left.sqrt(), right.sqrt());
+        self.image = StereoImage {
+            balance: if left + right > 0.0 { (right - left) / (left + right) } else { 0.0 },
+            width: if mid + side > 0.0 { side / (mid + side) } else { 0.0 },
+        };
+    }
+}
+
+/// Samples of `channel` out of interleaved `data`.
+pub fn deinterleave(data: &[f32], channels: usize, channel: usize) -> Vec<f32> {
+    data.chunks_exact(channels.max(1)).map(|frame| frame[channel]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measure(frame: impl Fn(f32) -> [f32; 2]) -> StereoImage {
+        let mut meter = StereoMeter::default();
+        for buffer in 0..100 {
+            let data: Vec<f32> = (0..512).flat_map(|n| frame(((buffer * 512 + n) as f32 * 0.05).sin())).collect();
+            meter.update(&data, 2, 0.05);
+        }
+        meter.image
+    }
+
+    #[test]
+    fn balance_and_width_follow_the_mix() {
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+        let centred = measure(|s| [s, s]);
+        assert!(close(centred.balance, 0.0) && close(centred.width, 0.0), "{:?}", centred);
+        let left = measure(|s| [s, 0.0]);
+        assert!(close(left.balance, -1.0) && close(left.width, 0.5), "{:?}", left);
+        let right = measure(|s| [0.25 * s, 0.75 * s]);
+        assert!(close(right.balance, 0.5), "{:?}", right);
+        let out_of_phase = measure(|s| [s, -s]);
+        assert!(close(out_of_phase.width, 1.0), "{:?}", out_of_phase);
+    }
+}
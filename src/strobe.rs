@@ -0,0 +1,119 @@
+//! Strobe overlay of `AnimationMode::Strobe` and `AnimationMode::StrobeStrips`.
+//!
+//! Every kick found by the beat tracker starts a flash in `color3` that fades out over
+//! `decay_ms`, drawn over the `Full` spectrum: across the whole matrix, or on each strip as
+//! far as that strip is lit. Flashing light can trigger seizures, so a limiter, on unless
+//! turned off, lets at most `max_rate` flashes start per second (3 is the usual threshold for
+//! general flashes) and caps the step in luminance any LED takes at `max_delta`.
+
+use crate::color::Color;
+use crate::constants::{DEFAULT_STROBE_DECAY_MS, DEFAULT_STROBE_MAX_DELTA, DEFAULT_STROBE_MAX_RATE};
+use std::time::Instant;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrobeSettings {
+    /// How long (ms) a flash takes to fade out.
+    pub decay_ms: usize,
+    /// Photosensitivity limiter.
+    pub limiter: bool,
+    /// Flashes per second the limiter lets through; kicks coming faster are skipped.
+    pub max_rate: f32,
+    /// Largest change in relative luminance (0..1) the limiter lets a flash cause on an LED.
+    pub max_delta: f32,
+}
+
+impl Default for StrobeSettings {
+    fn default() -> Self {
+        StrobeSettings {
+            decay_ms: DEFAULT_STROBE_DECAY_MS,
+            limiter: true,
+            max_rate: DEFAULT_STROBE_MAX_RATE,
+            max_delta: DEFAULT_STROBE_MAX_DELTA,
+        }
+    }
+}
+
+/// Flash state, kept by the render loop between frames.
+#[derive(Debug, Clone, Default)]
+pub struct Strobe {
+    /// Kick count of the last analysis seen.
+    kicks: u32,
+    /// When the current flash started.
+    started: Option<Instant>,
+}
+
+impl Strobe {
+    /// Flash level (0..1) at `now`, given the kick count of the latest analysis.
+    pub fn update(&mut self, kicks: u32, now: Instant, settings: &StrobeSettings) -> f32 {
+        if kicks != self.kicks {
+            self.kicks = kicks;
+            let allowed = match self.started {
+                Some(started) if settings.limiter => now.duration_since(started).as_secs_f32() * settings.max_rate >= 1.0,
+                _ => true,
+            };
+            if allowed {
+                self.started = Some(now);
+            }
+        }
+        match self.started {
+            Some(started) if settings.decay_ms > 0 => {
+                (1.0 - now.duration_since(started).as_secs_f32() * 1000.0 / settings.decay_ms as f32).max(0.0)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// `color` mixed towards `flash` by `amount`, or less if the limiter would see too large a step.
+pub fn flash_color(color: &Color, flash: &Color, amount: f32, settings: &StrobeSettings) -> Color {
+    let mut amount = amount.clamp(0.0, 1.0);
+    if settings.limiter {
+        let delta = (luminance(flash) - luminance(color)).abs();
+        if delta * amount > settings.max_delta {
+            amount = settings.max_delta / delta;
+        }
+    }
+    color.mix(flash, amount)
+}
+
+/// Relative luminance (0..1) with the Rec. 709 weights. LED PWM is already linear in light,
+/// so there is no gamma to undo.
+fn luminance(color: &Color) -> f32 {
+    (0.2126 * color.r as f32 + 0.7152 * color.g as f32 + 0.0722 * color.b as f32) / 255.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{BLACK, WHITE};
+    use std::time::Duration;
+
+    #[test]
+    fn limiter_skips_kicks_that_come_too_fast() {
+        let settings = StrobeSettings { decay_ms: 100, limiter: true, max_rate: 3.0, max_delta: 1.0 };
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut strobe = Strobe::default();
+
+        assert_eq!(strobe.update(0, at(0), &settings), 0.0);
+        assert_eq!(strobe.update(1, at(0), &settings), 1.0);
+        assert!((strobe.update(1, at(50), &settings) - 0.5).abs() < 1e-3);
+        // A kick 200 ms later is within the same third of a second: no new flash
+        assert_eq!(strobe.update(2, at(200), &settings), 0.0);
+        assert_eq!(strobe.update(3, at(340), &settings), 1.0);
+
+        let unlimited = StrobeSettings { limiter: false, ..settings };
+        assert_eq!(strobe.update(4, at(400), &unlimited), 1.0);
+    }
+
+    #[test]
+    fn limiter_caps_the_luminance_step() {
+        let settings = StrobeSettings { max_delta: 0.4, ..StrobeSettings::default() };
+        let flashed = flash_color(&BLACK, &WHITE, 1.0, &settings);
+        assert!((luminance(&flashed) - 0.4).abs() < 0.01, "{:?}", flashed);
+        assert_eq!(flash_color(&BLACK, &WHITE, 0.2, &settings).to_rgb_slice(), BLACK.mix(&WHITE, 0.2).to_rgb_slice());
+
+        let unlimited = StrobeSettings { limiter: false, ..settings };
+        assert_eq!(flash_color(&BLACK, &WHITE, 1.0, &unlimited).to_rgb_slice(), WHITE.to_rgb_slice());
+    }
+}
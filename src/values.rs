@@ -1,20 +1,73 @@
-﻿use std::sync::{Arc, Mutex};
-use crate::settings::{Settings};
+﻿use std::time::{Duration, Instant};
+use crate::agc::AutoGain;
+use crate::audio::AudioFormat;
+use crate::beat::{Beat, BeatTracker};
+use crate::calibration::{CalibrationStatus, Calibrator};
+use crate::chroma::{Chroma, ChromaTracker};
+use crate::constant_q::ConstantQ;
+use crate::constants::{MAX_FFT_SIZE, MAX_SMOOTH_SIZE};
+use crate::dsp::window_coefficients;
+use crate::features::SpectralFeatures;
+use crate::settings::{FftWindow, Settings};
+use crate::stereo::{StereoImage, StereoMeter};
 
 #[derive(Debug, Clone)]
 pub struct StateValues
 {
     pub frequencies: FrequenciesValues,
+    /// Attack/release follower of each band, feeding `frequencies`.
+    pub envelopes: Vec<Envelope>,
     pub samples_window: SamplesWindow,
+    pub format: AudioFormat,
+    /// Mono samples added since the last FFT.
+    pub hop_pending: usize,
+    /// Coefficients of `settings.fft_window` for `settings.fft_size`, rebuilt when either changes.
+    pub window: Vec<f32>,
+    window_kind: FftWindow,
+    /// Windows of the per-band FFT sizes of `Transform::ConstantQ`.
+    pub constant_q: ConstantQ,
+    pub auto_gain: AutoGain,
+    /// Gain chosen by the AGC after the last spectrum.
+    pub agc_gain: f32,
+    pub beat: BeatTracker,
+    pub chroma: ChromaTracker,
+    /// Smoothed features of the latest spectra.
+    pub features: SpectralFeatures,
+    pub stereo: StereoMeter,
+    /// Left and right analysed apart, only while the split stereo display is on.
+    pub channels: Vec<ChannelValues>,
+    pub calibrator: Calibrator,
+}
+
+/// One channel of the split stereo display: its own samples and a band per strip of its half.
+#[derive(Debug, Clone)]
+pub struct ChannelValues {
+    pub samples_window: SamplesWindow,
+    pub frequencies: FrequenciesValues,
+    pub envelopes: Vec<Envelope>,
 }
 
 impl StateValues {
 
-    pub fn new(settings: Arc<Mutex<Settings>>) -> Self {
+    pub fn new(settings: &Settings, format: AudioFormat) -> Self {
 
         let mut result = StateValues {
             frequencies : Vec::new(),
-            samples_window: SamplesWindow::new(1024*8),
+            envelopes: Vec::new(),
+            samples_window: SamplesWindow::new(MAX_FFT_SIZE),
+            format,
+            hop_pending: 0,
+            window: Vec::new(),
+            window_kind: settings.fft_window,
+            constant_q: ConstantQ::default(),
+            auto_gain: AutoGain::default(),
+            agc_gain: settings.agc.max_gain,
+            beat: BeatTracker::default(),
+            chroma: ChromaTracker::default(),
+            features: SpectralFeatures::default(),
+            stereo: StereoMeter::default(),
+            channels: Vec::new(),
+            calibrator: Calibrator::default(),
         };
 
         result.update_settings(settings);
@@ -22,36 +75,116 @@ impl StateValues {
         result
     }
 
-    pub fn update_settings(&mut self, settings: Arc<Mutex<Settings>>) {
-        let nb_frequencies = settings.lock().unwrap().frequencies.len();
-        self.frequencies.reserve(nb_frequencies);
-        for _ in 0..nb_frequencies {
-            self.frequencies.push(SamplesWindow::new(100));
+    /// Follow the band count and FFT window; called on every buffer since BLE can change them.
+    pub fn update_settings(&mut self, settings: &Settings) {
+        let nb_frequencies = settings.frequencies.len();
+        if self.frequencies.len() != nb_frequencies {
+            self.frequencies.resize_with(nb_frequencies, || SamplesWindow::new(MAX_SMOOTH_SIZE));
+        }
+        self.envelopes.resize(nb_frequencies, Envelope::default());
+        if self.window.len() != settings.fft_size || self.window_kind != settings.fft_window {
+            self.window = window_coefficients(settings.fft_window, settings.fft_size);
+            self.window_kind = settings.fft_window;
+        }
+
+        let split_channels = if settings.stereo_split && self.format.channels >= 2 && settings.geometry.strips >= 2 { 2 } else { 0 };
+        self.channels.resize_with(split_channels, || ChannelValues {
+            samples_window: SamplesWindow::new(MAX_FFT_SIZE),
+            frequencies: Vec::new(),
+            envelopes: Vec::new(),
+        });
+        let half = settings.geometry.strips / 2;
+        for channel in &mut self.channels {
+            if channel.frequencies.len() != half {
+                channel.frequencies.resize_with(half, || SamplesWindow::new(MAX_SMOOTH_SIZE));
+            }
+            channel.envelopes.resize(half, Envelope::default());
+        }
+    }
+
+    /// Snapshot of the smoothed bands (and, for the oscilloscope, the newest `samples`) for the renderer.
+    pub fn analysis(&self, settings: &Settings, samples: usize) -> Analysis {
+        Analysis {
+            levels: self.frequencies.iter().map(|window| window.average(settings.smooth_size)).collect(),
+            maxima: self.frequencies.iter().map(|window| window.max(settings.smooth_size)).collect(),
+            samples: self.samples_window.newest_to_vec(samples),
+            agc_gain: self.agc_gain,
+            beat: self.beat.beat,
+            chroma: self.chroma.chroma,
+            features: self.features,
+            channel_levels: self.channels.iter()
+                .map(|channel| channel.frequencies.iter().map(|window| window.average(settings.smooth_size)).collect())
+                .collect(),
+            channel_maxima: self.channels.iter()
+                .map(|channel| channel.frequencies.iter().map(|window| window.max(settings.smooth_size)).collect())
+                .collect(),
+            stereo: self.stereo.image,
+            calibration: self.calibrator.status.clone(),
         }
     }
 }
 
+/// What the DSP thread hands to the render loop after each buffer.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    /// Level of each band averaged over the last `smooth_size` spectra, before gains.
+    pub levels: Vec<f32>,
+    /// Highest level of each band over the same spectra.
+    pub maxima: Vec<f32>,
+    /// Newest mono samples, oldest first; only filled in oscilloscope mode.
+    pub samples: Vec<f32>,
+    /// Gain to use instead of `Settings::gain` when the AGC is enabled.
+    pub agc_gain: f32,
+    pub beat: Beat,
+    pub chroma: Chroma,
+    pub features: SpectralFeatures,
+    /// `levels` and `maxima` of the left then the right channel, with the split stereo display.
+    pub channel_levels: Vec<Vec<f32>>,
+    pub channel_maxima: Vec<Vec<f32>>,
+    pub stereo: StereoImage,
+    pub calibration: CalibrationStatus,
+}
+
+/// Fixed-capacity ring of the newest samples.
+///
+/// Adding a sample is O(1) once full: it overwrites the oldest slot instead of shifting.
+/// A running prefix sum gives the average of any newest-N span in O(1) as well.
 #[derive(Debug, Clone)]
 pub struct SamplesWindow
 {
-    pub samples: Arc<Mutex<Vec<f32>>>,
+    samples: Vec<f32>,
+    /// Slot the next sample goes into, i.e. one past the newest.
+    head: usize,
+    len: usize,
+    /// `prefix[k % (max_size + 1)]` is the sum of the first `k` samples ever added.
+    /// One extra slot keeps the sum just before the oldest retained sample.
+    prefix: Vec<f64>,
+    count: usize,
     max_size: usize,
 }
 
 impl SamplesWindow {
     pub fn new(max_size: usize) -> Self {
+        let max_size = max_size.max(1);
         SamplesWindow {
-            samples: Arc::new(Mutex::new(Vec::with_capacity(max_size))),
+            samples: vec![0.0; max_size],
+            head: 0,
+            len: 0,
+            prefix: vec![0.0; max_size + 1],
+            count: 0,
             max_size,
         }
     }
 
     pub fn add_sample(&mut self, sample: f32) {
-        let mut samples = self.samples.lock().unwrap();
-        if samples.len() >= self.max_size {
-            samples.remove(0);
-        }
-        samples.push(sample);
+        self.samples[self.head] = sample;
+        self.head = (self.head + 1) % self.max_size;
+        self.len = (self.len + 1).min(self.max_size);
+
+        let slots = self.prefix.len();
+        let total = self.prefix[self.count % slots] + sample as f64;
+        self.count += 1;
+        self.prefix[self.count % slots] = total;
     }
 
     pub fn add_samples(&mut self, samples: &[f32]) {
@@ -60,25 +193,171 @@ impl SamplesWindow {
         }
     }
 
-    pub fn average(&self, size: usize) -> f32 {
-        let samples = self.samples.lock().unwrap();
-        if samples.is_empty() {
-            0.0
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// The newest `size` samples (or all of them), oldest first, as the two halves of the ring.
+    pub fn newest(&self, size: usize) -> (&[f32], &[f32]) {
+        let size = size.min(self.len);
+        let start = (self.head + self.max_size - size) % self.max_size;
+        if start + size <= self.max_size {
+            (&self.samples[start..start + size], &[])
         } else {
-            // take size items from the end of the vector
-            let sum: f32 = samples.iter().rev().take(size).rev().sum();
-            sum / size as f32
+            (&self.samples[start..], &self.samples[..self.head])
         }
     }
 
+    /// The newest `size` samples (or all of them), oldest first, in one buffer.
+    pub fn newest_to_vec(&self, size: usize) -> Vec<f32> {
+        let (older, newer) = self.newest(size);
+        [older, newer].concat()
+    }
+
+    /// Mean of the newest `size` samples (fewer if the window holds fewer).
+    pub fn average(&self, size: usize) -> f32 {
+        let size = size.min(self.len);
+        if size == 0 {
+            return 0.0;
+        }
+        let slots = self.prefix.len();
+        let sum = self.prefix[self.count % slots] - self.prefix[(self.count - size) % slots];
+        (sum / size as f64) as f32
+    }
+
+    /// Largest of the newest `size` samples.
     pub fn max(&self, size: usize) -> f32 {
-        let samples = self.samples.lock().unwrap();
-        if samples.is_empty() {
-            0.0
+        let (older, newer) = self.newest(size);
+        older.iter().chain(newer).copied().reduce(f32::max).unwrap_or(0.0)
+    }
+}
+
+pub type FrequenciesValues = Vec<SamplesWindow>;
+
+/// Falling peak marker of one band, in strip heights (1.0 = top of the strip).
+#[derive(Debug, Clone, Copy)]
+pub struct PeakHold {
+    peak: f32,
+    since: Instant,
+}
+
+impl Default for PeakHold {
+    fn default() -> Self {
+        PeakHold { peak: 0.0, since: Instant::now() }
+    }
+}
+
+impl PeakHold {
+    /// Height of the marker at `now`: the last peak, held for `hold`, then falling by `decay` per second.
+    pub fn level(&self, now: Instant, hold: Duration, decay: f32) -> f32 {
+        let falling = now.saturating_duration_since(self.since).saturating_sub(hold).as_secs_f32();
+        (self.peak - decay * falling).max(0.0)
+    }
+
+    /// Feed the latest level; a level at or above the marker pushes it up and restarts the hold.
+    pub fn update(&mut self, level: f32, now: Instant, hold: Duration, decay: f32) -> f32 {
+        let current = self.level(now, hold, decay);
+        if level >= current {
+            self.peak = level;
+            self.since = now;
+            level
         } else {
-            *samples.iter().take(size).max_by(|a, b| a.partial_cmp(b).unwrap()).unwrap()
+            current
         }
     }
 }
 
-pub type FrequenciesValues = Vec<SamplesWindow>;
\ No newline at end of file
+/// One-pole envelope follower with separate time constants for rising and falling input.
+/// Times are in milliseconds of audio, so the response does not depend on how often it is fed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Envelope {
+    value: f32,
+}
+
+impl Envelope {
+    /// Move towards `input`, `elapsed` seconds after the previous update; a time of 0 follows instantly.
+    pub fn update(&mut self, input: f32, elapsed: f32, attack_ms: f32, release_ms: f32) -> f32 {
+        let time_constant_ms = if input > self.value { attack_ms } else { release_ms };
+        let smoothing = if time_constant_ms <= 0.0 {
+            1.0
+        } else {
+            1.0 - (-elapsed * 1000.0 / time_constant_ms).exp()
+        };
+        self.value += (input - self.value) * smoothing;
+        self.value
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window_with(max_size: usize, samples: &[f32]) -> SamplesWindow {
+        let mut window = SamplesWindow::new(max_size);
+        window.add_samples(samples);
+        window
+    }
+
+    #[test]
+    fn keeps_the_newest_samples_in_order_after_wrapping() {
+        let window = window_with(4, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(window.len(), 4);
+        assert_eq!(window.newest_to_vec(10), vec![3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(window.newest_to_vec(3), vec![4.0, 5.0, 6.0]);
+        assert_eq!(window.newest(3), (&[4.0][..], &[5.0, 6.0][..]));
+    }
+
+    #[test]
+    fn average_covers_only_the_newest_samples() {
+        let window = window_with(4, &[100.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(window.average(2), 3.5);
+        assert_eq!(window.average(4), 2.5);
+        // Asking for more than the window holds averages what is there.
+        assert_eq!(window.average(10), 2.5);
+        assert_eq!(window_with(4, &[2.0]).average(3), 2.0);
+        assert_eq!(SamplesWindow::new(4).average(3), 0.0);
+    }
+
+    #[test]
+    fn max_covers_only_the_newest_samples() {
+        let window = window_with(5, &[9.0, 1.0, 7.0, 2.0, 3.0, 0.5]);
+        assert_eq!(window.max(2), 3.0);
+        assert_eq!(window.max(4), 7.0);
+        assert_eq!(window.max(100), 7.0);
+        assert_eq!(window_with(3, &[-2.0, -1.0]).max(2), -1.0);
+        assert_eq!(SamplesWindow::new(3).max(2), 0.0);
+    }
+
+    #[test]
+    fn running_sum_stays_exact_over_many_wraps() {
+        let mut window = SamplesWindow::new(8);
+        for i in 0..100_000 {
+            window.add_sample((i % 10) as f32 * 0.1);
+        }
+        let expected = window.newest_to_vec(8).iter().sum::<f32>() / 8.0;
+        assert!((window.average(8) - expected).abs() < 1e-5);
+    }
+
+    #[test]
+    fn envelope_timing_does_not_depend_on_the_update_rate() {
+        // 100 ms of a step at 100 and at 1000 updates per second, attack 100 ms, release 1 s
+        let follow = |input: f32, updates: usize, envelope: &mut Envelope| {
+            for _ in 0..updates {
+                envelope.update(input, 0.1 / updates as f32, 100.0, 1000.0);
+            }
+            envelope.value()
+        };
+        let (mut slow, mut fast) = (Envelope::default(), Envelope::default());
+        let rise = follow(1.0, 10, &mut slow);
+        assert!((rise - follow(1.0, 100, &mut fast)).abs() < 1e-4);
+        assert!((rise - (1.0 - (-1f32).exp())).abs() < 1e-4);
+
+        // Falling follows the release: 100 ms is a tenth of its time constant
+        let fall = follow(0.0, 10, &mut slow);
+        assert!((fall - rise * (-0.1f32).exp()).abs() < 1e-4);
+        assert_eq!(Envelope::default().update(0.5, 0.01, 0.0, 0.0), 0.5);
+    }
+}
@@ -0,0 +1,98 @@
+//! Perceptual weighting curves, an alternative to the skew power law of `dsp::weight`.
+//!
+//! Each curve gives the ear's relative sensitivity in dB, 0 dB at 1 kHz: band levels are
+//! multiplied by it so a band lights up as loud as it sounds rather than as loud as it measures.
+
+/// IEC 61672 A-weighting: the ear at low levels, strongly cutting the bass.
+pub fn a_weighting_db(freq_hz: f32) -> f32 {
+    let f2 = (freq_hz as f64).powi(2);
+    let response = 12194f64.powi(2) * f2 * f2
+        / ((f2 + 20.6f64.powi(2))
+            * ((f2 + 107.7f64.powi(2)) * (f2 + 737.9f64.powi(2))).sqrt()
+            * (f2 + 12194f64.powi(2)));
+    (20.0 * response.log10() + 2.0) as f32
+}
+
+/// IEC 61672 C-weighting: the ear at high levels, nearly flat between 50 Hz and 5 kHz.
+pub fn c_weighting_db(freq_hz: f32) -> f32 {
+    let f2 = (freq_hz as f64).powi(2);
+    let response = 12194f64.powi(2) * f2 / ((f2 + 20.6f64.powi(2)) * (f2 + 12194f64.powi(2)));
+    (20.0 * response.log10() + 0.06) as f32
+}
+
+// ISO 226:2003 table: frequency (Hz), exponent of loudness perception αf,
+// magnitude of the linear transfer function LU (dB) and hearing threshold TF (dB).
+const ISO226_HZ: [f32; 29] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0, 500.0,
+    630.0, 800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0, 4000.0, 5000.0, 6300.0, 8000.0, 10000.0, 12500.0,
+];
+const ISO226_AF: [f32; 29] = [
+    0.532, 0.506, 0.480, 0.455, 0.432, 0.409, 0.387, 0.367, 0.349, 0.330, 0.315, 0.301, 0.288, 0.276, 0.267,
+    0.259, 0.253, 0.250, 0.246, 0.244, 0.243, 0.243, 0.243, 0.242, 0.242, 0.245, 0.254, 0.271, 0.301,
+];
+const ISO226_LU: [f32; 29] = [
+    -31.6, -27.2, -23.0, -19.1, -15.9, -13.0, -10.3, -8.1, -6.2, -4.5, -3.1, -2.0, -1.1, -0.4, 0.0,
+    0.3, 0.5, 0.0, -2.7, -4.1, -1.0, 1.7, 2.5, 1.2, -2.1, -7.1, -11.2, -10.7, -3.1,
+];
+const ISO226_TF: [f32; 29] = [
+    78.5, 68.7, 59.5, 51.1, 44.0, 37.5, 31.5, 26.5, 22.1, 17.9, 14.4, 11.4, 8.6, 6.2, 4.4,
+    3.0, 2.2, 2.4, 3.5, 1.7, -1.3, -4.2, -6.0, -5.4, -1.5, 6.0, 12.6, 13.9, 12.3,
+];
+
+/// Sound pressure level (dB) of the ISO 226 contour of loudness `phon` at table entry `i`.
+fn iso226_spl(i: usize, phon: f32) -> f32 {
+    let (af, lu, tf) = (ISO226_AF[i], ISO226_LU[i], ISO226_TF[i]);
+    let a = 4.47e-3 * (10f32.powf(0.025 * phon) - 1.15) + (0.4 * 10f32.powf((tf + lu) / 10.0 - 9.0)).powf(af);
+    10.0 / af * a.log10() - lu + 94.0
+}
+
+/// ISO 226 equal-loudness weighting at `phon` (20–90): the inverse of the contour, so a tone
+/// needing 10 dB more than at 1 kHz to sound as loud is weighted -10 dB. The standard covers
+/// 20 Hz–12.5 kHz; beyond that the nearest end of the table is used.
+pub fn iso226_weighting_db(freq_hz: f32, phon: f32) -> f32 {
+    let last = ISO226_HZ.len() - 1;
+    let spl = if freq_hz <= ISO226_HZ[0] {
+        iso226_spl(0, phon)
+    } else if freq_hz >= ISO226_HZ[last] {
+        iso226_spl(last, phon)
+    } else {
+        // Straight lines between table points on a log-frequency axis
+        let upper = ISO226_HZ.iter().position(|&hz| hz >= freq_hz).unwrap_or(last);
+        let lower = upper - 1;
+        let t = (freq_hz / ISO226_HZ[lower]).ln() / (ISO226_HZ[upper] / ISO226_HZ[lower]).ln();
+        iso226_spl(lower, phon) * (1.0 - t) + iso226_spl(upper, phon) * t
+    };
+    phon - spl
+}
+
+/// Linear gain of a weighting in dB.
+pub fn db_to_gain(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_are_zero_db_at_1_khz() {
+        assert!(a_weighting_db(1000.0).abs() < 0.1);
+        assert!(c_weighting_db(1000.0).abs() < 0.1);
+        for phon in [20.0, 40.0, 60.0, 90.0] {
+            assert!(iso226_weighting_db(1000.0, phon).abs() < 0.1, "{} phon", phon);
+        }
+    }
+
+    #[test]
+    fn curves_match_published_values() {
+        // IEC 61672 tables: A(100 Hz) = -19.1 dB, A(10 kHz) = -2.5 dB, C(31.5 Hz) = -3.0 dB
+        assert!((a_weighting_db(100.0) + 19.1).abs() < 0.1);
+        assert!((a_weighting_db(10000.0) + 2.5).abs() < 0.1);
+        assert!((c_weighting_db(31.5) + 3.0).abs() < 0.1);
+        // ISO 226: at 40 phon a 100 Hz tone needs 64.4 dB SPL; the louder, the flatter the contour
+        let quiet = iso226_weighting_db(100.0, 40.0);
+        let loud = iso226_weighting_db(100.0, 80.0);
+        assert!((quiet + 24.4).abs() < 0.5, "{}", quiet);
+        assert!(loud > quiet, "{} vs {}", loud, quiet);
+    }
+}